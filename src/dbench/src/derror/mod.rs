@@ -0,0 +1 @@
+pub mod option_error;
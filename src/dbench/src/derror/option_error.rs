@@ -0,0 +1,20 @@
+use thiserror::Error;
+
+#[derive(Debug, Error, PartialEq)]
+pub enum OptionError {
+    #[error("Unknown option `{0}`")]
+    Unknown(String),
+    #[error("Option `{0}` needs a value")]
+    Missing(String),
+    #[error("`{value}` is not valid for `{option}`")]
+    Invalid { option: String, value: String },
+}
+
+impl OptionError {
+    pub fn invalid(option: &str, value: &str) -> Self {
+        Self::Invalid {
+            option: option.to_string(),
+            value: value.to_string(),
+        }
+    }
+}
@@ -0,0 +1,65 @@
+use crate::options::{Options, USAGE};
+use crate::workload::run;
+use dserver::{EvictionPolicy, Server};
+use std::env;
+use std::process::exit;
+
+mod derror;
+mod options;
+mod report;
+mod workload;
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    if args.iter().any(|a| a == "--help") {
+        println!("{}", USAGE);
+        return;
+    }
+    let options = Options::parse(&args).unwrap_or_else(|e| {
+        eprintln!("{}\n{}", e, USAGE);
+        exit(1);
+    });
+
+    // Without an address the run is self-contained. Full packs evict so the key space can be larger than them.
+    let server = match &options.address {
+        Some(_) => None,
+        None => match Server::new("127.0.0.1", 0)
+            .packs(options.packs)
+            .eviction(EvictionPolicy::Lru)
+            .workers(options.connections)
+            .max_connections(options.connections)
+            .start()
+        {
+            Ok(s) => Some(s),
+            Err(e) => {
+                eprintln!("{}", e);
+                exit(1);
+            }
+        },
+    };
+    let address = match (&options.address, &server) {
+        (Some(a), _) => a.clone(),
+        (None, Some(s)) => s.local_addr().to_string(),
+        (None, None) => unreachable!(),
+    };
+
+    println!(
+        "{} connections x {} requests to {}, mix {}:{}:{}, {} keys, {} values",
+        options.connections,
+        options.requests,
+        address,
+        options.mix.add,
+        options.mix.get,
+        options.mix.del,
+        options.keys,
+        options.tag
+    );
+    let report = run(&address, &options).unwrap_or_else(|e| {
+        eprintln!("{}\n{}", e, USAGE);
+        exit(1);
+    });
+    println!("{}", report);
+    if let Some(s) = server {
+        s.stop();
+    }
+}
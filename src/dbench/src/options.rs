@@ -0,0 +1,176 @@
+use crate::derror::option_error::OptionError;
+use dserver::constant::constant::{ADVANCED_PACK_COUNT, MAX_VALUE_LEN};
+
+/// Usage of the tool.
+pub const USAGE: &str = "\
+dbench [options]
+  --address <host:port>   server to load, an in-process server is started if it is not given
+  --packs <n>             pack count of the in-process server (16)
+  --connections <n>       concurrent connections (8)
+  --requests <n>          requests of every connection (10000)
+  --mix <add:get:del>     weights of the commands (20:75:5)
+  --keys <n>              size of the key space (1000)
+  --type <tag>            value type, i8, i16, i32, f32, f64, s or l (s)
+  --size <n>              length of the text values, at most 64 (16)";
+
+/// Weights of the commands in the workload.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Mix {
+    pub add: u32,
+    pub get: u32,
+    pub del: u32,
+}
+
+impl Mix {
+    pub fn total(&self) -> u32 {
+        self.add + self.get + self.del
+    }
+}
+
+/// Settings of a benchmark run.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Options {
+    pub address: Option<String>,
+    pub packs: u32,
+    pub connections: usize,
+    pub requests: usize,
+    pub mix: Mix,
+    pub keys: u32,
+    pub tag: String,
+    pub size: usize,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            address: None,
+            packs: ADVANCED_PACK_COUNT,
+            connections: 8,
+            requests: 10_000,
+            mix: Mix {
+                add: 20,
+                get: 75,
+                del: 5,
+            },
+            keys: 1000,
+            tag: "s".to_string(),
+            size: 16,
+        }
+    }
+}
+
+impl Options {
+    /// Reads the options from the arguments, the program name is not included.
+    pub fn parse(args: &[String]) -> Result<Self, OptionError> {
+        let mut options = Options::default();
+        let mut args = args.iter();
+        while let Some(option) = args.next() {
+            let value = args
+                .next()
+                .ok_or_else(|| OptionError::Missing(option.clone()))?;
+            match option.as_str() {
+                "--address" => options.address = Some(value.clone()),
+                "--packs" => options.packs = positive(option, value)?,
+                "--connections" => options.connections = positive(option, value)?,
+                "--requests" => options.requests = positive(option, value)?,
+                "--mix" => options.mix = mix(value)?,
+                "--keys" => options.keys = positive(option, value)?,
+                "--type" => match value.as_str() {
+                    "i8" | "i16" | "i32" | "f32" | "f64" | "s" | "l" => options.tag = value.clone(),
+                    _ => return Err(OptionError::invalid(option, value)),
+                },
+                "--size" => match positive(option, value)? {
+                    size if size <= MAX_VALUE_LEN => options.size = size,
+                    _ => return Err(OptionError::invalid(option, value)),
+                },
+                _ => return Err(OptionError::Unknown(option.clone())),
+            }
+        }
+        Ok(options)
+    }
+}
+
+fn positive<T>(option: &str, value: &str) -> Result<T, OptionError>
+where
+    T: std::str::FromStr + Default + PartialEq,
+{
+    match value.parse::<T>() {
+        Ok(n) if n != T::default() => Ok(n),
+        _ => Err(OptionError::invalid(option, value)),
+    }
+}
+
+/// Parses the weights like `20:75:5`.
+fn mix(value: &str) -> Result<Mix, OptionError> {
+    let invalid = || OptionError::invalid("--mix", value);
+    let weights = value
+        .split(':')
+        .map(|w| w.parse::<u32>().map_err(|_| invalid()))
+        .collect::<Result<Vec<u32>, OptionError>>()?;
+    match weights.as_slice() {
+        [add, get, del] if add + get + del > 0 => Ok(Mix {
+            add: *add,
+            get: *get,
+            del: *del,
+        }),
+        _ => Err(invalid()),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn args(line: &str) -> Vec<String> {
+        line.split_whitespace().map(|a| a.to_string()).collect()
+    }
+
+    #[test]
+    fn should_options_parsed_over_defaults() {
+        let options = Options::parse(&args("--connections 4 --mix 50:50:0 --type i32")).unwrap();
+        assert_eq!(options.connections, 4);
+        assert_eq!(
+            options.mix,
+            Mix {
+                add: 50,
+                get: 50,
+                del: 0
+            }
+        );
+        assert_eq!(options.tag, "i32");
+        assert_eq!(options.requests, Options::default().requests);
+        assert_eq!(options.address, None);
+    }
+
+    #[test]
+    fn should_wrong_options_rejected() {
+        assert_eq!(
+            Options::parse(&args("--threads 4")),
+            Err(OptionError::Unknown("--threads".to_string()))
+        );
+        assert_eq!(
+            Options::parse(&args("--keys")),
+            Err(OptionError::Missing("--keys".to_string()))
+        );
+        assert_eq!(
+            Options::parse(&args("--connections 0")),
+            Err(OptionError::invalid("--connections", "0"))
+        );
+        assert_eq!(
+            Options::parse(&args("--mix 0:0:0")),
+            Err(OptionError::invalid("--mix", "0:0:0"))
+        );
+        assert_eq!(
+            Options::parse(&args("--size 100")),
+            Err(OptionError::invalid("--size", "100"))
+        );
+        assert_eq!(
+            Options::parse(&args("--size 64")).unwrap().size,
+            MAX_VALUE_LEN
+        );
+        assert_eq!(
+            Options::parse(&args("--type u64")),
+            Err(OptionError::invalid("--type", "u64"))
+        );
+    }
+}
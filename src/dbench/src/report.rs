@@ -0,0 +1,82 @@
+use std::fmt::{Display, Formatter};
+use std::time::Duration;
+
+/// Results of a benchmark run.
+#[derive(Debug)]
+pub struct Report {
+    pub requests: usize,
+    pub errors: usize,
+    pub elapsed: Duration,
+    pub p50: Duration,
+    pub p99: Duration,
+    pub p999: Duration,
+    pub max: Duration,
+}
+
+impl Report {
+    /// Creates the report from the latencies of all requests.
+    pub fn new(mut latencies: Vec<Duration>, errors: usize, elapsed: Duration) -> Self {
+        latencies.sort_unstable();
+        Report {
+            requests: latencies.len(),
+            errors,
+            elapsed,
+            p50: percentile(&latencies, 500),
+            p99: percentile(&latencies, 990),
+            p999: percentile(&latencies, 999),
+            max: latencies.last().copied().unwrap_or_default(),
+        }
+    }
+
+    /// Returns the number of requests per second.
+    pub fn throughput(&self) -> f64 {
+        match self.elapsed.as_secs_f64() {
+            s if s > 0.0 => self.requests as f64 / s,
+            _ => 0.0,
+        }
+    }
+}
+
+impl Display for Report {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "{} requests in {:.2?}, {} errors",
+            self.requests, self.elapsed, self.errors
+        )?;
+        writeln!(f, "throughput  {:.0} req/s", self.throughput())?;
+        writeln!(f, "p50         {:.2?}", self.p50)?;
+        writeln!(f, "p99         {:.2?}", self.p99)?;
+        writeln!(f, "p999        {:.2?}", self.p999)?;
+        write!(f, "max         {:.2?}", self.max)
+    }
+}
+
+/// Returns the nearest rank percentile of the sorted latencies, the percentile is given in per mille.
+fn percentile(sorted: &[Duration], per_mille: usize) -> Duration {
+    if sorted.is_empty() {
+        return Duration::ZERO;
+    }
+    let rank = (per_mille * sorted.len()).div_ceil(1000);
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn should_percentiles_calculated_by_nearest_rank() {
+        let latencies: Vec<Duration> = (1..=1000).rev().map(Duration::from_micros).collect();
+        let report = Report::new(latencies, 0, Duration::from_secs(2));
+        assert_eq!(report.p50, Duration::from_micros(500));
+        assert_eq!(report.p99, Duration::from_micros(990));
+        assert_eq!(report.p999, Duration::from_micros(999));
+        assert_eq!(report.max, Duration::from_micros(1000));
+        assert_eq!(report.throughput(), 500.0);
+
+        let empty = Report::new(Vec::new(), 0, Duration::ZERO);
+        assert_eq!(empty.p99, Duration::ZERO);
+        assert_eq!(empty.throughput(), 0.0);
+    }
+}
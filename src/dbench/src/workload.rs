@@ -0,0 +1,153 @@
+use crate::options::{Mix, Options};
+use crate::report::Report;
+use dclient::Client;
+use dserver::derror::message_parse_error::MessageParseError;
+use dserver::model::message::parse_value;
+use dserver::model::Random;
+use dserver::Value;
+use std::thread;
+use std::time::{Duration, Instant};
+
+enum Operation {
+    Add,
+    Get,
+    Del,
+}
+
+impl Mix {
+    fn pick(&self, random: &mut Random) -> Operation {
+        let n = random.below(u64::from(self.total())) as u32;
+        if n < self.add {
+            Operation::Add
+        } else if n < self.add + self.get {
+            Operation::Get
+        } else {
+            Operation::Del
+        }
+    }
+}
+
+/// Returns a value of the type with the configured text length.
+///
+/// A value that the server wouldn't accept is an error, the run never falls back to another type.
+fn value(options: &Options, random: &mut Random) -> Result<Value, MessageParseError> {
+    let n = random.number();
+    let payload = match options.tag.as_str() {
+        "s" => "x".repeat(options.size),
+        "l" => n.is_multiple_of(2).to_string(),
+        "i8" => (n as i8).to_string(),
+        "i16" => (n as i16).to_string(),
+        "f32" | "f64" => format!("{}.5", n % 1000),
+        _ => (n as i32).to_string(),
+    };
+    parse_value(&options.tag, &payload)
+}
+
+/// Drives the workload over the connections of the address and reports the results.
+///
+/// Returns an error if the options produce values that the server doesn't accept.
+pub fn run(address: &str, options: &Options) -> Result<Report, MessageParseError> {
+    let started = Instant::now();
+    let workers: Vec<_> = (0..options.connections)
+        .map(|id| {
+            let (address, options) = (address.to_string(), options.clone());
+            thread::spawn(move || connection(id as u64, &address, &options))
+        })
+        .collect();
+
+    let mut latencies = Vec::with_capacity(options.connections * options.requests);
+    let mut errors = 0;
+    for worker in workers {
+        match worker.join() {
+            Ok(Ok((l, e))) => {
+                latencies.extend(l);
+                errors += e;
+            }
+            Ok(Err(e)) => return Err(e),
+            Err(_) => errors += options.requests,
+        }
+    }
+    Ok(Report::new(latencies, errors, started.elapsed()))
+}
+
+/// Sends the requests of a single connection and returns their latencies with the error count.
+fn connection(
+    id: u64,
+    address: &str,
+    options: &Options,
+) -> Result<(Vec<Duration>, usize), MessageParseError> {
+    let mut client = Client::new(address).timeout(Duration::from_secs(5));
+    // Every connection has its own generator, so the workload is the same from run to run.
+    let mut random = Random::new(id + 1);
+    let mut latencies = Vec::with_capacity(options.requests);
+    let mut errors = 0;
+    for _ in 0..options.requests {
+        let key = format!("key{}", random.below(u64::from(options.keys)));
+        let operation = options.mix.pick(&mut random);
+        let value = value(options, &mut random)?;
+        let start = Instant::now();
+        let result = match operation {
+            Operation::Add => client.add(&key, value),
+            Operation::Get => client.get(&key).map(|_| ()),
+            Operation::Del => client.del(&key).map(|_| ()),
+        };
+        latencies.push(start.elapsed());
+        if result.is_err() {
+            errors += 1;
+        }
+    }
+    Ok((latencies, errors))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use dserver::{EvictionPolicy, Server};
+
+    #[test]
+    fn should_workload_run_against_in_process_server() {
+        let server = Server::new("127.0.0.1", 0)
+            .packs(4)
+            .eviction(EvictionPolicy::Lru)
+            .start()
+            .unwrap();
+        let options = Options {
+            connections: 3,
+            requests: 200,
+            keys: 50,
+            tag: "i16".to_string(),
+            ..Default::default()
+        };
+        let report = run(&server.local_addr().to_string(), &options).unwrap();
+        assert_eq!(report.requests, 600);
+        assert_eq!(report.errors, 0);
+        assert!(report.p50 <= report.p99 && report.p99 <= report.p999);
+    }
+
+    #[test]
+    fn should_values_over_the_limit_stop_the_run() {
+        let options = Options {
+            size: 100,
+            ..Default::default()
+        };
+        assert_eq!(
+            value(&options, &mut Random::new(1)),
+            Err(MessageParseError::ValueTooLong)
+        );
+        assert!(run("127.0.0.1:1", &options).is_err());
+    }
+
+    #[test]
+    fn should_mix_follow_weights() {
+        let mix = Mix {
+            add: 1,
+            get: 0,
+            del: 3,
+        };
+        let mut random = Random::new(7);
+        let adds = (0..4000)
+            .filter(|_| matches!(mix.pick(&mut random), Operation::Add))
+            .count();
+        assert!((800..1200).contains(&adds));
+    }
+}
@@ -0,0 +1,22 @@
+use dserver::derror::message_parse_error::MessageParseError;
+use dserver::derror::new_item_error::NewItemError;
+use thiserror::Error;
+
+/// Problems of a shell line that are found before it is sent to the server.
+#[derive(Debug, Error, PartialEq)]
+pub enum InputError {
+    #[error("Unknown command `{0}`, try help")]
+    UnknownCommand(String),
+    #[error("Usage: {0}")]
+    Usage(&'static str),
+    #[error("Quote is not closed")]
+    Quote,
+    #[error("Key is longer than {0} characters")]
+    KeyTooLong(usize),
+    #[error("Time to live must be a positive number of seconds up to a hundred years, not `{0}`")]
+    Ttl(String),
+    #[error("{0}")]
+    Value(#[from] MessageParseError),
+    #[error("{0}")]
+    Item(#[from] NewItemError),
+}
@@ -0,0 +1 @@
+pub mod input_error;
@@ -0,0 +1,386 @@
+use crate::derror::input_error::InputError;
+use dserver::constant::constant::{MAX_KEY_LEN, MAX_TTL_SECS};
+use dserver::derror::message_parse_error::MessageParseError;
+use dserver::model::message::parse_value;
+use dserver::{Item, Value};
+use std::time::Duration;
+use uuid::Uuid;
+
+/// A shell line that is checked and ready to send.
+#[derive(Debug, PartialEq)]
+pub enum Input {
+    Add(String, Value, Option<Duration>),
+    AddIfAbsent(String, Value),
+    AddIfPresent(String, Value),
+    Get(String),
+    GetWithUuid(String),
+    CompareAndSwap(String, Uuid, Value),
+    Del(String),
+    Expire(String, Duration),
+    Ttl(String),
+    Persist(String),
+    ListPush(String, Value),
+    ListPop(String),
+    ListRange(String, i64, i64),
+    SetAdd(String, Value),
+    SetRemove(String, Value),
+    SetMembers(String),
+    HashSet(String, String, Value),
+    HashGet(String, String),
+    HashDelete(String, String),
+    HashGetAll(String),
+    Increment(String, bool),
+    Decrement(String, bool),
+    IncrementBy(String, Value, bool),
+    MultiGet(Vec<String>),
+    MultiSet(Vec<(String, Value)>, bool),
+    MultiDelete(Vec<String>),
+    Info,
+    Save,
+    RewriteLog,
+    Help,
+    History,
+    Quit,
+}
+
+/// Short descriptions of the commands.
+pub const HELP: &str = "\
+add <key> <type> <value> [ttl]  adds or replaces a value, ttl is in seconds
+anx <key> <type> <value>        adds only if the key is absent
+axx <key> <type> <value>        replaces only if the key is present
+get <key> [uuid]                returns the value, uuid returns the uuid of the item too
+cas <key> <uuid> <type> <value> replaces the value only if the item still has the uuid
+del <key>                       deletes the key
+exp <key> <ttl>                 sets the time to live in seconds
+ttl <key>                       returns the remaining time to live
+prs <key>                       removes the expiry
+lpush <key> <type> <value>      pushes a value to the head of a list
+lpop <key>                      removes the value at the head of a list
+lrange <key> <start> <stop>     lists the values between two positions, -1 is the last one
+sadd <key> <type> <value>       adds a member to a set
+srem <key> <type> <value>       removes a member from a set
+smembers <key>                  lists the members of a set
+hset <key> <field> <type> <value>  sets a field of a hash
+hget <key> <field>              returns a field of a hash
+hdel <key> <field>              removes a field of a hash
+hgetall <key>                   lists the fields of a hash
+inc <key> [new]                 adds one to a number, new starts a missing key at zero
+dec <key> [new]                 subtracts one from a number
+iby <key> <type> <value> [new]  adds the amount to a number, it keeps the type of the number
+mget <key>...                   returns the values of the keys
+mset <key> <type> <value>... [all]  adds or replaces the values, all sets every value or none
+mdel <key>...                   deletes the keys
+inf                             item counts of the packs
+sav                             saves a snapshot
+rwl                             compacts the write log
+history                         lists the previous commands
+quit                            leaves the shell
+Types are i8, i16, i32, i64, i128, u8, u16, u32, u64, f32, f64, s (text), l (true or false),
+b (base64 bytes), ts (UTC time like 2024-05-01T12:30:00Z), d (seconds like 1.5) and uuid.
+Text with spaces is written in double quotes, add motd s \"hello world\"";
+
+impl Input {
+    /// Parses a shell line like `add ServerName s localhost`.
+    ///
+    /// Keys and values are checked with the rules of the server, so a wrong line never leaves the shell.
+    /// Returns None for empty lines and comments that start with `#`.
+    pub fn parse(line: &str) -> Result<Option<Self>, InputError> {
+        let words = split(line)?;
+        let (command, args) = match words.split_first() {
+            Some((c, _)) if c.starts_with('#') => return Ok(None),
+            Some((c, a)) => (c.to_lowercase(), a),
+            None => return Ok(None),
+        };
+        let args: Vec<&str> = args.iter().map(|a| a.as_str()).collect();
+        let input = match (command.as_str(), args.as_slice()) {
+            ("add", [k, t, v]) => Input::Add(key(k)?, item_value(k, t, v)?, None),
+            ("add", [k, t, v, ttl]) => {
+                Input::Add(key(k)?, item_value(k, t, v)?, Some(seconds(ttl)?))
+            }
+            ("add", _) => return Err(InputError::Usage("add <key> <type> <value> [ttl]")),
+            ("anx", [k, t, v]) => Input::AddIfAbsent(key(k)?, item_value(k, t, v)?),
+            ("anx", _) => return Err(InputError::Usage("anx <key> <type> <value>")),
+            ("axx", [k, t, v]) => Input::AddIfPresent(key(k)?, item_value(k, t, v)?),
+            ("axx", _) => return Err(InputError::Usage("axx <key> <type> <value>")),
+            ("get", [k]) => Input::Get(key(k)?),
+            ("get", [k, "uuid"]) => Input::GetWithUuid(key(k)?),
+            ("get", _) => return Err(InputError::Usage("get <key> [uuid]")),
+            ("cas", [k, u, t, v]) => Input::CompareAndSwap(key(k)?, uuid(u)?, item_value(k, t, v)?),
+            ("cas", _) => return Err(InputError::Usage("cas <key> <uuid> <type> <value>")),
+            ("del", [k]) => Input::Del(key(k)?),
+            ("del", _) => return Err(InputError::Usage("del <key>")),
+            ("exp", [k, ttl]) => Input::Expire(key(k)?, seconds(ttl)?),
+            ("exp", _) => return Err(InputError::Usage("exp <key> <ttl>")),
+            ("ttl", [k]) => Input::Ttl(key(k)?),
+            ("ttl", _) => return Err(InputError::Usage("ttl <key>")),
+            ("prs", [k]) => Input::Persist(key(k)?),
+            ("prs", _) => return Err(InputError::Usage("prs <key>")),
+            ("lpush", [k, t, v]) => Input::ListPush(key(k)?, element(t, v)?),
+            ("lpush", _) => return Err(InputError::Usage("lpush <key> <type> <value>")),
+            ("lpop", [k]) => Input::ListPop(key(k)?),
+            ("lpop", _) => return Err(InputError::Usage("lpop <key>")),
+            ("lrange", [k, start, stop]) => {
+                Input::ListRange(key(k)?, position(start)?, position(stop)?)
+            }
+            ("lrange", _) => return Err(InputError::Usage("lrange <key> <start> <stop>")),
+            ("sadd", [k, t, v]) => Input::SetAdd(key(k)?, element(t, v)?),
+            ("sadd", _) => return Err(InputError::Usage("sadd <key> <type> <value>")),
+            ("srem", [k, t, v]) => Input::SetRemove(key(k)?, element(t, v)?),
+            ("srem", _) => return Err(InputError::Usage("srem <key> <type> <value>")),
+            ("smembers", [k]) => Input::SetMembers(key(k)?),
+            ("smembers", _) => return Err(InputError::Usage("smembers <key>")),
+            ("hset", [k, f, t, v]) => Input::HashSet(key(k)?, key(f)?, element(t, v)?),
+            ("hset", _) => return Err(InputError::Usage("hset <key> <field> <type> <value>")),
+            ("hget", [k, f]) => Input::HashGet(key(k)?, key(f)?),
+            ("hget", _) => return Err(InputError::Usage("hget <key> <field>")),
+            ("hdel", [k, f]) => Input::HashDelete(key(k)?, key(f)?),
+            ("hdel", _) => return Err(InputError::Usage("hdel <key> <field>")),
+            ("hgetall", [k]) => Input::HashGetAll(key(k)?),
+            ("hgetall", _) => return Err(InputError::Usage("hgetall <key>")),
+            ("inc", [k]) => Input::Increment(key(k)?, false),
+            ("inc", [k, "new"]) => Input::Increment(key(k)?, true),
+            ("inc", _) => return Err(InputError::Usage("inc <key> [new]")),
+            ("dec", [k]) => Input::Decrement(key(k)?, false),
+            ("dec", [k, "new"]) => Input::Decrement(key(k)?, true),
+            ("dec", _) => return Err(InputError::Usage("dec <key> [new]")),
+            ("iby", [k, t, v]) => Input::IncrementBy(key(k)?, number(t, v)?, false),
+            ("iby", [k, t, v, "new"]) => Input::IncrementBy(key(k)?, number(t, v)?, true),
+            ("iby", _) => return Err(InputError::Usage("iby <key> <type> <value> [new]")),
+            ("mget", keys) if !keys.is_empty() => {
+                Input::MultiGet(keys.iter().map(|k| key(k)).collect::<Result<_, _>>()?)
+            }
+            ("mget", _) => return Err(InputError::Usage("mget <key>...")),
+            ("mset", parts) if parts.len() % 3 == 0 || parts.len() % 3 == 1 => {
+                let (triples, all) = match parts.split_last() {
+                    Some((&"all", rest)) if parts.len() % 3 == 1 => (rest, true),
+                    _ => (parts, false),
+                };
+                if triples.is_empty() || triples.len() % 3 != 0 {
+                    return Err(InputError::Usage("mset <key> <type> <value>... [all]"));
+                }
+                let pairs = triples
+                    .chunks(3)
+                    .map(|t| Ok((key(t[0])?, item_value(t[0], t[1], t[2])?)))
+                    .collect::<Result<_, InputError>>()?;
+                Input::MultiSet(pairs, all)
+            }
+            ("mset", _) => return Err(InputError::Usage("mset <key> <type> <value>... [all]")),
+            ("mdel", keys) if !keys.is_empty() => {
+                Input::MultiDelete(keys.iter().map(|k| key(k)).collect::<Result<_, _>>()?)
+            }
+            ("mdel", _) => return Err(InputError::Usage("mdel <key>...")),
+            ("inf", []) => Input::Info,
+            ("sav", []) => Input::Save,
+            ("rwl", []) => Input::RewriteLog,
+            ("help", _) => Input::Help,
+            ("history", []) => Input::History,
+            ("quit" | "exit", []) => Input::Quit,
+            (c, _) => return Err(InputError::UnknownCommand(c.to_string())),
+        };
+        Ok(Some(input))
+    }
+}
+
+fn key(k: &str) -> Result<String, InputError> {
+    if k.len() > MAX_KEY_LEN {
+        return Err(InputError::KeyTooLong(MAX_KEY_LEN));
+    }
+    Ok(k.to_string())
+}
+
+/// Converts the value by its type tag and checks it as an item like the server does.
+fn item_value(k: &str, tag: &str, v: &str) -> Result<Value, InputError> {
+    let value = parse_value(tag, v)?;
+    Item::new(k.to_string(), value.clone())?;
+    Ok(value)
+}
+
+/// Converts the element of a collection and checks it like the server does.
+fn element(tag: &str, v: &str) -> Result<Value, InputError> {
+    let value = parse_value(tag, v)?;
+    Item::validate_element(&value)?;
+    Ok(value)
+}
+
+/// Converts the amount of a counter command, only numbers can be added.
+fn number(tag: &str, v: &str) -> Result<Value, InputError> {
+    match parse_value(tag, v)? {
+        value if value.is_number() => Ok(value),
+        _ => Err(MessageParseError::invalid_value(tag, v).into()),
+    }
+}
+
+fn uuid(v: &str) -> Result<Uuid, InputError> {
+    Uuid::parse_str(v).map_err(|_| MessageParseError::invalid_value("uuid", v).into())
+}
+
+fn position(v: &str) -> Result<i64, InputError> {
+    v.parse()
+        .map_err(|_| MessageParseError::invalid_value("i64", v).into())
+}
+
+fn seconds(v: &str) -> Result<Duration, InputError> {
+    match v.parse::<u64>() {
+        Ok(s) if s > 0 && s <= MAX_TTL_SECS => Ok(Duration::from_secs(s)),
+        _ => Err(InputError::Ttl(v.to_string())),
+    }
+}
+
+/// Splits the line by whitespace. Double quotes keep the spaces of a word.
+fn split(line: &str) -> Result<Vec<String>, InputError> {
+    let mut words = Vec::new();
+    let mut word: Option<String> = None;
+    let mut quoted = false;
+    for c in line.chars() {
+        match c {
+            '"' => {
+                quoted = !quoted;
+                word.get_or_insert_with(String::new);
+            }
+            c if c.is_whitespace() && !quoted => {
+                if let Some(w) = word.take() {
+                    words.push(w);
+                }
+            }
+            c => word.get_or_insert_with(String::new).push(c),
+        }
+    }
+    if quoted {
+        return Err(InputError::Quote);
+    }
+    words.extend(word);
+    Ok(words)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn should_lines_parsed_to_inputs() {
+        assert_eq!(
+            Input::parse("add ServerName s localhost").unwrap(),
+            Some(Input::Add(
+                "ServerName".to_string(),
+                Value::Text("localhost".to_string()),
+                None
+            ))
+        );
+        assert_eq!(
+            Input::parse("ADD motd s \"hello world\" 60").unwrap(),
+            Some(Input::Add(
+                "motd".to_string(),
+                Value::Text("hello world".to_string()),
+                Some(Duration::from_secs(60))
+            ))
+        );
+        assert_eq!(
+            Input::parse("  get   Level ").unwrap(),
+            Some(Input::Get("Level".to_string()))
+        );
+        assert!(Input::parse("add empty s \"\"").unwrap().is_some());
+        assert_eq!(
+            Input::parse("hset user name s dory").unwrap(),
+            Some(Input::HashSet(
+                "user".to_string(),
+                "name".to_string(),
+                Value::Text("dory".to_string())
+            ))
+        );
+        assert_eq!(
+            Input::parse("lrange queue 0 -1").unwrap(),
+            Some(Input::ListRange("queue".to_string(), 0, -1))
+        );
+        assert_eq!(
+            Input::parse("iby hits i64 -2 new").unwrap(),
+            Some(Input::IncrementBy(
+                "hits".to_string(),
+                Value::HugeNumber(-2),
+                true
+            ))
+        );
+        assert_eq!(
+            Input::parse("mset a s on b i8 2 all").unwrap(),
+            Some(Input::MultiSet(
+                vec![
+                    ("a".to_string(), Value::Text("on".to_string())),
+                    ("b".to_string(), Value::ThinNumber(2))
+                ],
+                true
+            ))
+        );
+        assert_eq!(
+            Input::parse("mget a b").unwrap(),
+            Some(Input::MultiGet(vec!["a".to_string(), "b".to_string()]))
+        );
+        assert_eq!(Input::parse("").unwrap(), None);
+        assert_eq!(Input::parse("# comment").unwrap(), None);
+    }
+
+    #[test]
+    fn should_wrong_lines_rejected_locally() {
+        assert_eq!(
+            Input::parse("add VeryVeryLongKeyName l true"),
+            Err(InputError::KeyTooLong(MAX_KEY_LEN))
+        );
+        assert_eq!(
+            Input::parse("add Level i8 999"),
+            Err(InputError::Value(MessageParseError::out_of_range(
+                "i8", "999"
+            )))
+        );
+        assert_eq!(
+            Input::parse("add Level u256 1"),
+            Err(InputError::Value(MessageParseError::UnknownType(
+                "u256".to_string()
+            )))
+        );
+        assert_eq!(
+            Input::parse("exp Level 0"),
+            Err(InputError::Ttl("0".to_string()))
+        );
+        assert_eq!(
+            Input::parse("exp Level 18446744073709551615"),
+            Err(InputError::Ttl("18446744073709551615".to_string()))
+        );
+        assert_eq!(
+            Input::parse("get"),
+            Err(InputError::Usage("get <key> [uuid]"))
+        );
+        assert_eq!(
+            Input::parse("cas config v1 s on"),
+            Err(InputError::Value(MessageParseError::invalid_value(
+                "uuid", "v1"
+            )))
+        );
+        assert_eq!(
+            Input::parse("hget user VeryVeryLongFieldName"),
+            Err(InputError::KeyTooLong(MAX_KEY_LEN))
+        );
+        assert_eq!(
+            Input::parse("lrange queue 0 last"),
+            Err(InputError::Value(MessageParseError::invalid_value(
+                "i64", "last"
+            )))
+        );
+        assert_eq!(
+            Input::parse("set a b"),
+            Err(InputError::UnknownCommand("set".to_string()))
+        );
+        assert_eq!(
+            Input::parse("iby hits s 1"),
+            Err(InputError::Value(MessageParseError::invalid_value(
+                "s", "1"
+            )))
+        );
+        assert_eq!(
+            Input::parse("mset a s on b i8"),
+            Err(InputError::Usage("mset <key> <type> <value>... [all]"))
+        );
+        assert_eq!(
+            Input::parse("mdel"),
+            Err(InputError::Usage("mdel <key>..."))
+        );
+        assert_eq!(Input::parse("add a s \"open"), Err(InputError::Quote));
+    }
+}
@@ -0,0 +1,133 @@
+use crate::input::{Input, HELP};
+use crate::shell::execute;
+use dclient::Client;
+use rustyline::error::ReadlineError;
+use rustyline::DefaultEditor;
+use std::env;
+use std::fs::File;
+use std::io::{stdin, BufRead, BufReader, IsTerminal};
+use std::path::PathBuf;
+use std::process::exit;
+use std::time::Duration;
+
+mod derror;
+mod input;
+mod shell;
+
+const DEFAULT_ADDRESS: &str = "localhost:5555";
+const HISTORY_FILE: &str = ".dcli_history";
+const TIMEOUT_SECS: u64 = 5;
+
+/// `dcli [host:port] [file]`
+///
+/// Without a file the shell is interactive. A file, `-` or piped stdin runs the commands one after another.
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    if args.len() > 3 {
+        eprintln!("Usage: dcli [host:port] [file|-]");
+        exit(1);
+    }
+    let address = args.get(1).map(|a| a.as_str()).unwrap_or(DEFAULT_ADDRESS);
+    let mut client = Client::new(address).timeout(Duration::from_secs(TIMEOUT_SECS));
+
+    let succeeded = match args.get(2).map(|a| a.as_str()) {
+        Some("-") => script(&mut client, stdin().lock()),
+        Some(path) => match File::open(path) {
+            Ok(f) => script(&mut client, BufReader::new(f)),
+            Err(e) => {
+                eprintln!("{} couldn't open, {}", path, e);
+                false
+            }
+        },
+        None if !stdin().is_terminal() => script(&mut client, stdin().lock()),
+        None => interactive(&mut client, address),
+    };
+    if !succeeded {
+        exit(1);
+    }
+}
+
+/// Runs the commands of the reader and returns false if any of them fails.
+fn script<R: BufRead>(client: &mut Client, reader: R) -> bool {
+    let mut succeeded = true;
+    for (number, line) in reader.lines().enumerate() {
+        let line = match line {
+            Ok(l) => l,
+            Err(e) => {
+                eprintln!("Input couldn't read, {}", e);
+                return false;
+            }
+        };
+        match Input::parse(&line) {
+            Ok(None) => {}
+            Ok(Some(Input::Quit)) => break,
+            Ok(Some(Input::Help | Input::History)) => {}
+            Ok(Some(input)) => match execute(client, input) {
+                Ok(output) => println!("{}", output),
+                Err(e) => {
+                    println!("(error) line {}: {}", number + 1, e);
+                    succeeded = false;
+                }
+            },
+            Err(e) => {
+                println!("(error) line {}: {}", number + 1, e);
+                succeeded = false;
+            }
+        }
+    }
+    succeeded
+}
+
+/// Reads the commands from the terminal with line editing and history.
+fn interactive(client: &mut Client, address: &str) -> bool {
+    let mut editor = match DefaultEditor::new() {
+        Ok(e) => e,
+        Err(e) => {
+            eprintln!("Terminal couldn't be used, {}", e);
+            return false;
+        }
+    };
+    let history = history_path();
+    if let Some(h) = &history {
+        let _ = editor.load_history(h);
+    }
+    println!("Connected to {}, try help", address);
+    let prompt = format!("{}> ", address);
+    loop {
+        let line = match editor.readline(&prompt) {
+            Ok(l) => l,
+            Err(ReadlineError::Interrupted | ReadlineError::Eof) => break,
+            Err(e) => {
+                eprintln!("{}", e);
+                break;
+            }
+        };
+        if !line.trim().is_empty() {
+            let _ = editor.add_history_entry(line.as_str());
+        }
+        match Input::parse(&line) {
+            Ok(None) => {}
+            Ok(Some(Input::Quit)) => break,
+            Ok(Some(Input::Help)) => println!("{}", HELP),
+            Ok(Some(Input::History)) => {
+                for (i, entry) in editor.history().iter().enumerate() {
+                    println!("{:>4}  {}", i + 1, entry);
+                }
+            }
+            Ok(Some(input)) => match execute(client, input) {
+                Ok(output) => println!("{}", output),
+                Err(e) => println!("(error) {}", e),
+            },
+            Err(e) => println!("(error) {}", e),
+        }
+    }
+    if let Some(h) = &history {
+        let _ = editor.save_history(h);
+    }
+    true
+}
+
+/// The history is kept in the home directory of the user.
+fn history_path() -> Option<PathBuf> {
+    env::var_os("HOME").map(|h| PathBuf::from(h).join(HISTORY_FILE))
+}
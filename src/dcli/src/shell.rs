@@ -0,0 +1,210 @@
+use crate::input::Input;
+use dclient::{Client, ClientError, Ttl};
+use dserver::Value;
+
+/// Sends the input to the server and returns the printable result.
+///
+/// Help, history and quit are handled by the shell itself, they are not sent.
+pub fn execute(client: &mut Client, input: Input) -> Result<String, ClientError> {
+    let output = match input {
+        Input::Add(k, v, None) => client.add(&k, v).map(|_| ok())?,
+        Input::Add(k, v, Some(ttl)) => client.add_with_ttl(&k, v, ttl).map(|_| ok())?,
+        Input::AddIfAbsent(k, v) => match client.add_if_absent(&k, v)? {
+            true => ok(),
+            false => "(key exists)".to_string(),
+        },
+        Input::AddIfPresent(k, v) => found(client.add_if_present(&k, v)?),
+        Input::Get(k) => match client.get(&k)? {
+            Some(v) => value(&v),
+            None => not_found(),
+        },
+        Input::GetWithUuid(k) => match client.get_with_uuid(&k)? {
+            Some((v, u)) => format!("{}\n{}", value(&v), value(&Value::Uuid(u))),
+            None => not_found(),
+        },
+        Input::CompareAndSwap(k, u, v) => match client.cas(&k, u, v)? {
+            Some(u) => value(&Value::Uuid(u)),
+            None => not_found(),
+        },
+        Input::Del(k) => found(client.del(&k)?),
+        Input::Expire(k, ttl) => found(client.expire(&k, ttl)?),
+        Input::Persist(k) => found(client.persist(&k)?),
+        Input::Ttl(k) => match client.ttl(&k)? {
+            Some(Ttl::Never) => "(never expires)".to_string(),
+            Some(Ttl::Remaining(d)) => format!("{} seconds", d.as_secs()),
+            None => not_found(),
+        },
+        Input::ListPush(k, v) => format!("{} values", client.lpush(&k, v)?),
+        Input::ListPop(k) => match client.lpop(&k)? {
+            Some(v) => value(&v),
+            None => not_found(),
+        },
+        Input::ListRange(k, start, stop) => values(&client.lrange(&k, start, stop)?),
+        Input::SetAdd(k, v) => changed(client.sadd(&k, v)?),
+        Input::SetRemove(k, v) => changed(client.srem(&k, v)?),
+        Input::SetMembers(k) => values(&client.smembers(&k)?),
+        Input::HashSet(k, f, v) => changed(client.hset(&k, &f, v)?),
+        Input::HashGet(k, f) => match client.hget(&k, &f)? {
+            Some(v) => value(&v),
+            None => not_found(),
+        },
+        Input::HashDelete(k, f) => changed(client.hdel(&k, &f)?),
+        Input::HashGetAll(k) => {
+            let fields: Vec<String> = client
+                .hgetall(&k)?
+                .iter()
+                .map(|(f, v)| format!("{}: {}", f, value(v)))
+                .collect();
+            numbered(fields)
+        }
+        Input::Increment(k, create) => counter(client.incr(&k, create)?),
+        Input::Decrement(k, create) => counter(client.decr(&k, create)?),
+        Input::IncrementBy(k, v, create) => counter(client.incr_by(&k, v, create)?),
+        Input::MultiGet(keys) => {
+            let keys: Vec<&str> = keys.iter().map(String::as_str).collect();
+            let found = client.mget(&keys)?;
+            numbered(
+                found
+                    .iter()
+                    .map(|v| v.as_ref().map(value).unwrap_or_else(not_found))
+                    .collect(),
+            )
+        }
+        Input::MultiSet(pairs, all) => {
+            let pairs: Vec<(&str, Value)> =
+                pairs.iter().map(|(k, v)| (k.as_str(), v.clone())).collect();
+            let results = client.mset(&pairs, all)?;
+            numbered(
+                results
+                    .iter()
+                    .map(|r| match r {
+                        Ok(_) => ok(),
+                        Err(e) => format!("(error) {}", e),
+                    })
+                    .collect(),
+            )
+        }
+        Input::MultiDelete(keys) => {
+            let keys: Vec<&str> = keys.iter().map(String::as_str).collect();
+            numbered(client.mdel(&keys)?.into_iter().map(found).collect())
+        }
+        Input::Info => client.info()?.replace(',', "\n"),
+        Input::Save => format!("{} items saved", client.save()?),
+        Input::RewriteLog => format!("{} items in the write log", client.rewrite_log()?),
+        Input::Help | Input::History | Input::Quit => String::new(),
+    };
+    Ok(output)
+}
+
+/// Prints the value with its type, like `(i8) 3` or `(s) "localhost"`.
+pub fn value(v: &Value) -> String {
+    match v {
+        Value::Text(t) => format!("({}) {:?}", v.tag(), t),
+        _ => format!("({}) {}", v.tag(), v.payload()),
+    }
+}
+
+/// Prints the values one per line with their numbers, like `1) (s) "on"`.
+fn values(values: &[Value]) -> String {
+    numbered(values.iter().map(value).collect())
+}
+
+fn numbered(lines: Vec<String>) -> String {
+    if lines.is_empty() {
+        return "(empty)".to_string();
+    }
+    lines
+        .iter()
+        .enumerate()
+        .map(|(i, l)| format!("{}) {}", i + 1, l))
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+/// Prints whether a collection command changed an element.
+fn changed(c: bool) -> String {
+    match c {
+        true => "(1 changed)".to_string(),
+        false => "(0 changed)".to_string(),
+    }
+}
+
+/// Prints the new number of a counter, a missing key was not created.
+fn counter(number: Option<Value>) -> String {
+    match number {
+        Some(v) => value(&v),
+        None => not_found(),
+    }
+}
+
+fn ok() -> String {
+    "OK".to_string()
+}
+
+fn not_found() -> String {
+    "(not found)".to_string()
+}
+
+fn found(f: bool) -> String {
+    match f {
+        true => ok(),
+        false => not_found(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use dserver::Server;
+
+    #[test]
+    fn should_results_printed_for_humans() {
+        let server = Server::new("127.0.0.1", 0).start().unwrap();
+        let mut client = Client::new(server.local_addr().to_string());
+        let mut run = |line: &str| {
+            let input = Input::parse(line).unwrap().unwrap();
+            execute(&mut client, input).unwrap()
+        };
+        assert_eq!(run("add ServerName s localhost"), "OK");
+        assert_eq!(run("get ServerName"), "(s) \"localhost\"");
+        assert_eq!(run("anx ServerName s remote"), "(key exists)");
+        assert_eq!(run("add Level i16 -300 60"), "OK");
+        assert_eq!(run("get Level"), "(i16) -300");
+        assert_eq!(run("ttl Level"), "60 seconds");
+        assert_eq!(run("prs Level"), "OK");
+        assert_eq!(run("ttl Level"), "(never expires)");
+        assert_eq!(run("del Debug"), "(not found)");
+        assert_eq!(run("inf"), "#1 2/1000");
+        assert_eq!(run("lpush queue s a"), "1 values");
+        assert_eq!(run("lpush queue i8 2"), "2 values");
+        assert_eq!(run("lrange queue 0 -1"), "1) (i8) 2\n2) (s) \"a\"");
+        assert_eq!(run("sadd tags s x"), "(1 changed)");
+        assert_eq!(run("sadd tags s x"), "(0 changed)");
+        assert_eq!(run("smembers none"), "(empty)");
+        assert_eq!(run("hset user name s dory"), "(1 changed)");
+        assert_eq!(run("hgetall user"), "1) name: (s) \"dory\"");
+        assert_eq!(run("hget user none"), "(not found)");
+        assert_eq!(run("inc hits"), "(not found)");
+        assert_eq!(run("inc hits new"), "(i32) 1");
+        assert_eq!(run("iby hits i8 -3"), "(i32) -2");
+        assert_eq!(run("mset a s on b i8 2 all"), "1) OK\n2) OK");
+        assert_eq!(
+            run("mget a none b"),
+            "1) (s) \"on\"\n2) (not found)\n3) (i8) 2"
+        );
+        assert_eq!(run("mdel a none"), "1) OK\n2) (not found)");
+        let read = run("get ServerName uuid");
+        let (_, uuid) = read.split_once("\n(uuid) ").unwrap();
+        assert!(run(&format!("cas ServerName {} s remote", uuid)).starts_with("(uuid) "));
+        assert!(matches!(
+            execute(
+                &mut client,
+                Input::parse(&format!("cas ServerName {} s other", uuid))
+                    .unwrap()
+                    .unwrap()
+            ),
+            Err(ClientError::Conflict)
+        ));
+        assert_eq!(value(&Value::Logical(true)), "(l) true");
+    }
+}
@@ -0,0 +1,1081 @@
+use crate::derror::client_error::ClientError;
+use dserver::model::message::{escape, parse_versioned, split, value_parts};
+use dserver::model::{Command, Message};
+use dserver::server::binary::{self, MAGIC, VERSION};
+use dserver::server::connection::Frame;
+use dserver::server::response::{parse_results, Code, Response};
+use dserver::Value;
+use log::{info, warn};
+use std::io::{BufRead, BufReader, ErrorKind, Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::Duration;
+use uuid::Uuid;
+
+/// Remaining lifetime of a key.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Ttl {
+    /// The key never expires.
+    Never,
+    /// The key expires after the duration. It is rounded up to seconds by the server.
+    Remaining(Duration),
+}
+
+/// A reply of the server, its code and the value if it carries one.
+#[derive(Debug, PartialEq)]
+pub struct Reply {
+    pub code: Code,
+    pub value: Option<Value>,
+    /// The uuid of the item, GET replies it if it is asked.
+    pub version: Option<Uuid>,
+    /// The reply of every key of a multi-key command, in the order of the keys.
+    pub results: Vec<Reply>,
+}
+
+impl TryFrom<&str> for Reply {
+    type Error = ClientError;
+
+    /// Parses a reply line like `200 Success|s|localhost|`, the payload is unescaped.
+    ///
+    /// Collections come with their element count and elements, like `200 Success|list|1|s|on|`.
+    /// Multi-key commands reply the count of their keys and the result of every key, like `200 Success|2|200|s|on|404|`.
+    fn try_from(line: &str) -> Result<Self, Self::Error> {
+        let protocol = || ClientError::Protocol(line.to_string());
+        let content = line
+            .strip_suffix("\r\n")
+            .or_else(|| line.strip_suffix('\n'))
+            .unwrap_or(line);
+        let parts = split(content).map_err(|_| protocol())?;
+        let (head, rest) = parts.split_first().ok_or_else(protocol)?;
+        let number = head.split(' ').next().ok_or_else(protocol)?;
+        let code = number
+            .parse::<u16>()
+            .ok()
+            .and_then(|n| Code::try_from(n).ok())
+            .ok_or_else(protocol)?;
+        let (value, version, results) = match rest {
+            [] => (None, None, Vec::new()),
+            [count, ..] if count.bytes().all(|b| b.is_ascii_digit()) => {
+                let results = parse_results(rest).map_err(|_| protocol())?;
+                (None, None, results.into_iter().map(Reply::from).collect())
+            }
+            parts => {
+                let (value, version) = parse_versioned(parts).map_err(|_| protocol())?;
+                (Some(value), version, Vec::new())
+            }
+        };
+        Ok(Reply {
+            code,
+            value,
+            version,
+            results,
+        })
+    }
+}
+
+impl From<Response> for Reply {
+    fn from(response: Response) -> Self {
+        Reply {
+            code: response.code(),
+            value: response.value().cloned(),
+            version: response.version(),
+            results: response
+                .results()
+                .iter()
+                .cloned()
+                .map(Reply::from)
+                .collect(),
+        }
+    }
+}
+
+/// Wire format that the client speaks.
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub enum Protocol {
+    /// Pipe separated lines, pipes and line breaks in keys and values are escaped.
+    #[default]
+    Text,
+    /// Length prefixed frames with typed values and request ids.
+    Binary,
+}
+
+/// An open connection to the server.
+struct Connection {
+    writer: TcpStream,
+    reader: BufReader<TcpStream>,
+}
+
+/// Talks to a dory server over a single TCP connection.
+///
+/// The connection is opened with the first request and reused by the following ones.
+/// If it breaks, or a reply doesn't arrive in time, it is dropped and the next request opens a new one.
+///
+/// # Examples
+///
+/// ```
+/// use dclient::Client;
+/// use dserver::{Server, Value};
+/// use std::time::Duration;
+///
+/// let server = Server::new("127.0.0.1", 0).start().unwrap();
+/// let mut client = Client::new(server.local_addr().to_string()).timeout(Duration::from_secs(1));
+/// client.add("server", Value::Text("london".to_string())).unwrap();
+/// assert_eq!(client.get("server").unwrap(), Some(Value::Text("london".to_string())));
+/// ```
+pub struct Client {
+    address: String,
+    timeout: Option<Duration>,
+    protocol: Protocol,
+    connection: Option<Connection>,
+    next_id: u32,
+}
+
+impl Client {
+    /// Creates a client for the address. The connection is opened with the first request.
+    pub fn new<A: Into<String>>(address: A) -> Self {
+        Client {
+            address: address.into(),
+            timeout: None,
+            protocol: Protocol::default(),
+            connection: None,
+            next_id: 0,
+        }
+    }
+
+    /// Sets the time limit of connecting, writing a message and reading its reply.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Sets the wire format of the connection, text is the default.
+    pub fn protocol(mut self, protocol: Protocol) -> Self {
+        self.protocol = protocol;
+        self
+    }
+
+    /// Opens the connection immediately instead of the first request.
+    pub fn connect(mut self) -> Result<Self, ClientError> {
+        self.connection()?;
+        Ok(self)
+    }
+
+    /// Adds the value or replaces the value of an existing key.
+    pub fn add(&mut self, key: &str, value: Value) -> Result<(), ClientError> {
+        self.expect_success(message(Command::Add, key, Some(value)))
+    }
+
+    /// Adds the value with a time to live. The duration is rounded up to seconds.
+    pub fn add_with_ttl(
+        &mut self,
+        key: &str,
+        value: Value,
+        ttl: Duration,
+    ) -> Result<(), ClientError> {
+        let message = message(Command::Add, key, Some(value)).with_ttl(Some(seconds(ttl)));
+        self.expect_success(message)
+    }
+
+    /// Adds the value only if the key is absent. Returns false if the key exists.
+    pub fn add_if_absent(&mut self, key: &str, value: Value) -> Result<bool, ClientError> {
+        match self.request(message(Command::Anx, key, Some(value)))? {
+            Reply {
+                code: Code::Success,
+                ..
+            } => Ok(true),
+            Reply {
+                code: Code::KeyExists,
+                ..
+            } => Ok(false),
+            r => Err(r.code.into()),
+        }
+    }
+
+    /// Replaces the value only if the key is present. Returns false if there is no such key.
+    pub fn add_if_present(&mut self, key: &str, value: Value) -> Result<bool, ClientError> {
+        self.found(message(Command::Axx, key, Some(value)))
+    }
+
+    /// Returns the value of the key or None if there is no such key.
+    pub fn get(&mut self, key: &str) -> Result<Option<Value>, ClientError> {
+        match self.request(message(Command::Get, key, None))? {
+            Reply {
+                code: Code::Success,
+                value: Some(v),
+                ..
+            } => Ok(Some(v)),
+            Reply {
+                code: Code::NotFound,
+                ..
+            } => Ok(None),
+            r => Err(r.code.into()),
+        }
+    }
+
+    /// Returns the value with the uuid of its item, the uuid changes whenever the value does.
+    pub fn get_with_uuid(&mut self, key: &str) -> Result<Option<(Value, Uuid)>, ClientError> {
+        match self.request(message(Command::Get, key, None).with_versioned(true))? {
+            Reply {
+                code: Code::Success,
+                value: Some(v),
+                version: Some(u),
+                ..
+            } => Ok(Some((v, u))),
+            Reply {
+                code: Code::NotFound,
+                ..
+            } => Ok(None),
+            r => Err(self.unexpected(r)),
+        }
+    }
+
+    /// Replaces the value only if the item still has the uuid that was read, and returns the new uuid.
+    ///
+    /// Returns None if there is no such key and [`ClientError::Conflict`] if the item was changed.
+    pub fn cas(
+        &mut self,
+        key: &str,
+        uuid: Uuid,
+        value: Value,
+    ) -> Result<Option<Uuid>, ClientError> {
+        match self.applied(message(Command::Cas, key, Some(value)).with_version(Some(uuid)))? {
+            Some(Value::Uuid(u)) => Ok(Some(u)),
+            None => Ok(None),
+            Some(v) => Err(ClientError::Protocol(format!("{:?}", v))),
+        }
+    }
+
+    /// Returns the values of the keys in their order, None for the keys that don't exist.
+    ///
+    /// All keys are read with one request, the packs of the keys read them together.
+    pub fn mget(&mut self, keys: &[&str]) -> Result<Vec<Option<Value>>, ClientError> {
+        let batch = keys
+            .iter()
+            .map(|k| message(Command::Get, k, None))
+            .collect();
+        self.results(message(Command::Mget, "", None).with_batch(batch))?
+            .into_iter()
+            .map(|r| match r {
+                Reply {
+                    code: Code::Success,
+                    value: Some(v),
+                    ..
+                } => Ok(Some(v)),
+                Reply {
+                    code: Code::NotFound,
+                    ..
+                } => Ok(None),
+                r => Err(self.unexpected(r)),
+            })
+            .collect()
+    }
+
+    /// Adds the values or replaces the values of existing keys with one request.
+    ///
+    /// Every key has its own result unless `all` is true. Then either every value is set,
+    /// or none of them is and every key fails, with `PackFull` for the keys whose pack has no room.
+    pub fn mset(
+        &mut self,
+        pairs: &[(&str, Value)],
+        all: bool,
+    ) -> Result<Vec<Result<(), ClientError>>, ClientError> {
+        let batch = pairs
+            .iter()
+            .map(|(k, v)| message(Command::Add, k, Some(v.clone())))
+            .collect();
+        let message = message(Command::Mset, "", None)
+            .with_batch(batch)
+            .with_atomic(all);
+        Ok(self
+            .results(message)?
+            .into_iter()
+            .map(|r| match r.code {
+                Code::Success => Ok(()),
+                code => Err(code.into()),
+            })
+            .collect())
+    }
+
+    /// Deletes the keys and tells for every key whether it existed.
+    pub fn mdel(&mut self, keys: &[&str]) -> Result<Vec<bool>, ClientError> {
+        let batch = keys
+            .iter()
+            .map(|k| message(Command::Del, k, None))
+            .collect();
+        self.results(message(Command::Mdel, "", None).with_batch(batch))?
+            .into_iter()
+            .map(|r| match r.code {
+                Code::Success => Ok(true),
+                Code::NotFound => Ok(false),
+                code => Err(code.into()),
+            })
+            .collect()
+    }
+
+    /// Deletes the key. Returns false if there is no such key.
+    pub fn del(&mut self, key: &str) -> Result<bool, ClientError> {
+        self.found(message(Command::Del, key, None))
+    }
+
+    /// Sets the time to live of the key. Returns false if there is no such key.
+    pub fn expire(&mut self, key: &str, ttl: Duration) -> Result<bool, ClientError> {
+        self.found(message(Command::Exp, key, None).with_ttl(Some(seconds(ttl))))
+    }
+
+    /// Removes the expiry of the key. Returns false if there is no such key.
+    pub fn persist(&mut self, key: &str) -> Result<bool, ClientError> {
+        self.found(message(Command::Prs, key, None))
+    }
+
+    /// Returns the remaining lifetime of the key or None if there is no such key.
+    pub fn ttl(&mut self, key: &str) -> Result<Option<Ttl>, ClientError> {
+        match self.request(message(Command::Ttl, key, None))? {
+            Reply {
+                code: Code::Success,
+                value: Some(Value::HugeNumber(s)),
+                ..
+            } => match u64::try_from(s) {
+                Ok(s) => Ok(Some(Ttl::Remaining(Duration::from_secs(s)))),
+                Err(_) => Ok(Some(Ttl::Never)),
+            },
+            Reply {
+                code: Code::NotFound,
+                ..
+            } => Ok(None),
+            r => Err(self.unexpected(r)),
+        }
+    }
+
+    /// Pushes the value to the head of the list and returns the length of the list.
+    ///
+    /// The list is created if the key is absent.
+    pub fn lpush(&mut self, key: &str, value: Value) -> Result<usize, ClientError> {
+        self.count(message(Command::Lpush, key, Some(value)))
+    }
+
+    /// Removes the value at the head of the list. Returns None if there is no such key.
+    pub fn lpop(&mut self, key: &str) -> Result<Option<Value>, ClientError> {
+        self.applied(message(Command::Lpop, key, None))
+    }
+
+    /// Returns the values between two positions of the list, both are included.
+    ///
+    /// Negative positions count from the tail, so `lrange(key, 0, -1)` returns the whole list.
+    pub fn lrange(&mut self, key: &str, start: i64, stop: i64) -> Result<Vec<Value>, ClientError> {
+        let message = message(Command::Lrange, key, None).with_range(Some((start, stop)));
+        match self.applied(message)? {
+            Some(Value::List(v)) => Ok(v),
+            None => Ok(Vec::new()),
+            Some(v) => Err(ClientError::Protocol(format!("{:?}", v))),
+        }
+    }
+
+    /// Adds the member to the set. Returns false if it was already a member.
+    pub fn sadd(&mut self, key: &str, member: Value) -> Result<bool, ClientError> {
+        self.flag(message(Command::Sadd, key, Some(member)))
+    }
+
+    /// Removes the member from the set. Returns false if it wasn't a member.
+    pub fn srem(&mut self, key: &str, member: Value) -> Result<bool, ClientError> {
+        self.flag(message(Command::Srem, key, Some(member)))
+    }
+
+    /// Returns the members of the set in the order they were added.
+    pub fn smembers(&mut self, key: &str) -> Result<Vec<Value>, ClientError> {
+        match self.applied(message(Command::Smembers, key, None))? {
+            Some(Value::Set(v)) => Ok(v),
+            None => Ok(Vec::new()),
+            Some(v) => Err(ClientError::Protocol(format!("{:?}", v))),
+        }
+    }
+
+    /// Sets the field of the hash. Returns false if the field existed and only its value changed.
+    pub fn hset(&mut self, key: &str, field: &str, value: Value) -> Result<bool, ClientError> {
+        let message = message(Command::Hset, key, Some(value)).with_field(Some(field.to_string()));
+        self.flag(message)
+    }
+
+    /// Returns the value of the field or None if there is no such key or field.
+    pub fn hget(&mut self, key: &str, field: &str) -> Result<Option<Value>, ClientError> {
+        self.applied(message(Command::Hget, key, None).with_field(Some(field.to_string())))
+    }
+
+    /// Removes the field from the hash. Returns false if there was no such field.
+    pub fn hdel(&mut self, key: &str, field: &str) -> Result<bool, ClientError> {
+        self.flag(message(Command::Hdel, key, None).with_field(Some(field.to_string())))
+    }
+
+    /// Returns the fields of the hash with their values.
+    pub fn hgetall(&mut self, key: &str) -> Result<Vec<(String, Value)>, ClientError> {
+        match self.applied(message(Command::Hgetall, key, None))? {
+            Some(Value::Hash(v)) => Ok(v),
+            None => Ok(Vec::new()),
+            Some(v) => Err(ClientError::Protocol(format!("{:?}", v))),
+        }
+    }
+
+    /// Adds one to the number and returns the result. A missing key is None unless `create` starts it at zero.
+    pub fn incr(&mut self, key: &str, create: bool) -> Result<Option<Value>, ClientError> {
+        self.applied(message(Command::Inc, key, None).with_create(create))
+    }
+
+    /// Subtracts one from the number and returns the result, like [`Client::incr`].
+    pub fn decr(&mut self, key: &str, create: bool) -> Result<Option<Value>, ClientError> {
+        self.applied(message(Command::Dec, key, None).with_create(create))
+    }
+
+    /// Adds the amount to the number and returns the result, the number keeps its own type.
+    ///
+    /// A missing key starts from the zero of the amount's type if `create` is true.
+    pub fn incr_by(
+        &mut self,
+        key: &str,
+        amount: Value,
+        create: bool,
+    ) -> Result<Option<Value>, ClientError> {
+        self.applied(message(Command::Iby, key, Some(amount)).with_create(create))
+    }
+
+    /// Returns the item count and capacity of every pack, like `#1 12/1000,#2 8/1000`.
+    pub fn info(&mut self) -> Result<String, ClientError> {
+        match self.request(message(Command::Inf, "", None))? {
+            Reply {
+                code: Code::Success,
+                value: Some(Value::Text(t)),
+                ..
+            } => Ok(t),
+            r => Err(self.unexpected(r)),
+        }
+    }
+
+    /// Saves a snapshot on the server and returns the number of saved items.
+    pub fn save(&mut self) -> Result<usize, ClientError> {
+        self.count(message(Command::Sav, "", None))
+    }
+
+    /// Compacts the write log on the server and returns the number of items in it.
+    pub fn rewrite_log(&mut self) -> Result<usize, ClientError> {
+        self.count(message(Command::Rwl, "", None))
+    }
+
+    /// Closes the connection politely.
+    pub fn quit(mut self) -> Result<(), ClientError> {
+        if let Some(mut c) = self.connection.take() {
+            match self.protocol {
+                Protocol::Text => c.writer.write_all(b"QUIT\n")?,
+                Protocol::Binary => {
+                    binary::write_frame(&mut c.writer, &binary::encode_quit(self.next_id))?
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn expect_success(&mut self, message: Message) -> Result<(), ClientError> {
+        match self.request(message)?.code {
+            Code::Success => Ok(()),
+            code => Err(code.into()),
+        }
+    }
+
+    fn found(&mut self, message: Message) -> Result<bool, ClientError> {
+        match self.request(message)?.code {
+            Code::Success => Ok(true),
+            Code::NotFound => Ok(false),
+            code => Err(code.into()),
+        }
+    }
+
+    fn count(&mut self, message: Message) -> Result<usize, ClientError> {
+        match self.request(message)? {
+            Reply {
+                code: Code::Success,
+                value: Some(Value::LargeNumber(n)),
+                ..
+            } => Ok(n.max(0) as usize),
+            Reply {
+                code: Code::Success,
+                value: Some(Value::HugeNumber(n)),
+                ..
+            } => Ok(n.max(0) as usize),
+            r => Err(self.unexpected(r)),
+        }
+    }
+
+    /// Returns the value that a collection or counter command replied, or None if the key or the field is missing.
+    fn applied(&mut self, message: Message) -> Result<Option<Value>, ClientError> {
+        match self.request(message)? {
+            Reply {
+                code: Code::Success,
+                value: Some(v),
+                ..
+            } => Ok(Some(v)),
+            Reply {
+                code: Code::NotFound,
+                ..
+            } => Ok(None),
+            r => Err(self.unexpected(r)),
+        }
+    }
+
+    /// Returns the reply of every key of a multi-key command.
+    fn results(&mut self, message: Message) -> Result<Vec<Reply>, ClientError> {
+        let count = message.batch.len();
+        match self.request(message)? {
+            Reply {
+                code: Code::Success | Code::PackFull,
+                results,
+                ..
+            } if results.len() == count => Ok(results),
+            r => Err(self.unexpected(r)),
+        }
+    }
+
+    /// Returns true if a collection command changed one element, a missing key changes nothing.
+    fn flag(&mut self, message: Message) -> Result<bool, ClientError> {
+        match self.applied(message)? {
+            Some(Value::LargeNumber(n)) => Ok(n > 0),
+            None => Ok(false),
+            Some(v) => Err(ClientError::Protocol(format!("{:?}", v))),
+        }
+    }
+
+    fn unexpected(&self, reply: Reply) -> ClientError {
+        match reply.code {
+            Code::Success => ClientError::Protocol(format!("{:?}", reply.value)),
+            code => code.into(),
+        }
+    }
+
+    /// Sends the message and reads its reply over the open connection.
+    fn request(&mut self, message: Message) -> Result<Reply, ClientError> {
+        let result = match self.protocol {
+            Protocol::Text => self.exchange(&encode(&message)),
+            Protocol::Binary => self.exchange_frame(&message),
+        };
+        match &result {
+            Ok(Reply {
+                code: Code::MessageTooLong | Code::TooManyConnections,
+                ..
+            })
+            | Err(_) => {
+                // The server closes the connection after these replies,
+                // and a late reply of a timed out request would mix with the next one.
+                self.connection = None;
+            }
+            _ => {}
+        }
+        result
+    }
+
+    fn exchange(&mut self, message: &str) -> Result<Reply, ClientError> {
+        let connection = self.connection()?;
+        connection
+            .writer
+            .write_all(message.as_bytes())
+            .map_err(io_error)?;
+        let mut line = String::new();
+        match connection.reader.read_line(&mut line).map_err(io_error)? {
+            0 => Err(ClientError::Closed),
+            _ => Reply::try_from(line.as_str()),
+        }
+    }
+
+    fn exchange_frame(&mut self, message: &Message) -> Result<Reply, ClientError> {
+        let id = self.next_id;
+        self.next_id = self.next_id.wrapping_add(1);
+        let connection = self.connection()?;
+        binary::write_frame(&mut connection.writer, &binary::encode_request(id, message))
+            .map_err(io_error)?;
+        let body = match binary::read_frame(&mut connection.reader).map_err(io_error)? {
+            Some(Frame::Message(body)) => body,
+            Some(_) => return Err(ClientError::Protocol("frame is too long".to_string())),
+            None => return Err(ClientError::Closed),
+        };
+        match binary::decode_response(&body) {
+            Ok((i, response)) if i == id => Ok(response.into()),
+            Ok((i, _)) => Err(ClientError::Protocol(format!("reply of request {}", i))),
+            Err(e) => Err(ClientError::Protocol(e.to_string())),
+        }
+    }
+
+    fn connection(&mut self) -> Result<&mut Connection, ClientError> {
+        if self.connection.is_none() {
+            let stream = self.open()?;
+            stream.set_read_timeout(self.timeout)?;
+            stream.set_write_timeout(self.timeout)?;
+            stream.set_nodelay(true)?;
+            info!("Connected to {}", self.address);
+            let mut connection = Connection {
+                reader: BufReader::new(stream.try_clone()?),
+                writer: stream,
+            };
+            if self.protocol == Protocol::Binary {
+                handshake(&mut connection)?;
+            }
+            self.connection = Some(connection);
+        }
+        Ok(self.connection.as_mut().unwrap())
+    }
+
+    fn open(&self) -> Result<TcpStream, ClientError> {
+        let timeout = match self.timeout {
+            Some(t) => t,
+            None => return Ok(TcpStream::connect(&self.address)?),
+        };
+        let mut last = None;
+        for address in self.address.to_socket_addrs()? {
+            match TcpStream::connect_timeout(&address, timeout) {
+                Ok(s) => return Ok(s),
+                Err(e) => {
+                    warn!("{} couldn't connect, {}", address, e);
+                    last = Some(e);
+                }
+            }
+        }
+        Err(match last {
+            Some(e) => io_error(e),
+            None => ClientError::Protocol(self.address.clone()),
+        })
+    }
+}
+
+fn message(command: Command, key: &str, value: Option<Value>) -> Message {
+    Message::new(command, key.to_string(), value)
+}
+
+/// Asks the server to speak the binary protocol on the connection.
+fn handshake(connection: &mut Connection) -> Result<(), ClientError> {
+    connection
+        .writer
+        .write_all(&[MAGIC, VERSION])
+        .map_err(io_error)?;
+    let mut answer = [0_u8; 2];
+    connection
+        .reader
+        .read_exact(&mut answer)
+        .map_err(io_error)?;
+    match answer {
+        [MAGIC, VERSION] => Ok(()),
+        [MAGIC, _] => Err(ClientError::Protocol(format!(
+            "binary protocol v{} is not supported",
+            VERSION
+        ))),
+        _ => Err(ClientError::Protocol(format!("{:?}", answer))),
+    }
+}
+
+/// Writes the message in the text wire format, like `GET|server|` or `HSET|user|name|s|dory|`.
+fn encode(message: &Message) -> String {
+    let mut parts = vec![message.command.as_str().to_string()];
+    if !matches!(
+        message.command,
+        Command::Inf | Command::Sav | Command::Rwl | Command::Mget | Command::Mset | Command::Mdel
+    ) {
+        parts.push(escape(&message.key));
+    }
+    if let Some(u) = message.version {
+        parts.push(u.to_string());
+    }
+    if let Some(f) = &message.field {
+        parts.push(escape(f));
+    }
+    if let Some(v) = &message.value {
+        parts.extend(value_parts(v));
+    }
+    if let Some(ttl) = message.ttl {
+        parts.push(ttl.as_secs().to_string());
+    }
+    if let Some((start, stop)) = message.range {
+        parts.push(start.to_string());
+        parts.push(stop.to_string());
+    }
+    if message.create {
+        parts.push("new".to_string());
+    }
+    if message.versioned {
+        parts.push("uuid".to_string());
+    }
+    for m in &message.batch {
+        parts.push(escape(&m.key));
+        if let Some(v) = &m.value {
+            parts.extend(value_parts(v));
+        }
+    }
+    if message.atomic {
+        parts.push("all".to_string());
+    }
+    let mut message = parts.join("|");
+    message.push_str("|\n");
+    message
+}
+
+/// Returns the duration in whole seconds, rounded up.
+fn seconds(ttl: Duration) -> Duration {
+    Duration::from_secs(ttl.as_secs() + u64::from(ttl.subsec_nanos() > 0))
+}
+
+/// Separates the expired time limits from the other connection problems.
+fn io_error(e: std::io::Error) -> ClientError {
+    match e.kind() {
+        ErrorKind::WouldBlock | ErrorKind::TimedOut => ClientError::Timeout,
+        _ => ClientError::Io(e),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use dserver::constant::constant::{MAX_COLLECTION_LEN, MAX_ITEM};
+    use dserver::model::Random;
+    use dserver::{Server, ServerHandle};
+    use std::net::TcpListener;
+    use std::thread;
+
+    fn start() -> (ServerHandle, Client) {
+        let server = Server::new("127.0.0.1", 0).packs(4).start().unwrap();
+        let client = Client::new(server.local_addr().to_string()).timeout(Duration::from_secs(2));
+        (server, client)
+    }
+
+    #[test]
+    fn should_replies_parsed_to_code_and_value() {
+        assert_eq!(
+            Reply::try_from("200 Success|s|localhost|\r\n").unwrap(),
+            Reply {
+                code: Code::Success,
+                value: Some(Value::Text("localhost".to_string())),
+                version: None,
+                results: Vec::new()
+            }
+        );
+        assert_eq!(
+            Reply::try_from("404 NotFound|\r\n").unwrap(),
+            Reply {
+                code: Code::NotFound,
+                value: None,
+                version: None,
+                results: Vec::new()
+            }
+        );
+        assert_eq!(
+            Reply::try_from("200 Success|s|a\\|b\\n|\r\n").unwrap(),
+            Reply {
+                code: Code::Success,
+                value: Some(Value::Text("a|b\n".to_string())),
+                version: None,
+                results: Vec::new()
+            }
+        );
+        assert!(matches!(
+            Reply::try_from("HTTP/1.1 200 OK"),
+            Err(ClientError::Protocol(_))
+        ));
+        assert!(matches!(
+            Reply::try_from("999 Unknown|"),
+            Err(ClientError::Protocol(_))
+        ));
+    }
+
+    #[test]
+    fn should_typed_api_work_over_one_connection() {
+        let (_server, mut client) = start();
+        client.add("level", Value::ThinNumber(3)).unwrap();
+        client.add("pi", Value::LargeFloat(2.5)).unwrap();
+        assert_eq!(client.get("level").unwrap(), Some(Value::ThinNumber(3)));
+        assert_eq!(client.get("pi").unwrap(), Some(Value::LargeFloat(2.5)));
+        assert_eq!(client.get("none").unwrap(), None);
+
+        assert!(!client.add_if_absent("level", Value::ThinNumber(4)).unwrap());
+        assert!(client
+            .add_if_present("level", Value::ThinNumber(5))
+            .unwrap());
+        assert!(!client.add_if_present("none", Value::ThinNumber(5)).unwrap());
+
+        assert_eq!(client.ttl("level").unwrap(), Some(Ttl::Never));
+        assert!(client
+            .expire("level", Duration::from_millis(59_500))
+            .unwrap());
+        assert_eq!(
+            client.ttl("level").unwrap(),
+            Some(Ttl::Remaining(Duration::from_secs(60)))
+        );
+        assert!(client.persist("level").unwrap());
+        assert_eq!(client.ttl("none").unwrap(), None);
+
+        assert!(client.del("level").unwrap());
+        assert!(!client.del("level").unwrap());
+        assert!(client.info().unwrap().starts_with("#1 "));
+        client.quit().unwrap();
+    }
+
+    #[test]
+    fn should_binary_protocol_carry_any_text() {
+        let (server, _) = start();
+        let mut client = Client::new(server.local_addr().to_string())
+            .timeout(Duration::from_secs(2))
+            .protocol(Protocol::Binary)
+            .connect()
+            .unwrap();
+        let motd = Value::Text("a|b\r\nc".to_string());
+        client.add("motd", motd.clone()).unwrap();
+        assert_eq!(client.get("motd").unwrap(), Some(motd));
+        assert!(client.expire("motd", Duration::from_secs(30)).unwrap());
+        assert_eq!(
+            client.ttl("motd").unwrap(),
+            Some(Ttl::Remaining(Duration::from_secs(30)))
+        );
+        assert!(matches!(
+            client.add(&"k".repeat(20), Value::Logical(true)),
+            Err(ClientError::KeyTooLong)
+        ));
+        assert!(client.info().unwrap().starts_with("#1 "));
+        assert!(client.del("motd").unwrap());
+        client.quit().unwrap();
+    }
+
+    #[test]
+    fn should_collections_work_over_both_protocols() {
+        let (server, _) = start();
+        for protocol in [Protocol::Text, Protocol::Binary] {
+            let mut client = Client::new(server.local_addr().to_string())
+                .timeout(Duration::from_secs(2))
+                .protocol(protocol);
+            let on = Value::Text("a|b".to_string());
+            assert_eq!(client.lpush("queue", on.clone()).unwrap(), 1);
+            assert_eq!(client.lpush("queue", Value::ThinNumber(2)).unwrap(), 2);
+            assert_eq!(
+                client.lrange("queue", 0, -1).unwrap(),
+                vec![Value::ThinNumber(2), on.clone()]
+            );
+            assert_eq!(client.lpop("queue").unwrap(), Some(Value::ThinNumber(2)));
+            assert_eq!(client.lpop("queue").unwrap(), Some(on.clone()));
+            assert_eq!(client.lpop("queue").unwrap(), None);
+
+            assert!(client.sadd("tags", on.clone()).unwrap());
+            assert!(!client.sadd("tags", on.clone()).unwrap());
+            assert_eq!(client.smembers("tags").unwrap(), vec![on.clone()]);
+            assert!(client.srem("tags", on.clone()).unwrap());
+            assert!(client.smembers("tags").unwrap().is_empty());
+
+            assert!(client.hset("user", "name", on.clone()).unwrap());
+            assert!(!client.hset("user", "name", on.clone()).unwrap());
+            assert_eq!(client.hget("user", "name").unwrap(), Some(on.clone()));
+            assert_eq!(client.hget("user", "none").unwrap(), None);
+            assert_eq!(
+                client.hgetall("user").unwrap(),
+                vec![("name".to_string(), on.clone())]
+            );
+            assert!(matches!(
+                client.lpush("user", Value::Logical(true)),
+                Err(ClientError::WrongType)
+            ));
+            assert!(client.hdel("user", "name").unwrap());
+            assert!(!client.hdel("user", "name").unwrap());
+        }
+    }
+
+    #[test]
+    fn should_counters_work_over_both_protocols() {
+        let (server, _) = start();
+        for protocol in [Protocol::Text, Protocol::Binary] {
+            let mut client = Client::new(server.local_addr().to_string())
+                .timeout(Duration::from_secs(2))
+                .protocol(protocol);
+            let key = format!("{:?}", protocol);
+            assert_eq!(client.incr(&key, false).unwrap(), None);
+            assert_eq!(
+                client.incr(&key, true).unwrap(),
+                Some(Value::LargeNumber(1))
+            );
+            assert_eq!(
+                client.decr(&key, false).unwrap(),
+                Some(Value::LargeNumber(0))
+            );
+            assert_eq!(
+                client
+                    .incr_by(&key, Value::LargeNumber(i32::MAX), false)
+                    .unwrap(),
+                Some(Value::LargeNumber(i32::MAX))
+            );
+            assert!(matches!(
+                client.incr(&key, false),
+                Err(ClientError::Overflow)
+            ));
+            assert!(matches!(
+                client.incr_by(&key, Value::LargeFloat(0.5), false),
+                Err(ClientError::WrongType)
+            ));
+            assert!(matches!(
+                client.incr_by(&key, Value::Logical(true), false),
+                Err(ClientError::InvalidValue)
+            ));
+        }
+    }
+
+    #[test]
+    fn should_cas_detect_changes_over_both_protocols() {
+        let (server, _) = start();
+        for protocol in [Protocol::Text, Protocol::Binary] {
+            let mut client = Client::new(server.local_addr().to_string())
+                .timeout(Duration::from_secs(2))
+                .protocol(protocol);
+            let key = format!("{:?}", protocol);
+            client.add(&key, Value::Text("v1".to_string())).unwrap();
+            let (value, uuid) = client.get_with_uuid(&key).unwrap().unwrap();
+            assert_eq!(value, Value::Text("v1".to_string()));
+            let new_uuid = client
+                .cas(&key, uuid, Value::Text("v2".to_string()))
+                .unwrap()
+                .unwrap();
+            assert!(matches!(
+                client.cas(&key, uuid, Value::Text("v3".to_string())),
+                Err(ClientError::Conflict)
+            ));
+            assert_eq!(
+                client.get_with_uuid(&key).unwrap(),
+                Some((Value::Text("v2".to_string()), new_uuid))
+            );
+            assert_eq!(
+                client.cas("none", uuid, Value::Logical(true)).unwrap(),
+                None
+            );
+            assert_eq!(client.get_with_uuid("none").unwrap(), None);
+        }
+    }
+
+    #[test]
+    fn should_multi_key_commands_work_over_both_protocols() {
+        let (server, _) = start();
+        for protocol in [Protocol::Text, Protocol::Binary] {
+            let mut client = Client::new(server.local_addr().to_string())
+                .timeout(Duration::from_secs(2))
+                .protocol(protocol);
+            let on = Value::Text("a|b".to_string());
+            let results = client
+                .mset(&[("a", on.clone()), ("b", Value::ThinNumber(2))], true)
+                .unwrap();
+            assert!(results.iter().all(Result::is_ok));
+            assert!(matches!(
+                client.mset(
+                    &[
+                        ("c", Value::Logical(true)),
+                        ("d", Value::Text("x".repeat(100)))
+                    ],
+                    false,
+                ),
+                Err(ClientError::ValueTooLong)
+            ));
+            assert_eq!(client.get("c").unwrap(), None);
+            let results = client.mset(&[("c", Value::Logical(true))], false).unwrap();
+            assert!(matches!(results[..], [Ok(())]));
+            assert_eq!(
+                client.mget(&["b", "none", "a", "c"]).unwrap(),
+                vec![
+                    Some(Value::ThinNumber(2)),
+                    None,
+                    Some(on.clone()),
+                    Some(Value::Logical(true))
+                ]
+            );
+            assert_eq!(
+                client.mdel(&["a", "b", "c", "none"]).unwrap(),
+                vec![true, true, true, false]
+            );
+        }
+    }
+
+    #[test]
+    fn should_all_or_nothing_mset_tell_full_packs() {
+        let server = Server::new("127.0.0.1", 0).packs(1).start().unwrap();
+        for protocol in [Protocol::Text, Protocol::Binary] {
+            let mut client = Client::new(server.local_addr().to_string())
+                .timeout(Duration::from_secs(2))
+                .protocol(protocol);
+            if protocol == Protocol::Text {
+                for i in 0..MAX_ITEM {
+                    client
+                        .add(&format!("key{}", i), Value::Logical(true))
+                        .unwrap();
+                }
+            }
+            let results = client
+                .mset(
+                    &[
+                        ("key0", Value::Logical(false)),
+                        ("other", Value::Logical(false)),
+                    ],
+                    true,
+                )
+                .unwrap();
+            assert!(matches!(
+                results[..],
+                [Err(ClientError::PackFull), Err(ClientError::PackFull)]
+            ));
+            assert_eq!(client.get("key0").unwrap(), Some(Value::Logical(true)));
+        }
+    }
+
+    #[test]
+    fn should_full_collection_raise_an_error() {
+        let (_server, mut client) = start();
+        let limit = MAX_COLLECTION_LEN as i32;
+        for i in 0..limit {
+            client.sadd("numbers", Value::LargeNumber(i)).unwrap();
+        }
+        assert!(matches!(
+            client.sadd("numbers", Value::LargeNumber(limit)),
+            Err(ClientError::CollectionFull)
+        ));
+        assert!(!client.sadd("numbers", Value::LargeNumber(0)).unwrap());
+    }
+
+    #[test]
+    fn should_error_codes_mapped_to_client_errors() {
+        let (_server, mut client) = start();
+        let long_key = "k".repeat(20);
+        assert!(matches!(
+            client.add(&long_key, Value::Logical(true)),
+            Err(ClientError::KeyTooLong)
+        ));
+        assert!(matches!(
+            client.add("text", Value::Text("x".repeat(100))),
+            Err(ClientError::ValueTooLong)
+        ));
+        assert!(matches!(client.save(), Err(ClientError::Persistence)));
+        // The connection is still usable after the errors.
+        client.add("debug", Value::Logical(false)).unwrap();
+    }
+
+    #[test]
+    fn should_any_text_survive_add_then_get() {
+        let (_server, mut client) = start();
+        let alphabet = ['|', '\\', '\r', '\n', 'n', 'r', ' ', 'a', '🦀', '\0'];
+        let mut random = Random::new(0x9E3779B97F4A7C15);
+        for _ in 0..300 {
+            let text = random.text(&alphabet, 16);
+            let key: String = text.chars().take(4).collect();
+            client.add(&key, Value::Text(text.clone())).unwrap();
+            assert_eq!(client.get(&key).unwrap(), Some(Value::Text(text)));
+        }
+    }
+
+    #[test]
+    fn should_late_reply_raise_timeout_and_reconnect() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        thread::spawn(move || {
+            // The first connection never replies, the second one replies to one message.
+            let (_silent, _) = listener.accept().unwrap();
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut line = String::new();
+            reader.read_line(&mut line).unwrap();
+            (&stream).write_all(b"404 NotFound|\r\n").unwrap();
+            thread::sleep(Duration::from_secs(1));
+        });
+
+        let mut client = Client::new(address.to_string()).timeout(Duration::from_millis(100));
+        assert!(matches!(client.get("debug"), Err(ClientError::Timeout)));
+        assert_eq!(client.get("debug").unwrap(), None);
+    }
+}
@@ -0,0 +1,74 @@
+use dserver::server::response::Code;
+use thiserror::Error;
+
+/// Errors of the client. Error codes of the server have their own variants.
+#[derive(Debug, Error)]
+pub enum ClientError {
+    #[error("Connection problem, {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Server didn't reply in time")]
+    Timeout,
+    #[error("Server closed the connection")]
+    Closed,
+    #[error("Reply couldn't be understood `{0}`")]
+    Protocol(String),
+    #[error("Key already exists")]
+    KeyExists,
+    #[error("Key holds another kind of value")]
+    WrongType,
+    #[error("Collection is full")]
+    CollectionFull,
+    #[error("Pack of the key has no room")]
+    PackFull,
+    #[error("Result doesn't fit in the type of the number")]
+    Overflow,
+    #[error("Item was changed since its uuid was read")]
+    Conflict,
+    #[error("Key is too long")]
+    KeyTooLong,
+    #[error("Value is too long")]
+    ValueTooLong,
+    #[error("Value is invalid for its type")]
+    InvalidValue,
+    #[error("Value is out of the range of its type")]
+    ValueOutOfRange,
+    #[error("Server doesn't know the type")]
+    UnknownType,
+    #[error("Server doesn't know the command")]
+    UnknownCommand,
+    #[error("Message couldn't be parsed by the server, {0}")]
+    Rejected(Code),
+    #[error("Server couldn't save the data")]
+    Persistence,
+    #[error("Server has too many connections")]
+    TooManyConnections,
+    #[error("Server error")]
+    Server,
+}
+
+impl From<Code> for ClientError {
+    fn from(code: Code) -> Self {
+        match code {
+            Code::KeyExists => Self::KeyExists,
+            Code::WrongType => Self::WrongType,
+            Code::CollectionFull => Self::CollectionFull,
+            Code::PackFull => Self::PackFull,
+            Code::Overflow => Self::Overflow,
+            Code::Conflict => Self::Conflict,
+            Code::KeyTooLong => Self::KeyTooLong,
+            Code::ValueTooLong => Self::ValueTooLong,
+            Code::InvalidValue => Self::InvalidValue,
+            Code::ValueOutOfRange => Self::ValueOutOfRange,
+            Code::UnknownType => Self::UnknownType,
+            Code::UnknownCommand => Self::UnknownCommand,
+            Code::Persistence => Self::Persistence,
+            Code::TooManyConnections => Self::TooManyConnections,
+            Code::EmptyMessage
+            | Code::Encoding
+            | Code::Pattern
+            | Code::MessageTooLong
+            | Code::Escape => Self::Rejected(code),
+            Code::Success | Code::NotFound | Code::Error => Self::Server,
+        }
+    }
+}
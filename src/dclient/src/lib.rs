@@ -0,0 +1,10 @@
+//! Client library of the dory cache server.
+//!
+//! [`Client`] writes the commands in the wire format of the server
+//! and turns the replies back into [`dserver::Value`]s and [`ClientError`]s.
+
+pub mod client;
+pub mod derror;
+
+pub use client::{Client, Protocol, Reply, Ttl};
+pub use derror::client_error::ClientError;
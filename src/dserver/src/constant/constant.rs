@@ -1,3 +1,16 @@
 pub const MAX_ITEM: u16 = 1000;
 pub const MAX_KEY_LEN: usize = 16;
 pub const MAX_VALUE_LEN: usize = 64;
+pub const MAX_BYTES_LEN: usize = 64;
+pub const MAX_COLLECTION_LEN: usize = 64;
+pub const MAX_MESSAGE_LEN: usize = 4096;
+pub const DEFAULT_WORKER_COUNT: usize = 4;
+pub const DEFAULT_MAX_CONNECTIONS: usize = 64;
+pub const BASIC_PACK_COUNT: u32 = 1;
+pub const ADVANCED_PACK_COUNT: u32 = 16;
+pub const MAX_TTL_SECS: u64 = 3_153_600_000;
+pub const REAPER_INTERVAL_MS: u64 = 1000;
+pub const REAPER_BATCH: usize = 64;
+pub const DEFAULT_SNAPSHOT_FILE: &str = "dory.snapshot";
+pub const SNAPSHOT_INTERVAL_SECS: u64 = 300;
+pub const DEFAULT_WRITE_LOG_FILE: &str = "dory.wal";
@@ -0,0 +1,17 @@
+use thiserror::Error;
+
+#[derive(Debug, Error, PartialEq)]
+pub enum CodecError {
+    #[error("Unexpected end of data")]
+    UnexpectedEnd,
+    #[error("Unknown value tag {0}")]
+    UnknownTag(u8),
+    #[error("Encoding problem")]
+    Encoding,
+    #[error("Data continues after the last record")]
+    TrailingBytes,
+    #[error("Value is out of range")]
+    Range,
+    #[error("Invalid item, {0}")]
+    Item(String),
+}
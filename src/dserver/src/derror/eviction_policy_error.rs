@@ -0,0 +1,7 @@
+use thiserror::Error;
+
+#[derive(Debug, Error, PartialEq)]
+pub enum EvictionPolicyError {
+    #[error("Unknown eviction policy `{0}`")]
+    Unknown(String),
+}
@@ -0,0 +1,7 @@
+use thiserror::Error;
+
+#[derive(Debug, Error, PartialEq)]
+pub enum FsyncPolicyError {
+    #[error("Unknown fsync policy `{0}`")]
+    Unknown(String),
+}
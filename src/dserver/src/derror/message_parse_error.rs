@@ -1,3 +1,4 @@
+use crate::derror::command_error::CommandError;
 use std::str::Utf8Error;
 use thiserror::Error;
 
@@ -13,6 +14,32 @@ pub enum MessageParseError {
     Command,
     #[error("Pattern is wrong")]
     Pattern,
+    #[error("Unknown type `{0}`")]
+    UnknownType(String),
+    #[error("Invalid value `{got}` for type `{expected}`")]
+    InvalidValue { expected: String, got: String },
+    #[error("Value `{got}` is out of range for type `{expected}`")]
+    ValueOutOfRange { expected: String, got: String },
+    #[error("Value is too long")]
+    ValueTooLong,
+    #[error("Invalid escape sequence or raw line break")]
+    Escape,
+}
+
+impl MessageParseError {
+    pub fn invalid_value(expected: &str, got: &str) -> Self {
+        Self::InvalidValue {
+            expected: expected.to_string(),
+            got: got.to_string(),
+        }
+    }
+
+    pub fn out_of_range(expected: &str, got: &str) -> Self {
+        Self::ValueOutOfRange {
+            expected: expected.to_string(),
+            got: got.to_string(),
+        }
+    }
 }
 
 impl From<Utf8Error> for MessageParseError {
@@ -20,3 +47,9 @@ impl From<Utf8Error> for MessageParseError {
         Self::Encoding
     }
 }
+
+impl From<CommandError> for MessageParseError {
+    fn from(_: CommandError) -> Self {
+        Self::Command
+    }
+}
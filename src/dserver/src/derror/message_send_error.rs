@@ -1,3 +1,4 @@
+use crate::derror::new_item_error::NewItemError;
 use thiserror::Error;
 
 #[derive(Debug, Error, PartialEq)]
@@ -8,4 +9,12 @@ pub enum MessageSendError {
     Get,
     #[error("Message couldn't delete from pack")]
     Del,
-}
\ No newline at end of file
+    #[error("Message couldn't change the expiry of item")]
+    Expire,
+    #[error("Message couldn't update the collection of item")]
+    Update,
+    #[error("Message isn't sent to a pack")]
+    NotPackCommand,
+    #[error("Item couldn't create, {0}")]
+    Item(#[from] NewItemError),
+}
@@ -1,4 +1,12 @@
+pub mod codec_error;
 pub mod command_error;
+pub mod eviction_policy_error;
+pub mod fsync_policy_error;
 pub mod message_parse_error;
 pub mod message_send_error;
 pub mod new_item_error;
+pub mod operation_error;
+pub mod resp_error;
+pub mod server_error;
+pub mod snapshot_error;
+pub mod write_log_error;
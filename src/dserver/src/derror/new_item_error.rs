@@ -1,9 +1,17 @@
 use thiserror::Error;
 
-#[derive(Debug, Error)]
+#[derive(Debug, Error, PartialEq)]
 pub enum NewItemError {
     #[error("Key name is too long.")]
     InvalidKeyLen,
     #[error("Value is too long.")]
     InvalidValueLen,
+    #[error("Collection has too many elements.")]
+    TooManyElements,
+    #[error("Collections can't be nested.")]
+    NestedCollection,
+    #[error("Collection has the same element twice.")]
+    DuplicateElement,
+    #[error("Value is not a number.")]
+    NotNumber,
 }
@@ -0,0 +1,18 @@
+use crate::derror::new_item_error::NewItemError;
+use thiserror::Error;
+
+#[derive(Debug, Error, PartialEq)]
+pub enum OperationError {
+    #[error("There is no such key or field")]
+    NotFound,
+    #[error("Value of the key is not the kind of collection that the command works on")]
+    WrongType,
+    #[error("Collection has no room for another element")]
+    CollectionFull,
+    #[error("Result doesn't fit in the type of the number")]
+    Overflow,
+    #[error("Capacity of the pack is full")]
+    CapacityFull,
+    #[error("Element is invalid, {0}")]
+    Item(#[from] NewItemError),
+}
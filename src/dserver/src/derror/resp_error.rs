@@ -0,0 +1,9 @@
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum RespError {
+    #[error("Connection problem, {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Protocol error, {0}")]
+    Protocol(&'static str),
+}
@@ -0,0 +1,13 @@
+use crate::derror::snapshot_error::SnapshotError;
+use crate::derror::write_log_error::WriteLogError;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum ServerError {
+    #[error("Server couldn't listen, {0}")]
+    Bind(#[from] std::io::Error),
+    #[error("Snapshot couldn't load, {0}")]
+    Snapshot(#[from] SnapshotError),
+    #[error("Write log couldn't load, {0}")]
+    WriteLog(#[from] WriteLogError),
+}
@@ -0,0 +1,18 @@
+use crate::derror::codec_error::CodecError;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum SnapshotError {
+    #[error("Snapshot file couldn't be used, {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Not a snapshot file")]
+    Magic,
+    #[error("Unsupported snapshot version {0}")]
+    Version(u8),
+    #[error("Snapshot checksum doesn't match")]
+    Checksum,
+    #[error("Snapshot is corrupted, {0}")]
+    Codec(#[from] CodecError),
+    #[error("Snapshot is not enabled")]
+    Disabled,
+}
@@ -0,0 +1,18 @@
+use crate::derror::codec_error::CodecError;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum WriteLogError {
+    #[error("Write log file couldn't be used, {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Not a write log file")]
+    Magic,
+    #[error("Unsupported write log version {0}")]
+    Version(u8),
+    #[error("Write log is corrupted, {0}")]
+    Codec(#[from] CodecError),
+    #[error("Unknown write log record {0}")]
+    UnknownRecord(u8),
+    #[error("Write log is not enabled")]
+    Disabled,
+}
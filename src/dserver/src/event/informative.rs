@@ -1,13 +1,34 @@
-use super::super::model::Item;
+use super::super::model::{Item, Value};
 use std::sync::Arc;
+use std::time::Duration;
 use uuid::Uuid;
 
 /// It is the enum that contains the event definitions to be used in the receiver channel.
-#[derive(Debug)]
+///
+/// The same events are also sent back to the requester as the result of its command.
+#[derive(Debug, Clone)]
+#[allow(dead_code)]
 pub enum InformativeEvent {
     Added(Uuid),
+    Updated(Uuid),
+    KeyExists,
     AddError,
     NotFound,
     Found(Arc<Item>),
+    /// EXS found the key, its item is left as it was.
+    Present,
     GetError,
+    Deleted(Uuid),
+    DelError,
+    ExpiryChanged(Uuid),
+    TimeToLive(Option<Duration>),
+    Expired(String),
+    Evicted(String),
+    /// The reply of a collection command.
+    Applied(Value),
+    WrongType,
+    CollectionFull,
+    Overflow,
+    /// CAS found the item with another uuid.
+    Conflict,
 }
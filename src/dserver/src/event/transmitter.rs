@@ -1,8 +1,17 @@
-use super::super::model::{Candidate, Search};
+use super::super::model::{Batch, Candidate, Expiry, Search, Update};
 
 /// It is the enum that contains the event definitions to be used in the transmitter channel.
 #[derive(Debug)]
+#[allow(clippy::enum_variant_names)]
 pub enum TransmitterEvent {
     AddNewItem(Candidate),
     GetItem(Search),
+    DeleteItem(Search),
+    ExpireItem(Expiry),
+    GetTtl(Search),
+    /// Tells whether the key has an item without a use of it.
+    CheckItem(Search),
+    UpdateItem(Update),
+    /// The items of an all-or-nothing MSET for one pack.
+    PutBatch(Batch),
 }
@@ -0,0 +1,28 @@
+//! Dory is a lightweight key-value cache.
+//!
+//! Items live in packs and every pack is owned by its own worker thread.
+//! The server can be embedded and started in the process.
+//!
+//! ```
+//! use dserver::{EvictionPolicy, Server};
+//!
+//! let handle = Server::new("127.0.0.1", 0)
+//!     .packs(4)
+//!     .eviction(EvictionPolicy::Lru)
+//!     .start()
+//!     .unwrap();
+//! println!("Dory listens {}", handle.local_addr());
+//! handle.stop();
+//! ```
+
+pub mod constant;
+pub mod derror;
+pub mod event;
+pub mod model;
+pub mod persistence;
+pub mod server;
+
+pub use event::{InformativeEvent, TransmitterEvent};
+pub use model::{Candidate, EvictionPolicy, Item, Pack, Value};
+pub use persistence::fsync_policy::FsyncPolicy;
+pub use server::server::{Server, ServerHandle};
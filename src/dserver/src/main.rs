@@ -1,31 +1,39 @@
-use crate::server::server::Server;
-use event::{InformativeEvent, TransmitterEvent};
+use dserver::constant::constant::{
+    ADVANCED_PACK_COUNT, DEFAULT_MAX_CONNECTIONS, DEFAULT_SNAPSHOT_FILE, DEFAULT_WORKER_COUNT,
+    DEFAULT_WRITE_LOG_FILE, SNAPSHOT_INTERVAL_SECS,
+};
+use dserver::{EvictionPolicy, FsyncPolicy, Server};
 use log::{error, info};
-use model::{Candidate, Item, Pack, Value};
 use std::env;
 use std::process::exit;
-
-mod constant;
-mod derror;
-mod event;
-mod model;
-mod server;
+use std::str::FromStr;
+use std::time::Duration;
 
 fn main() {
     env_logger::init();
 
     let args: Vec<String> = env::args().collect();
     match args.len() {
-        2 => {
+        2..=6 => {
             let command = &args[1];
+            let workers = number_arg(&args, 2, DEFAULT_WORKER_COUNT);
+            let max_connections = number_arg(&args, 3, DEFAULT_MAX_CONNECTIONS);
+            let policy = policy_arg(&args, 4);
+            let fsync = fsync_arg(&args, 5);
             match command.to_lowercase().as_str() {
                 "basic" => {
-                    basic_mode();
+                    basic_mode(workers, max_connections, policy, fsync);
                 }
-                _ => {
-                    error!("Understandable command.");
-                    exit(1);
+                "advanced" => {
+                    pack_mode(ADVANCED_PACK_COUNT, workers, max_connections, policy, fsync);
                 }
+                mode => match mode.parse::<u32>() {
+                    Ok(packs) => pack_mode(packs, workers, max_connections, policy, fsync),
+                    Err(_) => {
+                        error!("Understandable command.");
+                        exit(1);
+                    }
+                },
             }
         }
         _ => {
@@ -35,9 +43,72 @@ fn main() {
     }
 }
 
-fn basic_mode() {
+/// Returns the optional numeric argument at the index or the default value.
+fn number_arg(args: &[String], index: usize, default: usize) -> usize {
+    match args.get(index) {
+        Some(a) => a.parse::<usize>().unwrap_or_else(|_| {
+            error!("{} is not a number.", a);
+            exit(1);
+        }),
+        None => default,
+    }
+}
+
+/// Returns the optional eviction policy argument at the index or the default policy.
+fn policy_arg(args: &[String], index: usize) -> EvictionPolicy {
+    match args.get(index) {
+        Some(a) => EvictionPolicy::from_str(a).unwrap_or_else(|e| {
+            error!("{}", e);
+            exit(1);
+        }),
+        None => EvictionPolicy::default(),
+    }
+}
+
+/// Returns the optional fsync policy argument of the write log at the index or the default policy.
+fn fsync_arg(args: &[String], index: usize) -> FsyncPolicy {
+    match args.get(index) {
+        Some(a) => FsyncPolicy::from_str(a).unwrap_or_else(|e| {
+            error!("{}", e);
+            exit(1);
+        }),
+        None => FsyncPolicy::default(),
+    }
+}
+
+fn basic_mode(workers: usize, max_connections: usize, policy: EvictionPolicy, fsync: FsyncPolicy) {
     info!("Basic mode is starting.");
-    let alpha = Server::new("0.0.0.0", 5555_u16);
+    let alpha = Server::new("0.0.0.0", 5555_u16)
+        .eviction(policy)
+        .workers(workers)
+        .max_connections(max_connections)
+        .snapshot(
+            DEFAULT_SNAPSHOT_FILE,
+            Duration::from_secs(SNAPSHOT_INTERVAL_SECS),
+        )
+        .write_log(DEFAULT_WRITE_LOG_FILE, fsync);
+    alpha.run();
+    info!("Simulation completed.");
+}
+
+fn pack_mode(
+    packs: u32,
+    workers: usize,
+    max_connections: usize,
+    policy: EvictionPolicy,
+    fsync: FsyncPolicy,
+) {
+    info!("Mode with {} packs is starting.", packs);
+    let alpha = Server::new("0.0.0.0", 5555_u16)
+        .packs(packs)
+        .eviction(policy)
+        .workers(workers)
+        .max_connections(max_connections)
+        .snapshot(
+            DEFAULT_SNAPSHOT_FILE,
+            Duration::from_secs(SNAPSHOT_INTERVAL_SECS),
+        )
+        .write_log(DEFAULT_WRITE_LOG_FILE, fsync);
     alpha.run();
     info!("Simulation completed.");
 }
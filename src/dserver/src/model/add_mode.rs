@@ -0,0 +1,25 @@
+use super::Command;
+use uuid::Uuid;
+
+/// Decides how an item is added to the pack when its key is already there or not.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum AddMode {
+    /// Adds the item or replaces the value of the existing key.
+    Upsert,
+    /// Adds the item only if the key is not in the pack.
+    IfAbsent,
+    /// Replaces the value only if the key is already in the pack.
+    IfPresent,
+    /// Replaces the value only if the item of the key still has the uuid.
+    IfVersion(Uuid),
+}
+
+impl From<&Command> for AddMode {
+    fn from(command: &Command) -> Self {
+        match command {
+            Command::Anx => Self::IfAbsent,
+            Command::Axx => Self::IfPresent,
+            _ => Self::Upsert,
+        }
+    }
+}
@@ -0,0 +1,17 @@
+use super::{Item, Pack};
+use crate::InformativeEvent;
+use crossbeam::channel::{Receiver, Sender};
+use std::sync::{Arc, Mutex};
+
+/// The items of an all-or-nothing MSET that belong to the same pack.
+///
+/// The worker tells over `ready` whether the pack has room for all of them and puts them only if the decision is true.
+/// The result of every item is sent back over the responder channel of the requester, in the order of the items.
+#[derive(Debug)]
+pub struct Batch {
+    pub pack: Arc<Mutex<Pack>>,
+    pub items: Vec<Item>,
+    pub ready: Sender<bool>,
+    pub decision: Receiver<bool>,
+    pub responder: Sender<InformativeEvent>,
+}
@@ -1,9 +1,15 @@
-use super::{Item, Pack};
+use super::{AddMode, Item, Pack};
+use crate::InformativeEvent;
+use crossbeam::channel::Sender;
 use std::sync::{Arc, Mutex};
 
 /// Carries candidate object information to be added to the package.
+///
+/// The result of the operation is sent back over the responder channel of the requester.
 #[derive(Debug)]
 pub struct Candidate {
     pub pack: Arc<Mutex<Pack>>,
     pub object: Item,
+    pub mode: AddMode,
+    pub responder: Sender<InformativeEvent>,
 }
@@ -5,8 +5,106 @@ use std::str::FromStr;
 #[derive(Debug, PartialEq, Clone)]
 pub enum Command {
     Add,
+    /// Adds only if the key is absent
+    Anx,
+    /// Adds only if the key is present
+    Axx,
     Get,
     Del,
+    Inf,
+    /// Sets the time to live of a key
+    Exp,
+    /// Returns the remaining time to live of a key
+    Ttl,
+    /// Removes the expiry of a key
+    Prs,
+    /// Saves a snapshot of all packs
+    Sav,
+    /// Rewrites the write log with the current items
+    Rwl,
+    /// Pushes an element to the head of a list
+    Lpush,
+    /// Removes the element at the head of a list
+    Lpop,
+    /// Returns the elements of a list between two positions
+    Lrange,
+    /// Adds a member to a set
+    Sadd,
+    /// Removes a member from a set
+    Srem,
+    /// Returns all members of a set
+    Smembers,
+    /// Sets a field of a hash
+    Hset,
+    /// Returns a field of a hash
+    Hget,
+    /// Removes a field from a hash
+    Hdel,
+    /// Returns all fields of a hash
+    Hgetall,
+    /// Increments a number by one
+    Inc,
+    /// Decrements a number by one
+    Dec,
+    /// Increments a number by the given amount
+    Iby,
+    /// Replaces the value only if the item still has the given uuid
+    Cas,
+    /// Returns the values of many keys
+    Mget,
+    /// Adds the values of many keys, optionally all or nothing
+    Mset,
+    /// Deletes many keys
+    Mdel,
+    /// Tells whether a key has an item, it is not a use of the item for eviction
+    Exs,
+}
+
+impl Command {
+    /// Returns the name of the command in the text protocol.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Add => "ADD",
+            Self::Anx => "ANX",
+            Self::Axx => "AXX",
+            Self::Get => "GET",
+            Self::Del => "DEL",
+            Self::Inf => "INF",
+            Self::Exp => "EXP",
+            Self::Ttl => "TTL",
+            Self::Prs => "PRS",
+            Self::Sav => "SAV",
+            Self::Rwl => "RWL",
+            Self::Lpush => "LPUSH",
+            Self::Lpop => "LPOP",
+            Self::Lrange => "LRANGE",
+            Self::Sadd => "SADD",
+            Self::Srem => "SREM",
+            Self::Smembers => "SMEMBERS",
+            Self::Hset => "HSET",
+            Self::Hget => "HGET",
+            Self::Hdel => "HDEL",
+            Self::Hgetall => "HGETALL",
+            Self::Inc => "INC",
+            Self::Dec => "DEC",
+            Self::Iby => "IBY",
+            Self::Cas => "CAS",
+            Self::Mget => "MGET",
+            Self::Mset => "MSET",
+            Self::Mdel => "MDEL",
+            Self::Exs => "EXS",
+        }
+    }
+
+    /// Returns the command that a multi-key command runs for every key.
+    pub fn per_key(&self) -> Option<Command> {
+        match self {
+            Self::Mget => Some(Self::Get),
+            Self::Mset => Some(Self::Add),
+            Self::Mdel => Some(Self::Del),
+            _ => None,
+        }
+    }
 }
 
 impl FromStr for Command {
@@ -15,8 +113,34 @@ impl FromStr for Command {
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         match s {
             "ADD" => Ok(Self::Add),
+            "ANX" => Ok(Self::Anx),
+            "AXX" => Ok(Self::Axx),
             "GET" => Ok(Self::Get),
             "DEL" => Ok(Self::Del),
+            "INF" => Ok(Self::Inf),
+            "EXP" => Ok(Self::Exp),
+            "TTL" => Ok(Self::Ttl),
+            "PRS" => Ok(Self::Prs),
+            "SAV" => Ok(Self::Sav),
+            "RWL" => Ok(Self::Rwl),
+            "LPUSH" => Ok(Self::Lpush),
+            "LPOP" => Ok(Self::Lpop),
+            "LRANGE" => Ok(Self::Lrange),
+            "SADD" => Ok(Self::Sadd),
+            "SREM" => Ok(Self::Srem),
+            "SMEMBERS" => Ok(Self::Smembers),
+            "HSET" => Ok(Self::Hset),
+            "HGET" => Ok(Self::Hget),
+            "HDEL" => Ok(Self::Hdel),
+            "HGETALL" => Ok(Self::Hgetall),
+            "INC" => Ok(Self::Inc),
+            "DEC" => Ok(Self::Dec),
+            "IBY" => Ok(Self::Iby),
+            "CAS" => Ok(Self::Cas),
+            "MGET" => Ok(Self::Mget),
+            "MSET" => Ok(Self::Mset),
+            "MDEL" => Ok(Self::Mdel),
+            "EXS" => Ok(Self::Exs),
             _ => Err(CommandError::Unknown),
         }
     }
@@ -0,0 +1,52 @@
+use crate::derror::eviction_policy_error::EvictionPolicyError;
+use std::str::FromStr;
+
+/// Decides which item leaves a full pack to make room for a new one.
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub enum EvictionPolicy {
+    /// New keys are not accepted while the pack is full.
+    #[default]
+    Reject,
+    /// The least recently used item is evicted.
+    Lru,
+    /// The least frequently used item is evicted.
+    Lfu,
+    /// The oldest item is evicted.
+    Fifo,
+    /// A random item is evicted.
+    Random,
+}
+
+impl FromStr for EvictionPolicy {
+    type Err = EvictionPolicyError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "reject" => Ok(Self::Reject),
+            "lru" => Ok(Self::Lru),
+            "lfu" => Ok(Self::Lfu),
+            "fifo" => Ok(Self::Fifo),
+            "random" => Ok(Self::Random),
+            _ => Err(EvictionPolicyError::Unknown(s.to_string())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn should_policy_names_could_be_parse() {
+        assert_eq!(
+            EvictionPolicy::from_str("LRU").unwrap(),
+            EvictionPolicy::Lru
+        );
+        assert_eq!(
+            EvictionPolicy::from_str("fifo").unwrap(),
+            EvictionPolicy::Fifo
+        );
+        assert_eq!(EvictionPolicy::default(), EvictionPolicy::Reject);
+        assert!(EvictionPolicy::from_str("mru").is_err());
+    }
+}
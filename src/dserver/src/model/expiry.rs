@@ -0,0 +1,16 @@
+use super::Pack;
+use crate::InformativeEvent;
+use crossbeam::channel::Sender;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Carries the new lifetime of a key's item.
+///
+/// The item never expires if the ttl is None.
+#[derive(Debug)]
+pub struct Expiry {
+    pub pack: Arc<Mutex<Pack>>,
+    pub key: String,
+    pub ttl: Option<Duration>,
+    pub responder: Sender<InformativeEvent>,
+}
@@ -0,0 +1,234 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+const SECONDS_PER_DAY: u64 = 86_400;
+/// 9999-12-31T23:59:59Z, the last second that the text form of a timestamp can show.
+pub const MAX_TIMESTAMP_SECS: u64 = 253_402_300_799;
+
+/// Returns the standard base64 form of the bytes, with padding.
+pub fn encode_base64(bytes: &[u8]) -> String {
+    let mut text = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0_u32, |n, (i, b)| n | u32::from(*b) << (16 - 8 * i));
+        for i in 0..4 {
+            match i <= chunk.len() {
+                true => text.push(BASE64[(n >> (18 - 6 * i) & 0x3f) as usize] as char),
+                false => text.push('='),
+            }
+        }
+    }
+    text
+}
+
+/// Reads the standard base64 form, padding is required.
+pub fn decode_base64(text: &str) -> Option<Vec<u8>> {
+    let text = text.as_bytes();
+    if !text.len().is_multiple_of(4) {
+        return None;
+    }
+    let mut bytes = Vec::with_capacity(text.len() / 4 * 3);
+    for (index, chunk) in text.chunks(4).enumerate() {
+        let last = index == text.len() / 4 - 1;
+        let padding = chunk.iter().rev().take_while(|c| **c == b'=').count();
+        if padding > 2 || (padding > 0 && !last) {
+            return None;
+        }
+        let mut n = 0_u32;
+        for (i, c) in chunk[..4 - padding].iter().enumerate() {
+            let sextet = BASE64.iter().position(|b| b == c)? as u32;
+            n |= sextet << (18 - 6 * i);
+        }
+        for i in 0..3 - padding {
+            bytes.push((n >> (16 - 8 * i)) as u8);
+        }
+    }
+    Some(bytes)
+}
+
+/// Returns the UTC time in RFC 3339 form like `2024-05-01T12:30:00.25Z`.
+///
+/// Times before the unix epoch are shown as the epoch.
+pub fn format_timestamp(time: SystemTime) -> String {
+    let since = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let (days, seconds) = (
+        since.as_secs() / SECONDS_PER_DAY,
+        since.as_secs() % SECONDS_PER_DAY,
+    );
+    let (year, month, day) = civil_from_days(days as i64);
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}{}Z",
+        year,
+        month,
+        day,
+        seconds / 3600,
+        seconds % 3600 / 60,
+        seconds % 60,
+        fraction(since.subsec_nanos())
+    )
+}
+
+/// Reads a UTC time in RFC 3339 form between the years 1970 and 9999.
+///
+/// The fraction of the second is optional and can have up to nine digits. The time zone must be `Z`.
+pub fn parse_timestamp(text: &str) -> Option<SystemTime> {
+    let (date_time, nanos) = match text.strip_suffix('Z')?.split_once('.') {
+        Some((d, f)) => (d, parse_fraction(f)?),
+        None => (text.strip_suffix('Z')?, 0),
+    };
+    let b = date_time.as_bytes();
+    if b.len() != 19
+        || b[4] != b'-'
+        || b[7] != b'-'
+        || b[10] != b'T'
+        || b[13] != b':'
+        || b[16] != b':'
+    {
+        return None;
+    }
+    let year = digits(&date_time[0..4])?;
+    let month = digits(&date_time[5..7])? as u32;
+    let day = digits(&date_time[8..10])? as u32;
+    let (hour, minute, second) = (
+        digits(&date_time[11..13])?,
+        digits(&date_time[14..16])?,
+        digits(&date_time[17..19])?,
+    );
+    if year < 1970 || hour > 23 || minute > 59 || second > 59 {
+        return None;
+    }
+    let days = days_from_civil(year as i64, month, day);
+    if civil_from_days(days) != (year as i64, month, day) {
+        return None;
+    }
+    let seconds = days as u64 * SECONDS_PER_DAY + hour * 3600 + minute * 60 + second;
+    UNIX_EPOCH.checked_add(Duration::new(seconds, nanos))
+}
+
+/// Returns the duration in seconds like `90` or `1.5`.
+pub fn format_duration(duration: Duration) -> String {
+    format!(
+        "{}{}",
+        duration.as_secs(),
+        fraction(duration.subsec_nanos())
+    )
+}
+
+/// Reads a duration in seconds, the fraction can have up to nine digits.
+pub fn parse_duration(text: &str) -> Option<Duration> {
+    let (seconds, nanos) = match text.split_once('.') {
+        Some((s, f)) => (s, parse_fraction(f)?),
+        None => (text, 0),
+    };
+    if seconds.is_empty() || seconds.len() > 20 {
+        return None;
+    }
+    Some(Duration::new(digits(seconds)?, nanos))
+}
+
+/// Returns the nanoseconds as a decimal fraction without trailing zeros, or nothing if they are zero.
+fn fraction(nanos: u32) -> String {
+    match nanos {
+        0 => String::new(),
+        n => format!(".{:09}", n).trim_end_matches('0').to_string(),
+    }
+}
+
+fn parse_fraction(text: &str) -> Option<u32> {
+    if text.is_empty() || text.len() > 9 {
+        return None;
+    }
+    let n = digits(text)? as u32;
+    Some(n * 10_u32.pow(9 - text.len() as u32))
+}
+
+/// Parses ascii digits only, so signs and spaces are not accepted.
+fn digits(text: &str) -> Option<u64> {
+    match text.bytes().all(|b| b.is_ascii_digit()) {
+        true => text.parse().ok(),
+        false => None,
+    }
+}
+
+/// Converts the days since the unix epoch to a date of the proleptic Gregorian calendar.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+/// Converts a date of the proleptic Gregorian calendar to the days since the unix epoch.
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = year - i64::from(month <= 2);
+    let era = year.div_euclid(400);
+    let yoe = year.rem_euclid(400);
+    let mp = (i64::from(month) + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + i64::from(day) - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn should_base64_follow_the_standard() {
+        assert_eq!(encode_base64(b""), "");
+        assert_eq!(encode_base64(b"f"), "Zg==");
+        assert_eq!(encode_base64(b"fo"), "Zm8=");
+        assert_eq!(encode_base64(b"foobar"), "Zm9vYmFy");
+        assert_eq!(encode_base64(&[0xff, 0x00, 0xfe]), "/wD+");
+        for bytes in [&b""[..], b"f", b"fo", b"foo", &[0, 255, 128, 7]] {
+            assert_eq!(decode_base64(&encode_base64(bytes)).unwrap(), bytes);
+        }
+        for wrong in ["Zg", "Zg=a", "Z===", "Zg==Zg==", "Z!8="] {
+            assert_eq!(decode_base64(wrong), None);
+        }
+    }
+
+    #[test]
+    fn should_timestamps_use_rfc3339_in_utc() {
+        let time = UNIX_EPOCH + Duration::new(1_714_566_600, 250_000_000);
+        assert_eq!(format_timestamp(time), "2024-05-01T12:30:00.25Z");
+        assert_eq!(parse_timestamp("2024-05-01T12:30:00.25Z"), Some(time));
+        assert_eq!(format_timestamp(UNIX_EPOCH), "1970-01-01T00:00:00Z");
+        let last = UNIX_EPOCH + Duration::from_secs(MAX_TIMESTAMP_SECS);
+        assert_eq!(parse_timestamp("9999-12-31T23:59:59Z"), Some(last));
+        assert_eq!(
+            parse_timestamp("2024-02-29T00:00:00Z").map(format_timestamp),
+            Some("2024-02-29T00:00:00Z".to_string())
+        );
+        for wrong in [
+            "2023-02-29T00:00:00Z",
+            "2024-05-01 12:30:00Z",
+            "2024-05-01T12:30:00",
+            "2024-05-01T24:00:00Z",
+            "1969-12-31T23:59:59Z",
+            "2024-05-01T12:30:00.Z",
+            "+024-05-01T12:30:00Z",
+        ] {
+            assert_eq!(parse_timestamp(wrong), None, "{}", wrong);
+        }
+    }
+
+    #[test]
+    fn should_durations_use_seconds() {
+        assert_eq!(format_duration(Duration::from_millis(1500)), "1.5");
+        assert_eq!(format_duration(Duration::from_secs(90)), "90");
+        assert_eq!(parse_duration("1.5"), Some(Duration::from_millis(1500)));
+        assert_eq!(parse_duration("0.000000001"), Some(Duration::from_nanos(1)));
+        for wrong in ["", "-1", "1.", ".5", "1.0000000001", "1s"] {
+            assert_eq!(parse_duration(wrong), None, "{}", wrong);
+        }
+    }
+}
@@ -1,7 +1,8 @@
-use super::Value;
-use crate::constant::constant::{MAX_KEY_LEN, MAX_VALUE_LEN};
+use super::{Usage, Value};
+use crate::constant::constant::{MAX_BYTES_LEN, MAX_COLLECTION_LEN, MAX_KEY_LEN, MAX_VALUE_LEN};
 use crate::derror::new_item_error::NewItemError;
 use std::fmt::{Display, Formatter};
+use std::time::{Duration, SystemTime};
 use uuid::Uuid;
 
 /// It is the key:value object that holds primitive data types by marking them with the unique key.
@@ -10,6 +11,9 @@ pub struct Item {
     pub key: String,
     pub value: Value,
     pub uuid: Uuid,
+    /// The moment the item becomes invalid. The item lives forever if it is None.
+    pub expires_at: Option<SystemTime>,
+    pub usage: Usage,
 }
 
 #[allow(dead_code)]
@@ -21,13 +25,14 @@ impl Item {
     /// # Panics
     ///
     /// If the key or value is greater than the allowed length, panic occurs.
+    /// Collections with too many or nested elements are not accepted either.
     ///
     /// # Examples
     ///
     /// ```
     ///  use dserver::{Item,Value};
     ///
-    ///  let sample = Item::new("server", Value::Text("localhost"));
+    ///  let sample = Item::new("server".to_string(), Value::Text("localhost".to_string()));
     ///  match sample {
     ///   Ok(s) => {
     ///      assert!(!s.uuid.is_nil());
@@ -38,23 +43,88 @@ impl Item {
     ///
     /// ```
     pub fn new(key: String, value: Value) -> Result<Self, NewItemError> {
-        if key.as_bytes().len() > MAX_KEY_LEN {
+        if key.len() > MAX_KEY_LEN {
             return Err(NewItemError::InvalidKeyLen);
         }
 
-        if let Value::Text(s) = &value {
-            if s.as_bytes().len() > MAX_VALUE_LEN {
-                return Err(NewItemError::InvalidValueLen);
-            }
-        }
+        Self::validate(&value)?;
 
         let id = Uuid::new_v4();
         Ok(Item {
             key,
             value,
             uuid: id,
+            expires_at: None,
+            usage: Usage::default(),
         })
     }
+
+    /// Checks the value against the length limits of its type.
+    ///
+    /// A collection can have at most MAX_COLLECTION_LEN elements and every element is checked like a single value.
+    /// Set members and hash fields must be unique, hash fields are as long as keys at most.
+    pub fn validate(value: &Value) -> Result<(), NewItemError> {
+        let elements = match value {
+            Value::List(v) => v.iter().collect::<Vec<&Value>>(),
+            Value::Set(v) => {
+                if v.iter().enumerate().any(|(i, e)| v[..i].contains(e)) {
+                    return Err(NewItemError::DuplicateElement);
+                }
+                v.iter().collect()
+            }
+            Value::Hash(v) => {
+                if v.iter().any(|(f, _)| f.len() > MAX_KEY_LEN) {
+                    return Err(NewItemError::InvalidKeyLen);
+                }
+                if v.iter()
+                    .enumerate()
+                    .any(|(i, (f, _))| v[..i].iter().any(|(o, _)| o == f))
+                {
+                    return Err(NewItemError::DuplicateElement);
+                }
+                v.iter().map(|(_, e)| e).collect()
+            }
+            v => return Self::validate_element(v),
+        };
+        if elements.len() > MAX_COLLECTION_LEN {
+            return Err(NewItemError::TooManyElements);
+        }
+        elements.into_iter().try_for_each(Self::validate_element)
+    }
+
+    /// Checks a single value that can be an element of a collection.
+    pub fn validate_element(value: &Value) -> Result<(), NewItemError> {
+        match value {
+            Value::Text(s) if s.len() > MAX_VALUE_LEN => Err(NewItemError::InvalidValueLen),
+            Value::Bytes(b) if b.len() > MAX_BYTES_LEN => Err(NewItemError::InvalidValueLen),
+            v if v.is_collection() => Err(NewItemError::NestedCollection),
+            _ => Ok(()),
+        }
+    }
+
+    /// Sets the item to expire after the given duration from now.
+    ///
+    /// A duration too long to be a point in time leaves the item without expiry instead of panicking.
+    /// The parsers never let such a duration in, it can only come from an embedding program.
+    pub fn expire_after(&mut self, ttl: Duration) {
+        self.expires_at = SystemTime::now().checked_add(ttl);
+    }
+
+    /// Removes the expiry of the item, so it lives until it is deleted.
+    pub fn persist(&mut self) {
+        self.expires_at = None;
+    }
+
+    /// Returns true if the expiry time of the item has passed.
+    pub fn is_expired(&self, now: SystemTime) -> bool {
+        matches!(self.expires_at, Some(e) if e <= now)
+    }
+
+    /// Returns the remaining lifetime of the item, or None if it doesn't expire.
+    pub fn time_to_live(&self, now: SystemTime) -> Option<Duration> {
+        self.expires_at
+            .map(|e| e.duration_since(now).unwrap_or(Duration::ZERO))
+    }
 }
 
 impl Display for Item {
@@ -68,8 +138,9 @@ mod test {
     use super::*;
 
     #[test]
+    #[allow(clippy::single_match)]
     fn should_created_item_has_uuid() {
-        let sample = Item::new("server".to_string(), Value::Text("localhost"));
+        let sample = Item::new("server".to_string(), Value::Text("localhost".to_string()));
         match sample {
             Ok(s) => {
                 assert!(!s.uuid.is_nil());
@@ -84,7 +155,7 @@ mod test {
     fn should_long_key_name_throw_panic() {
         let _ = Item::new(
             "server name is too long".to_string(),
-            Value::Text("localhost"),
+            Value::Text("localhost".to_string()),
         )
         .unwrap();
     }
@@ -96,13 +167,75 @@ mod test {
             "server".to_string(),
             Value::Text(
                 r#"This is the localhost name of the server but
-        it is really toooo long name can you understand me body."#,
+        it is really toooo long name can you understand me body."#
+                    .to_string(),
             ),
         )
         .unwrap();
     }
 
     #[test]
+    fn should_long_bytes_raise_an_error() {
+        let result = Item::new("blob".to_string(), Value::Bytes(vec![7; MAX_BYTES_LEN + 1]));
+        assert!(matches!(result, Err(NewItemError::InvalidValueLen)));
+        let item = Item::new("blob".to_string(), Value::Bytes(vec![7; MAX_BYTES_LEN])).unwrap();
+        assert_eq!(item.value, Value::Bytes(vec![7; MAX_BYTES_LEN]));
+    }
+
+    #[test]
+    fn should_extended_values_works() {
+        let values = vec![
+            Value::ThinUnsigned(u8::MAX),
+            Value::MidUnsigned(u16::MAX),
+            Value::LargeUnsigned(u32::MAX),
+            Value::HugeUnsigned(u64::MAX),
+            Value::HugeNumber(i64::MIN),
+            Value::GiantNumber(i128::MAX),
+            Value::Timestamp(SystemTime::UNIX_EPOCH),
+            Value::Duration(Duration::from_secs(90)),
+            Value::Uuid(Uuid::new_v4()),
+        ];
+        for v in values {
+            let item = Item::new("counter".to_string(), v.clone()).unwrap();
+            assert_eq!(item.value, v);
+        }
+    }
+
+    #[test]
+    fn should_collections_respect_their_limits() {
+        let numbers: Vec<Value> = (0..MAX_COLLECTION_LEN as i32)
+            .map(Value::LargeNumber)
+            .collect();
+        let item = Item::new("flags".to_string(), Value::List(numbers.clone())).unwrap();
+        assert_eq!(item.value, Value::List(numbers.clone()));
+
+        let mut too_many = numbers.clone();
+        too_many.push(Value::LargeNumber(-1));
+        let result = Item::new("flags".to_string(), Value::Set(too_many));
+        assert_eq!(result.unwrap_err(), NewItemError::TooManyElements);
+
+        let nested = Value::List(vec![Value::List(vec![])]);
+        let result = Item::new("flags".to_string(), nested);
+        assert_eq!(result.unwrap_err(), NewItemError::NestedCollection);
+
+        let twice = Value::Set(vec![Value::ThinNumber(1), Value::ThinNumber(1)]);
+        let result = Item::new("flags".to_string(), twice);
+        assert_eq!(result.unwrap_err(), NewItemError::DuplicateElement);
+
+        let long = Value::Hash(vec![(
+            "f".to_string(),
+            Value::Text("x".repeat(MAX_VALUE_LEN + 1)),
+        )]);
+        let result = Item::new("flags".to_string(), long);
+        assert_eq!(result.unwrap_err(), NewItemError::InvalidValueLen);
+
+        let field = Value::Hash(vec![("f".repeat(MAX_KEY_LEN + 1), Value::Logical(true))]);
+        let result = Item::new("flags".to_string(), field);
+        assert_eq!(result.unwrap_err(), NewItemError::InvalidKeyLen);
+    }
+
+    #[test]
+    #[allow(clippy::excessive_precision)]
     fn should_primitive_values_works() {
         let logson = Item::new("logs_on".to_string(), Value::Logical(true)).unwrap();
         assert_eq!(logson.value, Value::Logical(true));
@@ -123,4 +256,24 @@ mod test {
             Value::LargeFloat(24.342343243423423423431415)
         );
     }
+
+    #[test]
+    fn should_item_expires_after_ttl() {
+        let mut item = Item::new("token".to_string(), Value::Text("abc".to_string())).unwrap();
+        let now = SystemTime::now();
+        assert!(!item.is_expired(now));
+        assert_eq!(item.time_to_live(now), None);
+
+        item.expire_after(Duration::from_secs(60));
+        assert!(!item.is_expired(now));
+        assert!(item.is_expired(now + Duration::from_secs(61)));
+        let ttl = item.time_to_live(SystemTime::now()).unwrap();
+        assert!(ttl > Duration::from_secs(59) && ttl <= Duration::from_secs(60));
+
+        item.persist();
+        assert!(!item.is_expired(now + Duration::from_secs(61)));
+
+        item.expire_after(Duration::from_secs(u64::MAX));
+        assert_eq!(item.time_to_live(now), None);
+    }
 }
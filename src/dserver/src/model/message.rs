@@ -1,13 +1,18 @@
-use crate::constant::constant::MAX_KEY_LEN;
+use crate::constant::constant::{MAX_BYTES_LEN, MAX_KEY_LEN, MAX_TTL_SECS, MAX_VALUE_LEN};
 use crate::derror::message_parse_error::MessageParseError;
 use crate::derror::message_send_error::MessageSendError;
-use crate::model::command::Command;
-use crate::model::Search;
+use crate::model::format::{decode_base64, parse_duration, parse_timestamp};
+use crate::model::{AddMode, Command, Expiry, Operation, Search, Update};
+use crate::InformativeEvent;
 use crate::{Candidate, Item, Pack, TransmitterEvent, Value};
-use crossbeam::channel::Sender;
+use crossbeam::channel;
+use crossbeam::channel::{Receiver, Sender};
 use log::info;
-use std::str::from_utf8;
+use std::num::{IntErrorKind, ParseIntError};
+use std::str::{from_utf8, FromStr};
 use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use uuid::Uuid;
 
 /// Data model representing incoming messages to the TCP line
 #[derive(Debug, PartialEq, Clone)]
@@ -15,6 +20,23 @@ pub struct Message {
     pub command: Command,
     pub key: String,
     pub value: Option<Value>,
+    pub ttl: Option<Duration>,
+    /// The hash field that HSET, HGET and HDEL work on.
+    pub field: Option<String>,
+    /// The start and stop positions of LRANGE.
+    pub range: Option<(i64, i64)>,
+    /// INC, DEC and IBY create a missing key at zero if it is true.
+    pub create: bool,
+    /// INC, DEC and IBY count text that holds a 64-bit integer as a number if it is true.
+    pub text: bool,
+    /// GET replies the uuid of the item with its value if it is true.
+    pub versioned: bool,
+    /// The uuid that CAS expects the item to have.
+    pub version: Option<Uuid>,
+    /// The messages of MGET, MSET and MDEL, one for every key.
+    pub batch: Vec<Message>,
+    /// MSET puts none of the keys if one of them can't be put.
+    pub atomic: bool,
 }
 
 impl Message {
@@ -23,23 +45,132 @@ impl Message {
             command,
             key,
             value,
+            ttl: None,
+            field: None,
+            range: None,
+            create: false,
+            text: false,
+            versioned: false,
+            version: None,
+            batch: Vec::new(),
+            atomic: false,
         }
     }
 
+    /// Sets the time to live that the message carries.
+    pub fn with_ttl(mut self, ttl: Option<Duration>) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    /// Sets the hash field that the message carries.
+    pub fn with_field(mut self, field: Option<String>) -> Self {
+        self.field = field;
+        self
+    }
+
+    /// Sets the list positions that the message carries.
+    pub fn with_range(mut self, range: Option<(i64, i64)>) -> Self {
+        self.range = range;
+        self
+    }
+
+    /// Sets whether a counter command creates a missing key at zero.
+    pub fn with_create(mut self, create: bool) -> Self {
+        self.create = create;
+        self
+    }
+
+    /// Sets whether a counter command counts integer text as a number.
+    pub fn with_text(mut self, text: bool) -> Self {
+        self.text = text;
+        self
+    }
+
+    /// Sets whether GET replies the uuid of the item.
+    pub fn with_versioned(mut self, versioned: bool) -> Self {
+        self.versioned = versioned;
+        self
+    }
+
+    /// Sets the uuid that CAS expects the item to have.
+    pub fn with_version(mut self, version: Option<Uuid>) -> Self {
+        self.version = version;
+        self
+    }
+
+    /// Sets the messages that a multi-key command runs for its keys.
+    pub fn with_batch(mut self, batch: Vec<Message>) -> Self {
+        self.batch = batch;
+        self
+    }
+
+    /// Sets whether MSET puts all of its keys or none of them.
+    pub fn with_atomic(mut self, atomic: bool) -> Self {
+        self.atomic = atomic;
+        self
+    }
+
+    /// Returns the collection or counter operation of the message, or None if a part of it is missing.
+    pub fn operation(&self) -> Option<Operation> {
+        let value = || self.value.clone();
+        let field = || self.field.clone();
+        let increment = |amount| match self.text {
+            true => Operation::TextIncrement(amount, self.create),
+            false => Operation::Increment(amount, self.create),
+        };
+        let operation = match self.command {
+            Command::Lpush => Operation::ListPush(value()?),
+            Command::Lpop => Operation::ListPop,
+            Command::Lrange => {
+                let (start, stop) = self.range?;
+                Operation::ListRange(start, stop)
+            }
+            Command::Sadd => Operation::SetAdd(value()?),
+            Command::Srem => Operation::SetRemove(value()?),
+            Command::Smembers => Operation::SetMembers,
+            Command::Hset => Operation::HashSet(field()?, value()?),
+            Command::Hget => Operation::HashGet(field()?),
+            Command::Hdel => Operation::HashDelete(field()?),
+            Command::Hgetall => Operation::HashGetAll,
+            Command::Inc => increment(Value::LargeNumber(1)),
+            Command::Dec => increment(Value::LargeNumber(-1)),
+            Command::Iby => increment(value()?),
+            _ => return None,
+        };
+        Some(operation)
+    }
+
+    /// Sends the message to the pack worker as a transmitter event.
+    ///
+    /// The returned receiver delivers the result of this message only,
+    /// so the caller can route it back to the client that made the request.
     pub fn send(
         self,
         pack: &Arc<Mutex<Pack>>,
         event: &Sender<TransmitterEvent>,
-    ) -> Result<u8, MessageSendError> {
+    ) -> Result<Receiver<InformativeEvent>, MessageSendError> {
+        let (responder, reply) = channel::bounded(1);
         match self.command {
-            Command::Add => {
+            Command::Add | Command::Anx | Command::Axx | Command::Cas => {
                 info!("{:?}", self);
+                let mode = match self.command {
+                    Command::Cas => AddMode::IfVersion(self.version.ok_or(MessageSendError::Add)?),
+                    ref c => AddMode::from(c),
+                };
+                let value = self.value.ok_or(MessageSendError::Add)?;
+                let mut object = Item::new(self.key, value)?;
+                if let Some(ttl) = self.ttl {
+                    object.expire_after(ttl);
+                }
                 let r = event.send(TransmitterEvent::AddNewItem(Candidate {
                     pack: pack.clone(),
-                    object: Item::new(self.key, self.value.unwrap()).unwrap(),
+                    object,
+                    mode,
+                    responder,
                 }));
                 match r {
-                    Ok(_) => Ok(1),
+                    Ok(_) => Ok(reply),
                     Err(_) => Err(MessageSendError::Add),
                 }
             }
@@ -47,75 +178,438 @@ impl Message {
                 let r = event.send(TransmitterEvent::GetItem(Search {
                     pack: pack.clone(),
                     key: self.key,
+                    responder,
                 }));
                 match r {
-                    Ok(_) => Ok(1),
+                    Ok(_) => Ok(reply),
                     Err(_) => Err(MessageSendError::Get),
                 }
             }
             Command::Del => {
-                //TODO Not completed
-                info!("Del request");
-                Ok(1)
+                info!("{:?}", self);
+                let r = event.send(TransmitterEvent::DeleteItem(Search {
+                    pack: pack.clone(),
+                    key: self.key,
+                    responder,
+                }));
+                match r {
+                    Ok(_) => Ok(reply),
+                    Err(_) => Err(MessageSendError::Del),
+                }
+            }
+            Command::Exp | Command::Prs => {
+                info!("{:?}", self);
+                let r = event.send(TransmitterEvent::ExpireItem(Expiry {
+                    pack: pack.clone(),
+                    key: self.key,
+                    ttl: self.ttl,
+                    responder,
+                }));
+                match r {
+                    Ok(_) => Ok(reply),
+                    Err(_) => Err(MessageSendError::Expire),
+                }
+            }
+            Command::Ttl => {
+                let r = event.send(TransmitterEvent::GetTtl(Search {
+                    pack: pack.clone(),
+                    key: self.key,
+                    responder,
+                }));
+                match r {
+                    Ok(_) => Ok(reply),
+                    Err(_) => Err(MessageSendError::Get),
+                }
             }
+            Command::Exs => {
+                let r = event.send(TransmitterEvent::CheckItem(Search {
+                    pack: pack.clone(),
+                    key: self.key,
+                    responder,
+                }));
+                match r {
+                    Ok(_) => Ok(reply),
+                    Err(_) => Err(MessageSendError::Get),
+                }
+            }
+            Command::Inf
+            | Command::Sav
+            | Command::Rwl
+            | Command::Mget
+            | Command::Mset
+            | Command::Mdel => Err(MessageSendError::NotPackCommand),
+            _ => {
+                info!("{:?}", self);
+                let operation = self.operation().ok_or(MessageSendError::Update)?;
+                operation.validate()?;
+                let r = event.send(TransmitterEvent::UpdateItem(Update {
+                    pack: pack.clone(),
+                    key: self.key,
+                    operation,
+                    responder,
+                }));
+                match r {
+                    Ok(_) => Ok(reply),
+                    Err(_) => Err(MessageSendError::Update),
+                }
+            }
+        }
+    }
+}
+
+/// Escapes the text so it can be written as a single part of a message.
+///
+/// `\\`, `\|`, `\n` and `\r` stand for the backslash, the pipe, the line feed and the carriage return.
+pub fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '|' => escaped.push_str("\\|"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Splits the message at the pipes that are not escaped and unescapes every part.
+///
+/// Every part ends with a pipe, so nothing may follow the last one.
+/// Unknown escapes and raw line breaks, like a `\r` left by a `\r\r\n` terminator, are rejected.
+pub fn split(message: &str) -> Result<Vec<String>, MessageParseError> {
+    let mut parts = Vec::new();
+    let mut part = String::new();
+    let mut chars = message.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '|' => parts.push(std::mem::take(&mut part)),
+            '\\' => part.push(match chars.next() {
+                Some('\\') => '\\',
+                Some('|') => '|',
+                Some('n') => '\n',
+                Some('r') => '\r',
+                _ => return Err(MessageParseError::Escape),
+            }),
+            '\r' | '\n' => return Err(MessageParseError::Escape),
+            c => part.push(c),
         }
     }
+    if !part.is_empty() {
+        return Err(MessageParseError::Pattern);
+    }
+    Ok(parts)
 }
 
-fn get_part(text: &str) -> Option<(&str, &str)> {
-    for (i, c) in text.chars().enumerate() {
-        if c == '|' {
-            return Some((&text[..i], &text[i + 1..]));
+/// Converts the value part of the message to the Value variant of the type tag.
+pub fn parse_value(data_type: &str, v: &str) -> Result<Value, MessageParseError> {
+    match data_type {
+        "s" => {
+            if v.len() > MAX_VALUE_LEN {
+                return Err(MessageParseError::ValueTooLong);
+            }
+            Ok(Value::Text(v.to_string()))
         }
+        "i8" => parse_integer(data_type, v).map(Value::ThinNumber),
+        "i16" => parse_integer(data_type, v).map(Value::MidNumber),
+        "i32" => parse_integer(data_type, v).map(Value::LargeNumber),
+        "f32" => parse_float(data_type, v).map(Value::ThinFloat),
+        "f64" => parse_float(data_type, v).map(Value::LargeFloat),
+        "l" => v
+            .parse::<bool>()
+            .map(Value::Logical)
+            .map_err(|_| MessageParseError::invalid_value(data_type, v)),
+        "u8" => parse_integer(data_type, v).map(Value::ThinUnsigned),
+        "u16" => parse_integer(data_type, v).map(Value::MidUnsigned),
+        "u32" => parse_integer(data_type, v).map(Value::LargeUnsigned),
+        "u64" => parse_integer(data_type, v).map(Value::HugeUnsigned),
+        "i64" => parse_integer(data_type, v).map(Value::HugeNumber),
+        "i128" => parse_integer(data_type, v).map(Value::GiantNumber),
+        "b" => {
+            let bytes =
+                decode_base64(v).ok_or_else(|| MessageParseError::invalid_value(data_type, v))?;
+            if bytes.len() > MAX_BYTES_LEN {
+                return Err(MessageParseError::ValueTooLong);
+            }
+            Ok(Value::Bytes(bytes))
+        }
+        "ts" => parse_timestamp(v)
+            .map(Value::Timestamp)
+            .ok_or_else(|| MessageParseError::invalid_value(data_type, v)),
+        "d" => parse_duration(v)
+            .map(Value::Duration)
+            .ok_or_else(|| MessageParseError::invalid_value(data_type, v)),
+        "uuid" => Uuid::parse_str(v)
+            .map(Value::Uuid)
+            .map_err(|_| MessageParseError::invalid_value(data_type, v)),
+        _ => Err(MessageParseError::UnknownType(data_type.to_string())),
+    }
+}
+
+/// Returns the parts of a value in a message, its type tag and escaped payload.
+///
+/// Collections are written as their tag, their element count and the parts of every element.
+/// Every element of a hash starts with its field name, like `hash|1|name|s|dory`.
+pub fn value_parts(value: &Value) -> Vec<String> {
+    let mut parts = vec![value.tag().to_string()];
+    match value {
+        Value::List(v) | Value::Set(v) => {
+            parts.push(v.len().to_string());
+            v.iter().for_each(|e| parts.extend(value_parts(e)));
+        }
+        Value::Hash(v) => {
+            parts.push(v.len().to_string());
+            for (field, e) in v {
+                parts.push(escape(field));
+                parts.extend(value_parts(e));
+            }
+        }
+        v => parts.push(escape(&v.payload())),
+    }
+    parts
+}
+
+/// Reads a value from the unescaped parts that value_parts writes. All parts must be used.
+pub fn parse_parts(parts: &[String]) -> Result<Value, MessageParseError> {
+    let (value, rest) = next_value(parts, true)?;
+    match rest.is_empty() {
+        true => Ok(value),
+        false => Err(MessageParseError::Pattern),
+    }
+}
+
+/// Reads the value of a reply and the uuid of its item that follows it if GET asked for it.
+pub fn parse_versioned(parts: &[String]) -> Result<(Value, Option<Uuid>), MessageParseError> {
+    match next_value(parts, true)? {
+        (value, []) => Ok((value, None)),
+        (value, [version]) => Uuid::parse_str(version)
+            .map(|u| (value, Some(u)))
+            .map_err(|_| MessageParseError::invalid_value("uuid", version)),
+        _ => Err(MessageParseError::Pattern),
+    }
+}
+
+pub(crate) fn next_value(
+    parts: &[String],
+    collection: bool,
+) -> Result<(Value, &[String]), MessageParseError> {
+    let (tag, rest) = parts.split_first().ok_or(MessageParseError::Pattern)?;
+    let (count, mut rest) = match (tag.as_str(), rest) {
+        ("list" | "set" | "hash", [count, rest @ ..]) if collection => {
+            let count = parse_integer::<u16>("count", count)? as usize;
+            (count, rest)
+        }
+        ("e", [_, rest @ ..]) => return Ok((Value::Empty, rest)),
+        (tag, [payload, rest @ ..]) => return Ok((parse_value(tag, payload)?, rest)),
+        _ => return Err(MessageParseError::Pattern),
+    };
+    let mut elements = Vec::new();
+    let mut fields = Vec::new();
+    for _ in 0..count {
+        if tag == "hash" {
+            let (field, after) = rest.split_first().ok_or(MessageParseError::Pattern)?;
+            let (e, after) = next_value(after, false)?;
+            fields.push((field.clone(), e));
+            rest = after;
+        } else {
+            let (e, after) = next_value(rest, false)?;
+            elements.push(e);
+            rest = after;
+        }
+    }
+    let value = match tag.as_str() {
+        "list" => Value::List(elements),
+        "set" => Value::Set(elements),
+        _ => Value::Hash(fields),
+    };
+    Ok((value, rest))
+}
+
+/// Converts the time to live part of the message, in seconds, to a duration.
+///
+/// It is limited to a hundred years, so the expiry time of an item is always a valid point in time.
+fn parse_ttl(v: &str) -> Result<Duration, MessageParseError> {
+    match parse_integer::<u64>("ttl", v)? {
+        0 => Err(MessageParseError::out_of_range("ttl", v)),
+        seconds if seconds > MAX_TTL_SECS => Err(MessageParseError::out_of_range("ttl", v)),
+        seconds => Ok(Duration::from_secs(seconds)),
+    }
+}
+
+/// The optional last parts of the counter commands in any order, `new` creates a missing key at zero
+/// and `text` counts text that holds an integer as a number. It returns the create and text flags.
+fn parse_counter(options: &[&str]) -> Result<(bool, bool), MessageParseError> {
+    let (mut create, mut text) = (false, false);
+    for option in options {
+        match *option {
+            "new" if !create => create = true,
+            "text" if !text => text = true,
+            _ => return Err(MessageParseError::Pattern),
+        }
+    }
+    Ok((create, text))
+}
+
+/// The keys of multi-key commands and hash fields are limited like key names.
+fn parse_key(v: &str) -> Result<String, MessageParseError> {
+    match v.chars().count() > MAX_KEY_LEN {
+        true => Err(MessageParseError::KeyNameTooLong),
+        false => Ok(v.to_string()),
+    }
+}
+
+fn parse_integer<T>(data_type: &str, v: &str) -> Result<T, MessageParseError>
+where
+    T: FromStr<Err = ParseIntError>,
+{
+    v.parse::<T>().map_err(|e| match e.kind() {
+        IntErrorKind::PosOverflow | IntErrorKind::NegOverflow => {
+            MessageParseError::out_of_range(data_type, v)
+        }
+        _ => MessageParseError::invalid_value(data_type, v),
+    })
+}
+
+fn parse_float<T>(data_type: &str, v: &str) -> Result<T, MessageParseError>
+where
+    T: FromStr + Into<f64> + Copy,
+{
+    let n = v
+        .parse::<T>()
+        .map_err(|_| MessageParseError::invalid_value(data_type, v))?;
+    if !n.into().is_finite() {
+        return Err(MessageParseError::out_of_range(data_type, v));
     }
-    None
+    Ok(n)
 }
 
-impl<'a> TryFrom<&'a [u8]> for Message {
+impl TryFrom<&[u8]> for Message {
     type Error = MessageParseError;
 
     fn try_from(value: &[u8]) -> Result<Self, Self::Error> {
         if value.is_empty() {
             return Err(MessageParseError::Empty);
         }
-        let s = from_utf8(value)?;
-        let pipe_count = s.chars().filter(|c| *c == '|').count();
-        match pipe_count {
-            2 | 4 => {}
-            _ => {
-                return Err(MessageParseError::Pattern);
-            }
-        }
-        let (command, s) = get_part(s).unwrap();
-        let (key, s) = get_part(s).unwrap();
+        let parts = split(from_utf8(value)?)?;
+        let (command, args) = parts.split_first().ok_or(MessageParseError::Pattern)?;
+        let command = Command::from_str(command)?;
+        let args: Vec<&str> = args.iter().map(|a| a.as_str()).collect();
 
-        if key.chars().count() > MAX_KEY_LEN {
-            return Err(MessageParseError::KeyNameTooLong);
+        if let Some(key) = args.first() {
+            if key.chars().count() > MAX_KEY_LEN {
+                return Err(MessageParseError::KeyNameTooLong);
+            }
         }
 
-        match command {
-            "ADD" => {
-                let (data_type, s) = get_part(s).unwrap();
-                let (v, _) = get_part(s).unwrap();
-                let object_value = match data_type {
-                    "s" => Value::Text("TEST"), //TODO: Lifetime Error occurred for v. I have to find solution.
-                    "i8" => Value::ThinNumber(v.parse::<i8>().unwrap()),
-                    "i16" => Value::MidNumber(v.parse::<i16>().unwrap()),
-                    "i32" => Value::LargeNumber(v.parse::<i32>().unwrap()),
-                    "f32" => Value::ThinFloat(v.parse::<f32>().unwrap()),
-                    "f64" => Value::LargeFloat(v.parse::<f64>().unwrap()),
-                    "l" => Value::Logical(v.parse::<bool>().unwrap()),
-                    _ => Value::Empty,
+        match (&command, args.as_slice()) {
+            (Command::Inf | Command::Sav | Command::Rwl, []) => {
+                Ok(Message::new(command, String::new(), None))
+            }
+            (Command::Add | Command::Anx | Command::Axx, [key, data_type, v, ttl @ ..])
+                if ttl.len() <= 1 =>
+            {
+                let object_value = parse_value(data_type, v)?;
+                let ttl = match ttl.first() {
+                    Some(t) => Some(parse_ttl(t)?),
+                    None => None,
+                };
+                Ok(Message::new(command, key.to_string(), Some(object_value)).with_ttl(ttl))
+            }
+            (Command::Cas, [key, version, data_type, v, ttl @ ..]) if ttl.len() <= 1 => {
+                let version = Uuid::parse_str(version)
+                    .map_err(|_| MessageParseError::invalid_value("uuid", version))?;
+                let object_value = parse_value(data_type, v)?;
+                let ttl = match ttl.first() {
+                    Some(t) => Some(parse_ttl(t)?),
+                    None => None,
                 };
-                Ok(Message::new(
-                    Command::Add,
-                    key.to_string(),
-                    Some(object_value),
-                ))
+                Ok(Message::new(command, key.to_string(), Some(object_value))
+                    .with_ttl(ttl)
+                    .with_version(Some(version)))
+            }
+            (Command::Mget | Command::Mdel, keys) if !keys.is_empty() => {
+                let per_key = command.per_key().ok_or(MessageParseError::Command)?;
+                let batch = keys
+                    .iter()
+                    .map(|k| Ok(Message::new(per_key.clone(), parse_key(k)?, None)))
+                    .collect::<Result<Vec<Message>, MessageParseError>>()?;
+                Ok(Message::new(command, String::new(), None).with_batch(batch))
+            }
+            (Command::Mset, parts) if parts.len() % 3 == 0 || parts.last() == Some(&"all") => {
+                let (triples, atomic) = match parts.split_last() {
+                    Some((&"all", rest)) if parts.len() % 3 == 1 => (rest, true),
+                    _ => (parts, false),
+                };
+                if triples.is_empty() || triples.len() % 3 != 0 {
+                    return Err(MessageParseError::Pattern);
+                }
+                let batch = triples
+                    .chunks(3)
+                    .map(|t| {
+                        let value = parse_value(t[1], t[2])?;
+                        Ok(Message::new(Command::Add, parse_key(t[0])?, Some(value)))
+                    })
+                    .collect::<Result<Vec<Message>, MessageParseError>>()?;
+                Ok(Message::new(command, String::new(), None)
+                    .with_batch(batch)
+                    .with_atomic(atomic))
+            }
+            (Command::Get, [key, "uuid"]) => {
+                Ok(Message::new(command, key.to_string(), None).with_versioned(true))
+            }
+            (Command::Exp, [key, t]) => {
+                let ttl = parse_ttl(t)?;
+                Ok(Message::new(command, key.to_string(), None).with_ttl(Some(ttl)))
             }
-            "DEL" => Ok(Message::new(Command::Del, key.to_string(), None)),
-            "GET" => Ok(Message::new(Command::Get, key.to_string(), None)),
-            _ => Err(MessageParseError::Command),
+            (
+                Command::Get
+                | Command::Del
+                | Command::Ttl
+                | Command::Exs
+                | Command::Prs
+                | Command::Lpop
+                | Command::Smembers
+                | Command::Hgetall,
+                [key],
+            ) => Ok(Message::new(command, key.to_string(), None)),
+            (Command::Lpush | Command::Sadd | Command::Srem, [key, data_type, v]) => {
+                let object_value = parse_value(data_type, v)?;
+                Ok(Message::new(command, key.to_string(), Some(object_value)))
+            }
+            (Command::Lrange, [key, start, stop]) => {
+                let range = (parse_integer("i64", start)?, parse_integer("i64", stop)?);
+                Ok(Message::new(command, key.to_string(), None).with_range(Some(range)))
+            }
+            (Command::Hset, [key, field, data_type, v]) => {
+                let object_value = parse_value(data_type, v)?;
+                Ok(Message::new(command, key.to_string(), Some(object_value))
+                    .with_field(Some(parse_key(field)?)))
+            }
+            (Command::Hget | Command::Hdel, [key, field]) => {
+                Ok(
+                    Message::new(command, key.to_string(), None)
+                        .with_field(Some(parse_key(field)?)),
+                )
+            }
+            (Command::Inc | Command::Dec, [key, options @ ..]) if options.len() <= 2 => {
+                let (create, text) = parse_counter(options)?;
+                Ok(Message::new(command, key.to_string(), None)
+                    .with_create(create)
+                    .with_text(text))
+            }
+            (Command::Iby, [key, data_type, v, options @ ..]) if options.len() <= 2 => {
+                let amount = parse_value(data_type, v)?;
+                let (create, text) = parse_counter(options)?;
+                let float = matches!(amount, Value::ThinFloat(_) | Value::LargeFloat(_));
+                if !amount.is_number() || (text && float) {
+                    return Err(MessageParseError::invalid_value(data_type, v));
+                }
+                Ok(Message::new(command, key.to_string(), Some(amount))
+                    .with_create(create)
+                    .with_text(text))
+            }
+            _ => Err(MessageParseError::Pattern),
         }
     }
 }
@@ -130,11 +624,17 @@ mod test {
         ADD|DefaultPi|U32|3.1415|
     */
 
-    use std::f32::consts::PI;
+    use crate::constant::constant::{MAX_BYTES_LEN, MAX_TTL_SECS};
     use crate::derror::message_parse_error::MessageParseError;
     use crate::model::command::Command;
-    use crate::model::message::Message;
+    use crate::model::format::encode_base64;
+    use crate::model::message::{escape, parse_parts, parse_value, split, value_parts, Message};
+    use crate::model::operation::Operation;
+    use crate::model::random::Random;
     use crate::Value;
+    use std::f32::consts::PI;
+    use std::time::{Duration, UNIX_EPOCH};
+    use uuid::Uuid;
 
     #[test]
     fn should_add_messages_could_be_parse() {
@@ -143,7 +643,7 @@ mod test {
         let result = Message::try_from(bytes).unwrap();
         assert_eq!(result.command, Command::Add);
         assert_eq!(result.key, "ServerName".to_string());
-        assert_eq!(result.value, Some(Value::Text("")));
+        assert_eq!(result.value, Some(Value::Text("localhost".to_string())));
 
         let message = "ADD|Logs|l|true|";
         let bytes = message.as_bytes();
@@ -152,7 +652,7 @@ mod test {
         assert_eq!(result.key, "Logs".to_string());
         assert_eq!(result.value, Some(Value::Logical(true)));
 
-        let message = "ADD|DefaultPi|f32|3.1415|";
+        let message = "ADD|DefaultPi|f32|3.1415927|";
         let bytes = message.as_bytes();
         let result = Message::try_from(bytes).unwrap();
         assert_eq!(result.command, Command::Add);
@@ -191,4 +691,433 @@ mod test {
         let result = Message::try_from(bytes);
         assert_eq!(result, Err(MessageParseError::Empty));
     }
+
+    #[test]
+    fn should_unknown_type_raise_an_error() {
+        let result = Message::try_from("ADD|Logs|bool|true|".as_bytes());
+        assert_eq!(
+            result,
+            Err(MessageParseError::UnknownType("bool".to_string()))
+        );
+    }
+
+    #[test]
+    fn should_invalid_values_raise_an_error() {
+        let result = Message::try_from("ADD|x|l|maybe|".as_bytes());
+        assert_eq!(
+            result,
+            Err(MessageParseError::InvalidValue {
+                expected: "l".to_string(),
+                got: "maybe".to_string()
+            })
+        );
+
+        let result = Message::try_from("ADD|x|i32|ten|".as_bytes());
+        assert!(matches!(
+            result,
+            Err(MessageParseError::InvalidValue { .. })
+        ));
+
+        let result = Message::try_from("ADD|x|f64||".as_bytes());
+        assert!(matches!(
+            result,
+            Err(MessageParseError::InvalidValue { .. })
+        ));
+    }
+
+    #[test]
+    fn should_out_of_range_values_raise_an_error() {
+        let result = Message::try_from("ADD|x|i8|999|".as_bytes());
+        assert_eq!(
+            result,
+            Err(MessageParseError::ValueOutOfRange {
+                expected: "i8".to_string(),
+                got: "999".to_string()
+            })
+        );
+
+        let result = Message::try_from("ADD|x|i16|-40000|".as_bytes());
+        assert!(matches!(
+            result,
+            Err(MessageParseError::ValueOutOfRange { .. })
+        ));
+
+        let result = Message::try_from("ADD|x|f32|1e50|".as_bytes());
+        assert!(matches!(
+            result,
+            Err(MessageParseError::ValueOutOfRange { .. })
+        ));
+    }
+
+    #[test]
+    fn should_long_text_value_raise_an_error() {
+        let message = format!("ADD|x|s|{}|", "a".repeat(65));
+        let result = Message::try_from(message.as_bytes());
+        assert_eq!(result, Err(MessageParseError::ValueTooLong));
+    }
+
+    #[test]
+    fn should_server_commands_take_no_parts() {
+        for (text, command) in [
+            ("INF|", Command::Inf),
+            ("SAV|", Command::Sav),
+            ("RWL|", Command::Rwl),
+        ] {
+            let result = Message::try_from(text.as_bytes()).unwrap();
+            assert_eq!(result.command, command);
+            assert_eq!(result.key, "");
+        }
+        assert_eq!(
+            Message::try_from("INF|x|".as_bytes()),
+            Err(MessageParseError::Pattern)
+        );
+        assert_eq!(
+            Message::try_from("INF".as_bytes()),
+            Err(MessageParseError::Pattern)
+        );
+    }
+
+    #[test]
+    fn should_malformed_messages_raise_an_error() {
+        let result = Message::try_from("ADD|x|".as_bytes());
+        assert_eq!(result, Err(MessageParseError::Pattern));
+
+        let result = Message::try_from("ADD|x|l|".as_bytes());
+        assert_eq!(result, Err(MessageParseError::Pattern));
+
+        let result = Message::try_from("ANX|x|l|true|".as_bytes()).unwrap();
+        assert_eq!(result.command, Command::Anx);
+        assert_eq!(result.value, Some(Value::Logical(true)));
+
+        let result = Message::try_from("AXX|x|".as_bytes());
+        assert_eq!(result, Err(MessageParseError::Pattern));
+
+        let result = Message::try_from("PUT|x|".as_bytes());
+        assert_eq!(result, Err(MessageParseError::Command));
+
+        let result = Message::try_from("EXP|x|".as_bytes());
+        assert_eq!(result, Err(MessageParseError::Pattern));
+
+        let result = Message::try_from("ADD|x|l|true|1|2|3|".as_bytes());
+        assert_eq!(result, Err(MessageParseError::Pattern));
+
+        let result = Message::try_from([0xff_u8, b'|', b'|'].as_slice());
+        assert_eq!(result, Err(MessageParseError::Encoding));
+    }
+
+    #[test]
+    fn should_ttl_messages_could_be_parse() {
+        let result = Message::try_from("ADD|token|s|abc|60|".as_bytes()).unwrap();
+        assert_eq!(result.value, Some(Value::Text("abc".to_string())));
+        assert_eq!(result.ttl, Some(Duration::from_secs(60)));
+
+        let result = Message::try_from("EXP|token|30|".as_bytes()).unwrap();
+        assert_eq!(result.command, Command::Exp);
+        assert_eq!(result.ttl, Some(Duration::from_secs(30)));
+
+        let result = Message::try_from("TTL|token|".as_bytes()).unwrap();
+        assert_eq!(result.command, Command::Ttl);
+        let result = Message::try_from("EXS|token|".as_bytes()).unwrap();
+        assert_eq!(result.command, Command::Exs);
+        assert_eq!(result.value, None);
+        let result = Message::try_from("PRS|token|".as_bytes()).unwrap();
+        assert_eq!(result.command, Command::Prs);
+        assert_eq!(result.ttl, None);
+
+        let result = Message::try_from("ADD|token|s|abc|soon|".as_bytes());
+        assert!(matches!(
+            result,
+            Err(MessageParseError::InvalidValue { .. })
+        ));
+        let result = Message::try_from("EXP|token|0|".as_bytes());
+        assert!(matches!(
+            result,
+            Err(MessageParseError::ValueOutOfRange { .. })
+        ));
+        let result = Message::try_from(format!("EXP|token|{}|", u64::MAX).as_bytes());
+        assert_eq!(
+            result,
+            Err(MessageParseError::out_of_range(
+                "ttl",
+                &u64::MAX.to_string()
+            ))
+        );
+        let result = Message::try_from(format!("ADD|token|l|true|{}|", MAX_TTL_SECS).as_bytes());
+        assert_eq!(result.unwrap().ttl, Some(Duration::from_secs(MAX_TTL_SECS)));
+    }
+
+    #[test]
+    fn should_escaped_parts_could_be_parse() {
+        let result = Message::try_from(r"ADD|motd|s|a\|b\\c\r\n|".as_bytes()).unwrap();
+        assert_eq!(result.value, Some(Value::Text("a|b\\c\r\n".to_string())));
+
+        let result = Message::try_from(r"GET|a\|b|".as_bytes()).unwrap();
+        assert_eq!(result.key, "a|b");
+
+        assert_eq!(
+            Message::try_from(r"ADD|motd|s|a\tb|".as_bytes()),
+            Err(MessageParseError::Escape)
+        );
+        assert_eq!(
+            Message::try_from(r"GET|motd\".as_bytes()),
+            Err(MessageParseError::Escape)
+        );
+        // A raw carriage return is left by a `\r\r\n` terminator.
+        assert_eq!(
+            Message::try_from("GET|motd|\r".as_bytes()),
+            Err(MessageParseError::Escape)
+        );
+        assert_eq!(
+            Message::try_from("GET|motd|junk".as_bytes()),
+            Err(MessageParseError::Pattern)
+        );
+        assert_eq!(
+            Message::try_from("GET|motd|extra|".as_bytes()),
+            Err(MessageParseError::Pattern)
+        );
+    }
+
+    /// Returns random texts that are rich in the characters the protocol treats specially.
+    fn texts(seed: u64, count: usize) -> Vec<String> {
+        let alphabet = [
+            '|', '\\', '\r', '\n', 'n', 'r', ' ', 'a', 'Z', '0', 'ç', '🦀', '\t', '\0',
+        ];
+        let mut random = Random::new(seed);
+        (0..count).map(|_| random.text(&alphabet, 16)).collect()
+    }
+
+    #[test]
+    fn should_any_text_survive_escape_round_trip() {
+        for text in texts(0x2545F4914F6CDD1D, 2000) {
+            let escaped = escape(&text);
+            assert!(!escaped.contains(['\r', '\n']));
+            assert_eq!(split(&format!("{}|", escaped)), Ok(vec![text.clone()]));
+
+            let message = format!("ADD|k|s|{}|", escaped);
+            let result = Message::try_from(message.as_bytes()).unwrap();
+            assert_eq!(result.value, Some(Value::Text(text)));
+        }
+    }
+
+    #[test]
+    fn should_extended_types_could_be_parse() {
+        let cases = vec![
+            ("u8", "255", Value::ThinUnsigned(255)),
+            ("u16", "65535", Value::MidUnsigned(65535)),
+            ("u32", "4294967295", Value::LargeUnsigned(u32::MAX)),
+            ("u64", "18446744073709551615", Value::HugeUnsigned(u64::MAX)),
+            ("i64", "-9223372036854775808", Value::HugeNumber(i64::MIN)),
+            (
+                "i128",
+                "170141183460469231731687303715884105727",
+                Value::GiantNumber(i128::MAX),
+            ),
+            ("b", "AP8=", Value::Bytes(vec![0, 255])),
+            (
+                "ts",
+                "1970-01-02T00:00:00.5Z",
+                Value::Timestamp(UNIX_EPOCH + Duration::from_millis(86_400_500)),
+            ),
+            ("d", "1.5", Value::Duration(Duration::from_millis(1500))),
+            (
+                "uuid",
+                "936da01f-9abd-4d9d-80c7-02af85c822a8",
+                Value::Uuid(Uuid::parse_str("936da01f9abd4d9d80c702af85c822a8").unwrap()),
+            ),
+        ];
+        for (tag, payload, value) in cases {
+            let message = format!("ADD|k|{}|{}|", tag, payload);
+            let result = Message::try_from(message.as_bytes()).unwrap();
+            assert_eq!(result.value, Some(value.clone()));
+            assert_eq!(value.tag(), tag);
+            assert_eq!(parse_value(tag, &value.payload()), Ok(value));
+        }
+    }
+
+    #[test]
+    fn should_wrong_extended_values_raise_an_error() {
+        assert_eq!(
+            parse_value("u8", "256"),
+            Err(MessageParseError::out_of_range("u8", "256"))
+        );
+        assert_eq!(
+            parse_value("u64", "-1"),
+            Err(MessageParseError::invalid_value("u64", "-1"))
+        );
+        assert_eq!(
+            parse_value("b", "AP8"),
+            Err(MessageParseError::invalid_value("b", "AP8"))
+        );
+        let long = encode_base64(&[1; MAX_BYTES_LEN + 1]);
+        assert_eq!(
+            parse_value("b", &long),
+            Err(MessageParseError::ValueTooLong)
+        );
+        assert_eq!(
+            parse_value("ts", "2024-13-01T00:00:00Z"),
+            Err(MessageParseError::invalid_value(
+                "ts",
+                "2024-13-01T00:00:00Z"
+            ))
+        );
+        assert_eq!(
+            parse_value("d", "-5"),
+            Err(MessageParseError::invalid_value("d", "-5"))
+        );
+        assert_eq!(
+            parse_value("uuid", "not-a-uuid"),
+            Err(MessageParseError::invalid_value("uuid", "not-a-uuid"))
+        );
+    }
+
+    #[test]
+    fn should_collection_commands_could_be_parse() {
+        let result = Message::try_from("LPUSH|queue|i32|7|".as_bytes()).unwrap();
+        assert_eq!(result.command, Command::Lpush);
+        assert_eq!(result.value, Some(Value::LargeNumber(7)));
+
+        let result = Message::try_from("LRANGE|queue|0|-1|".as_bytes()).unwrap();
+        assert_eq!(result.range, Some((0, -1)));
+
+        let result = Message::try_from("HSET|user|name|s|dory|".as_bytes()).unwrap();
+        assert_eq!(result.field, Some("name".to_string()));
+        assert_eq!(result.value, Some(Value::Text("dory".to_string())));
+
+        let result = Message::try_from("HGETALL|user|".as_bytes()).unwrap();
+        assert_eq!(result.command, Command::Hgetall);
+
+        assert_eq!(
+            Message::try_from("HGET|user|VeryVeryLongFieldName|".as_bytes()),
+            Err(MessageParseError::KeyNameTooLong)
+        );
+        assert_eq!(
+            Message::try_from("LRANGE|queue|0|".as_bytes()),
+            Err(MessageParseError::Pattern)
+        );
+        assert_eq!(
+            Message::try_from("LRANGE|queue|a|1|".as_bytes()),
+            Err(MessageParseError::invalid_value("i64", "a"))
+        );
+        assert_eq!(
+            Message::try_from("SADD|tags|list|a|".as_bytes()),
+            Err(MessageParseError::UnknownType("list".to_string()))
+        );
+    }
+
+    #[test]
+    fn should_version_commands_could_be_parse() {
+        let version = "936da01f-9abd-4d9d-80c7-02af85c822a8";
+        let result =
+            Message::try_from(format!("CAS|config|{}|i8|3|60|", version).as_bytes()).unwrap();
+        assert_eq!(result.command, Command::Cas);
+        assert_eq!(result.version, Some(Uuid::parse_str(version).unwrap()));
+        assert_eq!(result.value, Some(Value::ThinNumber(3)));
+        assert_eq!(result.ttl, Some(Duration::from_secs(60)));
+
+        let result = Message::try_from("GET|config|uuid|".as_bytes()).unwrap();
+        assert!(result.versioned);
+        assert_eq!(
+            Message::try_from("CAS|config|v1|i8|3|".as_bytes()),
+            Err(MessageParseError::invalid_value("uuid", "v1"))
+        );
+        assert_eq!(
+            Message::try_from("GET|config|version|".as_bytes()),
+            Err(MessageParseError::Pattern)
+        );
+    }
+
+    #[test]
+    fn should_multi_key_commands_could_be_parse() {
+        let result = Message::try_from("MGET|a|b\\|c|".as_bytes()).unwrap();
+        assert_eq!(result.command, Command::Mget);
+        let keys: Vec<&str> = result.batch.iter().map(|m| m.key.as_str()).collect();
+        assert_eq!(keys, vec!["a", "b|c"]);
+        assert!(result.batch.iter().all(|m| m.command == Command::Get));
+
+        let result = Message::try_from("MSET|a|s|on|b|i8|2|all|".as_bytes()).unwrap();
+        assert!(result.atomic);
+        assert_eq!(result.batch[1].command, Command::Add);
+        assert_eq!(result.batch[1].value, Some(Value::ThinNumber(2)));
+        let result = Message::try_from("MSET|all|s|on|".as_bytes()).unwrap();
+        assert!(!result.atomic);
+        assert_eq!(result.batch[0].key, "all");
+
+        assert_eq!(
+            Message::try_from("MSET|a|s|".as_bytes()),
+            Err(MessageParseError::Pattern)
+        );
+        assert_eq!(
+            Message::try_from("MDEL|".as_bytes()),
+            Err(MessageParseError::Pattern)
+        );
+        assert_eq!(
+            Message::try_from("MGET|a|VeryVeryLongKeyName|".as_bytes()),
+            Err(MessageParseError::KeyNameTooLong)
+        );
+    }
+
+    #[test]
+    fn should_counter_commands_could_be_parse() {
+        let result = Message::try_from("INC|hits|".as_bytes()).unwrap();
+        assert_eq!(
+            result.operation(),
+            Some(Operation::Increment(Value::LargeNumber(1), false))
+        );
+        let result = Message::try_from("DEC|hits|new|".as_bytes()).unwrap();
+        assert_eq!(
+            result.operation(),
+            Some(Operation::Increment(Value::LargeNumber(-1), true))
+        );
+        let result = Message::try_from("IBY|load|f64|0.5|new|".as_bytes()).unwrap();
+        assert_eq!(result.value, Some(Value::LargeFloat(0.5)));
+        assert!(result.create);
+        let result = Message::try_from("INC|visits|text|new|".as_bytes()).unwrap();
+        assert_eq!(
+            result.operation(),
+            Some(Operation::TextIncrement(Value::LargeNumber(1), true))
+        );
+        let result = Message::try_from("IBY|visits|i64|5|text|".as_bytes()).unwrap();
+        assert_eq!(
+            result.operation(),
+            Some(Operation::TextIncrement(Value::HugeNumber(5), false))
+        );
+        assert!(matches!(
+            Message::try_from("IBY|visits|f64|0.5|text|".as_bytes()),
+            Err(MessageParseError::InvalidValue { .. })
+        ));
+        assert_eq!(
+            Message::try_from("INC|visits|new|new|".as_bytes()),
+            Err(MessageParseError::Pattern)
+        );
+
+        assert_eq!(
+            Message::try_from("INC|hits|old|".as_bytes()),
+            Err(MessageParseError::Pattern)
+        );
+        assert_eq!(
+            Message::try_from("IBY|hits|s|1|".as_bytes()),
+            Err(MessageParseError::invalid_value("s", "1"))
+        );
+    }
+
+    #[test]
+    fn should_collection_parts_survive_round_trip() {
+        let values = vec![
+            Value::Text("a|b".to_string()),
+            Value::List(vec![Value::ThinNumber(1), Value::Text("x\n".to_string())]),
+            Value::Set(vec![]),
+            Value::Hash(vec![
+                ("na|me".to_string(), Value::Text("dory".to_string())),
+                ("on".to_string(), Value::Logical(true)),
+            ]),
+        ];
+        for v in values {
+            let message = format!("{}|", value_parts(&v).join("|"));
+            assert_eq!(parse_parts(&split(&message).unwrap()), Ok(v));
+        }
+        let parts: Vec<String> = ["list", "2", "i8", "1"].map(String::from).to_vec();
+        assert_eq!(parse_parts(&parts), Err(MessageParseError::Pattern));
+        let parts: Vec<String> = ["list", "1", "list", "0"].map(String::from).to_vec();
+        assert!(parse_parts(&parts).is_err());
+    }
 }
@@ -1,17 +1,36 @@
+pub use add_mode::AddMode;
+pub use batch::Batch;
 pub use candidate::Candidate;
 pub use command::Command;
+pub use eviction_policy::EvictionPolicy;
+pub use expiry::Expiry;
 pub use item::Item;
 pub use message::Message;
+pub use operation::{Applied, Change, Operation};
 pub use pack::Pack;
 pub use pack_state::PackState;
+pub use pack_stats::PackStats;
+pub use random::Random;
 pub use search::Search;
+pub use update::Update;
+pub use usage::Usage;
 pub use value::Value;
 
+pub mod add_mode;
+pub mod batch;
 pub mod candidate;
 pub mod command;
+pub mod eviction_policy;
+pub mod expiry;
+pub mod format;
 pub mod item;
 pub mod message;
+pub mod operation;
 pub mod pack;
 pub mod pack_state;
+pub mod pack_stats;
+pub mod random;
 pub mod search;
+pub mod update;
+pub mod usage;
 pub mod value;
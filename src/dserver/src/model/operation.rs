@@ -0,0 +1,410 @@
+use super::{Item, Value};
+use crate::constant::constant::{MAX_COLLECTION_LEN, MAX_KEY_LEN};
+use crate::derror::new_item_error::NewItemError;
+use crate::derror::operation_error::OperationError;
+
+/// A command that works on the list, set, hash or number value of a key.
+#[derive(Debug, PartialEq, Clone)]
+pub enum Operation {
+    /// Pushes the element to the head of the list
+    ListPush(Value),
+    /// Removes the element at the head of the list
+    ListPop,
+    /// Returns the elements between two positions, both are included. Negative positions count from the tail.
+    ListRange(i64, i64),
+    /// Adds the member to the set if it is not there
+    SetAdd(Value),
+    SetRemove(Value),
+    SetMembers,
+    /// Sets the value of the field, the field is added if it is not there
+    HashSet(String, Value),
+    HashGet(String),
+    HashDelete(String),
+    HashGetAll,
+    /// Adds the amount to the number. A missing key starts from zero if the flag is true.
+    Increment(Value, bool),
+    /// Adds the integer amount like Increment, text that holds a 64-bit integer counts as a number and stays text.
+    /// Redis clients keep their counters as strings, so RESP INCR uses it.
+    TextIncrement(Value, bool),
+}
+
+/// The change that an operation made to the item of the key.
+#[derive(Debug, Clone)]
+pub enum Change {
+    /// Nothing has changed, the operation only read the collection.
+    Unchanged,
+    /// The item is added or its collection is changed.
+    Put(Item),
+    /// The collection became empty, so the key is removed.
+    Removed(String),
+}
+
+/// The result of an operation that a pack applied.
+#[derive(Debug, Clone)]
+pub struct Applied {
+    /// The value that is returned to the requester.
+    pub reply: Value,
+    pub change: Change,
+    /// The key that left the pack to make room for a new collection.
+    pub evicted: Option<String>,
+}
+
+impl Operation {
+    /// Checks the element or the field that the operation adds.
+    pub fn validate(&self) -> Result<(), NewItemError> {
+        match self {
+            Self::ListPush(e) | Self::SetAdd(e) | Self::SetRemove(e) => Item::validate_element(e),
+            Self::HashSet(f, _) | Self::HashGet(f) | Self::HashDelete(f)
+                if f.len() > MAX_KEY_LEN =>
+            {
+                Err(NewItemError::InvalidKeyLen)
+            }
+            Self::HashSet(_, e) => Item::validate_element(e),
+            Self::Increment(amount, _) if !amount.is_number() => Err(NewItemError::NotNumber),
+            Self::TextIncrement(amount, _) if integer(amount).is_none() => {
+                Err(NewItemError::NotNumber)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Returns the empty collection that the operation creates for a missing key.
+    ///
+    /// Only the operations that add an element create a collection.
+    /// An increment that is allowed to create the key starts from the zero of its amount's type.
+    pub fn empty(&self) -> Option<Value> {
+        match self {
+            Self::ListPush(_) => Some(Value::List(Vec::new())),
+            Self::SetAdd(_) => Some(Value::Set(Vec::new())),
+            Self::HashSet(..) => Some(Value::Hash(Vec::new())),
+            Self::Increment(Value::ThinFloat(_), true) => Some(Value::ThinFloat(0.0)),
+            Self::Increment(Value::LargeFloat(_), true) => Some(Value::LargeFloat(0.0)),
+            Self::Increment(amount, true) => with_integer(amount, 0),
+            Self::TextIncrement(amount, true) => with_integer(amount, 0),
+            _ => None,
+        }
+    }
+
+    /// Applies the operation to the collection and returns the reply with a flag that is true if the collection changed.
+    ///
+    /// Adding to a collection that already has MAX_COLLECTION_LEN elements returns OperationError::CollectionFull.
+    /// An increment keeps the type of the number and returns OperationError::Overflow instead of wrapping.
+    pub fn run(&self, value: &mut Value) -> Result<(Value, bool), OperationError> {
+        match (self, value) {
+            (Self::ListPush(e), Value::List(list)) => {
+                if list.len() >= MAX_COLLECTION_LEN {
+                    return Err(OperationError::CollectionFull);
+                }
+                list.insert(0, e.clone());
+                Ok((count(list.len()), true))
+            }
+            (Self::ListPop, Value::List(list)) if list.is_empty() => Err(OperationError::NotFound),
+            (Self::ListPop, Value::List(list)) => Ok((list.remove(0), true)),
+            (Self::ListRange(start, stop), Value::List(list)) => {
+                Ok((Value::List(range(list, *start, *stop).to_vec()), false))
+            }
+            (Self::SetAdd(e), Value::Set(set)) => {
+                if set.contains(e) {
+                    return Ok((count(0), false));
+                }
+                if set.len() >= MAX_COLLECTION_LEN {
+                    return Err(OperationError::CollectionFull);
+                }
+                set.push(e.clone());
+                Ok((count(1), true))
+            }
+            (Self::SetRemove(e), Value::Set(set)) => match set.iter().position(|m| m == e) {
+                Some(p) => {
+                    set.remove(p);
+                    Ok((count(1), true))
+                }
+                None => Ok((count(0), false)),
+            },
+            (Self::SetMembers, Value::Set(set)) => Ok((Value::Set(set.clone()), false)),
+            (Self::HashSet(f, e), Value::Hash(hash)) => {
+                match hash.iter().position(|(name, _)| name == f) {
+                    Some(p) => {
+                        let changed = hash[p].1 != *e;
+                        hash[p].1 = e.clone();
+                        Ok((count(0), changed))
+                    }
+                    None if hash.len() >= MAX_COLLECTION_LEN => Err(OperationError::CollectionFull),
+                    None => {
+                        hash.push((f.clone(), e.clone()));
+                        Ok((count(1), true))
+                    }
+                }
+            }
+            (Self::HashGet(f), Value::Hash(hash)) => hash
+                .iter()
+                .find(|(name, _)| name == f)
+                .map(|(_, v)| (v.clone(), false))
+                .ok_or(OperationError::NotFound),
+            (Self::HashDelete(f), Value::Hash(hash)) => {
+                match hash.iter().position(|(name, _)| name == f) {
+                    Some(p) => {
+                        hash.remove(p);
+                        Ok((count(1), true))
+                    }
+                    None => Ok((count(0), false)),
+                }
+            }
+            (Self::HashGetAll, Value::Hash(hash)) => Ok((Value::Hash(hash.clone()), false)),
+            (Self::Increment(amount, _), number) if number.is_number() => {
+                *number = add(number, amount)?;
+                Ok((number.clone(), true))
+            }
+            (Self::TextIncrement(amount, _), Value::Text(text)) => {
+                let n = text
+                    .parse::<i64>()
+                    .ok()
+                    .filter(|n| n.to_string() == *text)
+                    .ok_or(OperationError::WrongType)?;
+                let a = integer(amount).ok_or(OperationError::WrongType)?;
+                let sum = i64::try_from(i128::from(n) + a).map_err(|_| OperationError::Overflow)?;
+                *text = sum.to_string();
+                Ok((Value::HugeNumber(sum), true))
+            }
+            (Self::TextIncrement(amount, _), number) if number.is_number() => {
+                *number = add(number, amount)?;
+                Ok((number.clone(), true))
+            }
+            _ => Err(OperationError::WrongType),
+        }
+    }
+}
+
+/// Adds the amount to the number in the type of the number.
+///
+/// Integers are added as i128 and must fit back in their type, a float amount can't be added to an integer.
+/// Floats are added as f64 and the result must be finite.
+fn add(number: &Value, amount: &Value) -> Result<Value, OperationError> {
+    match (integer(number), integer(amount)) {
+        (Some(n), Some(a)) => n
+            .checked_add(a)
+            .and_then(|sum| with_integer(number, sum))
+            .ok_or(OperationError::Overflow),
+        (Some(_), None) => Err(OperationError::WrongType),
+        (None, _) => {
+            let sum = float(number).zip(float(amount)).map(|(n, a)| n + a);
+            match (number, sum) {
+                (Value::ThinFloat(_), Some(s)) if (s as f32).is_finite() => {
+                    Ok(Value::ThinFloat(s as f32))
+                }
+                (Value::LargeFloat(_), Some(s)) if s.is_finite() => Ok(Value::LargeFloat(s)),
+                (_, Some(_)) => Err(OperationError::Overflow),
+                (_, None) => Err(OperationError::WrongType),
+            }
+        }
+    }
+}
+
+fn integer(value: &Value) -> Option<i128> {
+    match value {
+        Value::ThinNumber(n) => Some(i128::from(*n)),
+        Value::MidNumber(n) => Some(i128::from(*n)),
+        Value::LargeNumber(n) => Some(i128::from(*n)),
+        Value::HugeNumber(n) => Some(i128::from(*n)),
+        Value::GiantNumber(n) => Some(*n),
+        Value::ThinUnsigned(n) => Some(i128::from(*n)),
+        Value::MidUnsigned(n) => Some(i128::from(*n)),
+        Value::LargeUnsigned(n) => Some(i128::from(*n)),
+        Value::HugeUnsigned(n) => Some(i128::from(*n)),
+        _ => None,
+    }
+}
+
+fn float(value: &Value) -> Option<f64> {
+    match value {
+        Value::ThinFloat(f) => Some(f64::from(*f)),
+        Value::LargeFloat(f) => Some(*f),
+        v => integer(v).map(|n| n as f64),
+    }
+}
+
+/// Returns the integer in the type of the template, or None if it doesn't fit or the template is not an integer.
+fn with_integer(template: &Value, n: i128) -> Option<Value> {
+    let value = match template {
+        Value::ThinNumber(_) => Value::ThinNumber(n.try_into().ok()?),
+        Value::MidNumber(_) => Value::MidNumber(n.try_into().ok()?),
+        Value::LargeNumber(_) => Value::LargeNumber(n.try_into().ok()?),
+        Value::HugeNumber(_) => Value::HugeNumber(n.try_into().ok()?),
+        Value::GiantNumber(_) => Value::GiantNumber(n),
+        Value::ThinUnsigned(_) => Value::ThinUnsigned(n.try_into().ok()?),
+        Value::MidUnsigned(_) => Value::MidUnsigned(n.try_into().ok()?),
+        Value::LargeUnsigned(_) => Value::LargeUnsigned(n.try_into().ok()?),
+        Value::HugeUnsigned(_) => Value::HugeUnsigned(n.try_into().ok()?),
+        _ => return None,
+    };
+    Some(value)
+}
+
+fn count(n: usize) -> Value {
+    Value::LargeNumber(n as i32)
+}
+
+/// Returns the part of the list between the positions like Redis does.
+///
+/// Positions out of the list are clamped, an empty part is returned if start comes after stop.
+fn range(list: &[Value], start: i64, stop: i64) -> &[Value] {
+    let len = list.len() as i64;
+    let start = if start < 0 {
+        (len + start).max(0)
+    } else {
+        start
+    };
+    let stop = if stop < 0 {
+        len + stop
+    } else {
+        stop.min(len - 1)
+    };
+    if start > stop {
+        return &[];
+    }
+    &list[start as usize..=stop as usize]
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn numbers(values: &[i32]) -> Vec<Value> {
+        values.iter().map(|n| Value::LargeNumber(*n)).collect()
+    }
+
+    #[test]
+    fn should_list_range_count_from_both_ends() {
+        let list = numbers(&[0, 1, 2, 3]);
+        assert_eq!(range(&list, 0, -1), &list[..]);
+        assert_eq!(range(&list, 1, 2), &list[1..3]);
+        assert_eq!(range(&list, -2, 100), &list[2..]);
+        assert_eq!(range(&list, -100, 0), &list[..1]);
+        assert!(range(&list, 3, 1).is_empty());
+        assert!(range(&list, 0, -100).is_empty());
+        assert!(range(&list, 10, 20).is_empty());
+        assert!(range(&[], 0, -1).is_empty());
+    }
+
+    #[test]
+    fn should_operations_reply_and_report_changes() {
+        let mut list = Value::List(Vec::new());
+        let push = Operation::ListPush(Value::LargeNumber(1));
+        assert_eq!(push.run(&mut list), Ok((Value::LargeNumber(1), true)));
+        let push = Operation::ListPush(Value::LargeNumber(2));
+        assert_eq!(push.run(&mut list), Ok((Value::LargeNumber(2), true)));
+        assert_eq!(list, Value::List(numbers(&[2, 1])));
+        assert_eq!(
+            Operation::ListPop.run(&mut list),
+            Ok((Value::LargeNumber(2), true))
+        );
+
+        let mut set = Value::Set(Vec::new());
+        let add = Operation::SetAdd(Value::ThinNumber(1));
+        assert_eq!(add.run(&mut set), Ok((Value::LargeNumber(1), true)));
+        assert_eq!(add.run(&mut set), Ok((Value::LargeNumber(0), false)));
+        let remove = Operation::SetRemove(Value::ThinNumber(2));
+        assert_eq!(remove.run(&mut set), Ok((Value::LargeNumber(0), false)));
+
+        let mut hash = Value::Hash(Vec::new());
+        let set_field = Operation::HashSet("beta".to_string(), Value::Logical(true));
+        assert_eq!(set_field.run(&mut hash), Ok((Value::LargeNumber(1), true)));
+        assert_eq!(set_field.run(&mut hash), Ok((Value::LargeNumber(0), false)));
+        let get = Operation::HashGet("beta".to_string());
+        assert_eq!(get.run(&mut hash), Ok((Value::Logical(true), false)));
+        let get = Operation::HashGet("none".to_string());
+        assert_eq!(get.run(&mut hash), Err(OperationError::NotFound));
+    }
+
+    #[test]
+    fn should_increments_keep_the_type_and_detect_overflow() {
+        let one = Operation::Increment(Value::LargeNumber(1), false);
+        let mut level = Value::ThinNumber(126);
+        assert_eq!(one.run(&mut level), Ok((Value::ThinNumber(127), true)));
+        assert_eq!(one.run(&mut level), Err(OperationError::Overflow));
+        assert_eq!(level, Value::ThinNumber(127));
+
+        let minus = Operation::Increment(Value::LargeNumber(-1), false);
+        let mut hits = Value::ThinUnsigned(0);
+        assert_eq!(minus.run(&mut hits), Err(OperationError::Overflow));
+        let mut huge = Value::HugeUnsigned(u64::MAX);
+        assert_eq!(
+            minus.run(&mut huge),
+            Ok((Value::HugeUnsigned(u64::MAX - 1), true))
+        );
+        let mut giant = Value::GiantNumber(i128::MAX);
+        assert_eq!(one.run(&mut giant), Err(OperationError::Overflow));
+
+        let half = Operation::Increment(Value::LargeFloat(0.5), false);
+        let mut ratio = Value::ThinFloat(1.0);
+        assert_eq!(half.run(&mut ratio), Ok((Value::ThinFloat(1.5), true)));
+        let mut big = Value::LargeFloat(f64::MAX);
+        let max = Operation::Increment(Value::LargeFloat(f64::MAX), false);
+        assert_eq!(max.run(&mut big), Err(OperationError::Overflow));
+        let mut ratio = Value::LargeFloat(1.0);
+        assert_eq!(one.run(&mut ratio), Ok((Value::LargeFloat(2.0), true)));
+
+        let mut count = Value::LargeNumber(1);
+        assert_eq!(half.run(&mut count), Err(OperationError::WrongType));
+        let mut text = Value::Text("1".to_string());
+        assert_eq!(one.run(&mut text), Err(OperationError::WrongType));
+    }
+
+    #[test]
+    fn should_text_increment_count_integer_text_as_number() {
+        let incr = Operation::TextIncrement(Value::HugeNumber(1), true);
+        let mut visits = Value::Text("5".to_string());
+        assert_eq!(incr.run(&mut visits), Ok((Value::HugeNumber(6), true)));
+        assert_eq!(visits, Value::Text("6".to_string()));
+        let mut hits = Value::ThinNumber(1);
+        assert_eq!(incr.run(&mut hits), Ok((Value::ThinNumber(2), true)));
+        assert_eq!(incr.empty(), Some(Value::HugeNumber(0)));
+
+        let only = Operation::TextIncrement(Value::ThinUnsigned(1), false);
+        assert_eq!(only.empty(), None);
+        let half = Operation::TextIncrement(Value::LargeFloat(0.5), true);
+        assert_eq!(half.validate(), Err(NewItemError::NotNumber));
+
+        for text in ["hello", "01", "+1", " 1", ""] {
+            let mut value = Value::Text(text.to_string());
+            assert_eq!(incr.run(&mut value), Err(OperationError::WrongType));
+        }
+        let mut max = Value::Text(i64::MAX.to_string());
+        assert_eq!(incr.run(&mut max), Err(OperationError::Overflow));
+        assert_eq!(max, Value::Text(i64::MAX.to_string()));
+    }
+
+    #[test]
+    fn should_increment_create_zero_of_the_amount_type() {
+        let create = Operation::Increment(Value::HugeNumber(5), true);
+        assert_eq!(create.empty(), Some(Value::HugeNumber(0)));
+        let create = Operation::Increment(Value::ThinFloat(0.5), true);
+        assert_eq!(create.empty(), Some(Value::ThinFloat(0.0)));
+        let only = Operation::Increment(Value::HugeNumber(5), false);
+        assert_eq!(only.empty(), None);
+        let text = Operation::Increment(Value::Text("1".to_string()), false);
+        assert_eq!(text.validate(), Err(NewItemError::NotNumber));
+    }
+
+    #[test]
+    fn should_wrong_kind_and_full_collection_raise_an_error() {
+        let mut text = Value::Text("on".to_string());
+        assert_eq!(
+            Operation::ListPop.run(&mut text),
+            Err(OperationError::WrongType)
+        );
+        let mut set = Value::Set(Vec::new());
+        assert_eq!(
+            Operation::HashGetAll.run(&mut set),
+            Err(OperationError::WrongType)
+        );
+
+        let mut full = Value::List(numbers(&[0; MAX_COLLECTION_LEN]));
+        let push = Operation::ListPush(Value::LargeNumber(1));
+        assert_eq!(push.run(&mut full), Err(OperationError::CollectionFull));
+
+        let nested = Operation::SetAdd(Value::List(Vec::new()));
+        assert_eq!(nested.validate(), Err(NewItemError::NestedCollection));
+        let long = Operation::HashGet("f".repeat(MAX_KEY_LEN + 1));
+        assert_eq!(long.validate(), Err(NewItemError::InvalidKeyLen));
+    }
+}
@@ -1,7 +1,16 @@
+use super::AddMode;
+use super::EvictionPolicy;
 use super::Item;
 use super::PackState;
+use super::PackStats;
+use super::Usage;
+use super::{Applied, Change, Operation};
 use crate::constant::constant::MAX_ITEM;
+use crate::derror::operation_error::OperationError;
 use log::warn;
+use std::collections::HashMap;
+use std::time::{Duration, SystemTime};
+use uuid::Uuid;
 
 /// The package object that holds the Item collection.
 ///
@@ -17,7 +26,7 @@ use log::warn;
 ///  };
 ///
 /// // Add some item into this pack
-/// let item = Item::new("server", Value::Text("london")).unwrap();
+/// let item = Item::new("server".to_string(), Value::Text("london".to_string())).unwrap();
 /// pack.add(item);
 ///
 /// assert!(pack.get_head() == 1);
@@ -27,47 +36,280 @@ pub struct Pack {
     pub id: u32,
     pub items: Vec<Item>,
     pub head: u16,
+    /// Position of every key in the items vector.
+    pub index: HashMap<String, usize>,
+    pub policy: EvictionPolicy,
+    /// Logical clock that orders the accesses to the items.
+    pub clock: u64,
 }
 
 #[allow(dead_code)]
 impl Pack {
-    /// Adds a new item to the pack
+    /// Adds a new item to the pack or replaces the item of the same key.
     ///
     /// # Warning
     ///
-    /// PackState::CapacityFull is returned if the package content has reached the maximum number of elements.
+    /// If the package content has reached the maximum number of elements, expired items are removed first.
+    /// If there is still no room, an item is evicted according to the eviction policy
+    /// and PackState::Evicted is returned. PackState::CapacityFull is returned with the Reject policy.
     /// Otherwise, the item is added to the package.
     pub fn add(&mut self, item: Item) -> Option<PackState> {
-        self.head += 1;
-        match &self.head {
-            0..=MAX_ITEM => {
-                self.items.push(item.clone());
-                Some(PackState::Added(item.uuid))
+        self.put(item, AddMode::Upsert)
+    }
+
+    /// Adds the item to the pack according to the add mode.
+    ///
+    /// If the key is already in the pack, the existing item is replaced by the new one.
+    /// So the value and the uuid of the key change together and PackState::Updated is returned.
+    /// PackState::KeyExists and PackState::KeyMissing are returned when the mode doesn't allow the operation.
+    pub fn put(&mut self, item: Item, mode: AddMode) -> Option<PackState> {
+        self.remove_if_expired(&item.key);
+        match (self.index.get(&item.key).copied(), mode) {
+            (Some(_), AddMode::IfAbsent) => Some(PackState::KeyExists),
+            (Some(position), AddMode::IfVersion(uuid)) if self.items[position].uuid != uuid => {
+                Some(PackState::Conflict)
+            }
+            (Some(position), _) => {
+                let uuid = item.uuid;
+                let mut item = item;
+                item.usage = self.items[position].usage;
+                item.usage.accessed = self.tick();
+                self.items[position] = item;
+                Some(PackState::Updated(uuid))
             }
-            _ => {
+            (None, AddMode::IfPresent | AddMode::IfVersion(_)) => Some(PackState::KeyMissing),
+            (None, _) => self.push(item),
+        }
+    }
+
+    fn push(&mut self, item: Item) -> Option<PackState> {
+        if self.items.len() >= MAX_ITEM as usize && self.reap(1).is_empty() {
+            if self.policy == EvictionPolicy::Reject {
                 warn!("Capacity is full for Pack #{}", self.id);
-                Some(PackState::CapacityFull)
+                return Some(PackState::CapacityFull);
+            }
+            let victim = self.victim()?.key.clone();
+            warn!(
+                "{} evicted from Pack #{} by {:?}",
+                victim, self.id, self.policy
+            );
+            self.remove(victim.clone());
+            let added = self.insert(item);
+            return Some(PackState::Evicted { added, key: victim });
+        }
+        Some(PackState::Added(self.insert(item)))
+    }
+
+    fn insert(&mut self, mut item: Item) -> Uuid {
+        let tick = self.tick();
+        item.usage = Usage {
+            inserted: tick,
+            accessed: tick,
+            hits: 0,
+        };
+        let uuid = item.uuid;
+        self.index.insert(item.key.clone(), self.items.len());
+        self.items.push(item);
+        self.head = self.items.len() as u16;
+        uuid
+    }
+
+    /// Returns the item that the eviction policy chooses to leave the pack.
+    fn victim(&self) -> Option<&Item> {
+        match self.policy {
+            EvictionPolicy::Reject => None,
+            EvictionPolicy::Lru => self.items.iter().min_by_key(|i| i.usage.accessed),
+            EvictionPolicy::Lfu => self
+                .items
+                .iter()
+                .min_by_key(|i| (i.usage.hits, i.usage.accessed)),
+            EvictionPolicy::Fifo => self.items.iter().min_by_key(|i| i.usage.inserted),
+            EvictionPolicy::Random => {
+                // The random part of a v4 uuid is enough to pick an item.
+                let position = Uuid::new_v4().as_u128() % self.items.len().max(1) as u128;
+                self.items.get(position as usize)
             }
         }
     }
 
+    fn tick(&mut self) -> u64 {
+        self.clock += 1;
+        self.clock
+    }
+
     /// Empties the pack contents and returns the head to the initial position.
     pub fn drop(&mut self) -> &Self {
         warn!("Pack #{} dropped", self.id);
         self.items = Vec::new();
+        self.index.clear();
         self.head = 0;
         self
     }
 
     /// Retrieves the value of a key from within the pack.
-    pub fn get(&self, key: String) -> Option<&Item> {
-        self.items.iter().find(|i| i.key == key)
+    ///
+    /// If the item of the key is expired, it is removed and None is returned.
+    /// Every successful get is recorded in the usage of the item.
+    pub fn get(&mut self, key: String) -> Option<&Item> {
+        self.remove_if_expired(&key);
+        let position = *self.index.get(&key)?;
+        let tick = self.tick();
+        let item = &mut self.items[position];
+        item.usage.accessed = tick;
+        item.usage.hits += 1;
+        Some(item)
+    }
+
+    /// Returns true if the key has an item that isn't expired.
+    ///
+    /// Unlike `get` it isn't a use of the item, so it never changes which item is evicted.
+    pub fn contains(&self, key: &str) -> bool {
+        self.index
+            .get(key)
+            .is_some_and(|p| !self.items[*p].is_expired(SystemTime::now()))
+    }
+
+    /// Changes the expiry of the key's item. The item never expires if the ttl is None.
+    ///
+    /// Returns the uuid of the item or None if there is no such key.
+    pub fn expire(&mut self, key: String, ttl: Option<Duration>) -> Option<Uuid> {
+        self.remove_if_expired(&key);
+        let position = *self.index.get(&key)?;
+        let item = &mut self.items[position];
+        match ttl {
+            Some(t) => item.expire_after(t),
+            None => item.persist(),
+        }
+        Some(item.uuid)
+    }
+
+    /// Applies the collection operation to the item of the key.
+    ///
+    /// A missing key gets a new collection if the operation adds an element, otherwise OperationError::NotFound is returned.
+    /// A new collection may evict another key like an added item does.
+    /// A changed item gets a new uuid, and it is removed when its collection becomes empty.
+    pub fn apply(&mut self, key: String, operation: &Operation) -> Result<Applied, OperationError> {
+        operation.validate()?;
+        self.remove_if_expired(&key);
+        let position = match self.index.get(&key) {
+            Some(p) => *p,
+            None => return self.create(key, operation),
+        };
+        let tick = self.tick();
+        let item = &mut self.items[position];
+        let (reply, changed) = operation.run(&mut item.value)?;
+        item.usage.accessed = tick;
+        let change = match changed {
+            false => {
+                item.usage.hits += 1;
+                Change::Unchanged
+            }
+            true if item.value.is_empty_collection() => {
+                self.remove(key.clone());
+                Change::Removed(key)
+            }
+            true => {
+                item.uuid = Uuid::new_v4();
+                Change::Put(item.clone())
+            }
+        };
+        Ok(Applied {
+            reply,
+            change,
+            evicted: None,
+        })
+    }
+
+    fn create(&mut self, key: String, operation: &Operation) -> Result<Applied, OperationError> {
+        let mut value = operation.empty().ok_or(OperationError::NotFound)?;
+        let (reply, _) = operation.run(&mut value)?;
+        let item = Item::new(key, value)?;
+        let evicted = match self.push(item.clone()) {
+            Some(PackState::Added(_)) => None,
+            Some(PackState::Evicted { key, .. }) => Some(key),
+            _ => return Err(OperationError::CapacityFull),
+        };
+        Ok(Applied {
+            reply,
+            change: Change::Put(item),
+            evicted,
+        })
+    }
+
+    /// Returns true if the items of the keys can be put without rejecting one of them.
+    ///
+    /// Keys that are already in the pack need no room, expired items leave room for new ones.
+    /// Packs that evict have room for any number of items.
+    pub fn fits(&self, keys: &[String]) -> bool {
+        if self.policy != EvictionPolicy::Reject {
+            return true;
+        }
+        let now = SystemTime::now();
+        let mut new_keys: Vec<&String> = keys
+            .iter()
+            .filter(|k| match self.index.get(*k) {
+                Some(p) => self.items[*p].is_expired(now),
+                None => true,
+            })
+            .collect();
+        new_keys.sort();
+        new_keys.dedup();
+        let expired = self.items.iter().filter(|i| i.is_expired(now)).count();
+        new_keys.len() <= MAX_ITEM as usize - self.items.len() + expired
+    }
+
+    /// Removes at most `limit` expired items from the pack and returns them.
+    pub fn reap(&mut self, limit: usize) -> Vec<Item> {
+        let now = SystemTime::now();
+        let expired: Vec<String> = self
+            .items
+            .iter()
+            .filter(|i| i.is_expired(now))
+            .take(limit)
+            .map(|i| i.key.clone())
+            .collect();
+        expired.into_iter().filter_map(|k| self.remove(k)).collect()
+    }
+
+    fn remove_if_expired(&mut self, key: &str) {
+        let expired = match self.index.get(key) {
+            Some(p) => self.items[*p].is_expired(SystemTime::now()),
+            None => false,
+        };
+        if expired {
+            warn!("{} expired in Pack #{}", key, self.id);
+            self.remove(key.to_string());
+        }
+    }
+
+    /// Removes the item of the key from the pack and frees its slot.
+    ///
+    /// The last item is moved into the freed slot, so the removal doesn't shift the items.
+    /// The head is moved back to the number of items kept in the pack.
+    /// None is returned if there is no item with the given key.
+    pub fn remove(&mut self, key: String) -> Option<Item> {
+        let position = self.index.remove(&key)?;
+        let item = self.items.swap_remove(position);
+        if let Some(moved) = self.items.get(position) {
+            self.index.insert(moved.key.clone(), position);
+        }
+        self.head = self.items.len() as u16;
+        Some(item)
     }
 
     /// Returns the current position of the head.
     pub fn get_head(&self) -> u16 {
         self.head
     }
+
+    /// Returns the statistics of the pack.
+    pub fn stats(&self) -> PackStats {
+        PackStats {
+            id: self.id,
+            items: self.items.len(),
+            capacity: MAX_ITEM as usize,
+        }
+    }
 }
 
 #[cfg(test)]
@@ -76,13 +318,14 @@ mod tests {
     use crate::Value;
 
     #[test]
+    #[allow(clippy::single_match)]
     fn should_we_can_add_items_to_pack() {
         let mut pack = Pack {
             id: 23,
             ..Default::default()
         };
         assert_eq!(pack.get_head(), 0);
-        let item = Item::new("server".to_string(), Value::Text("localhost")).unwrap();
+        let item = Item::new("server".to_string(), Value::Text("localhost".to_string())).unwrap();
         assert!(!item.uuid.is_nil());
         let state = pack.add(item);
         assert_eq!(pack.get_head(), 1);
@@ -98,9 +341,9 @@ mod tests {
             id: 23,
             ..Default::default()
         };
-        let item = Item::new("server".to_string(), Value::Text("localhost")).unwrap();
+        let item = Item::new("server".to_string(), Value::Text("localhost".to_string())).unwrap();
         pack.add(item);
-        let item = Item::new("logs_on".to_string(), Value::Text("true")).unwrap();
+        let item = Item::new("logs_on".to_string(), Value::Text("true".to_string())).unwrap();
         pack.add(item);
         assert!(pack.get_head() == 2);
         pack.drop();
@@ -113,12 +356,12 @@ mod tests {
             id: 23,
             ..Default::default()
         };
-        for _ in 0..=999 {
-            let item = Item::new("lorem".to_string(), Value::Text("ipsum")).unwrap();
+        for i in 0..=999 {
+            let item = Item::new(format!("lorem{}", i), Value::Text("ipsum".to_string())).unwrap();
             pack.add(item);
         }
         assert!(pack.get_head() == 1000);
-        let item = Item::new("lorem".to_string(), Value::Text("ipsum")).unwrap();
+        let item = Item::new("lorem".to_string(), Value::Text("ipsum".to_string())).unwrap();
         let state = pack.add(item).unwrap();
         assert_eq!(state, PackState::CapacityFull);
     }
@@ -129,12 +372,442 @@ mod tests {
             id: 23,
             ..Default::default()
         };
-        let item = Item::new("server".to_string(), Value::Text("london")).unwrap();
+        let item = Item::new("server".to_string(), Value::Text("london".to_string())).unwrap();
         pack.add(item);
-        let item = Item::new("debug".to_string(), Value::Text("on")).unwrap();
+        let item = Item::new("debug".to_string(), Value::Text("on".to_string())).unwrap();
         pack.add(item);
 
         let item = pack.get("debug".to_string()).unwrap();
-        assert_eq!(item.value, Value::Text("on"));
+        assert_eq!(item.value, Value::Text("on".to_string()));
+    }
+
+    #[test]
+    fn should_removed_item_frees_its_slot() {
+        let mut pack = Pack {
+            id: 23,
+            ..Default::default()
+        };
+        let item = Item::new("server".to_string(), Value::Text("london".to_string())).unwrap();
+        pack.add(item);
+        let item = Item::new("debug".to_string(), Value::Text("on".to_string())).unwrap();
+        pack.add(item);
+
+        let removed = pack.remove("server".to_string()).unwrap();
+        assert_eq!(removed.value, Value::Text("london".to_string()));
+        assert_eq!(pack.get_head(), 1);
+        assert!(pack.get("server".to_string()).is_none());
+        assert!(pack.get("debug".to_string()).is_some());
+        assert!(pack.remove("server".to_string()).is_none());
+        assert_eq!(pack.get_head(), 1);
+    }
+
+    #[test]
+    fn should_full_pack_accepts_item_after_remove() {
+        let mut pack = Pack {
+            id: 23,
+            ..Default::default()
+        };
+        for i in 0..MAX_ITEM {
+            let item = Item::new(format!("key{}", i), Value::Logical(true)).unwrap();
+            pack.add(item);
+        }
+        let item = Item::new("lorem".to_string(), Value::Text("ipsum".to_string())).unwrap();
+        assert_eq!(pack.add(item.clone()).unwrap(), PackState::CapacityFull);
+
+        pack.remove("key0".to_string());
+        let state = pack.add(item).unwrap();
+        assert!(matches!(state, PackState::Added(_)));
+        assert_eq!(pack.get_head(), MAX_ITEM);
+    }
+
+    #[test]
+    fn should_same_key_updated_instead_of_added_twice() {
+        let mut pack = Pack::default();
+        let first = Item::new("server".to_string(), Value::Text("london".to_string())).unwrap();
+        let first_uuid = first.uuid;
+        pack.add(first);
+        let second = Item::new("server".to_string(), Value::Text("paris".to_string())).unwrap();
+        let second_uuid = second.uuid;
+        let state = pack.add(second).unwrap();
+
+        assert_eq!(state, PackState::Updated(second_uuid));
+        assert_ne!(first_uuid, second_uuid);
+        assert_eq!(pack.get_head(), 1);
+        let item = pack.get("server".to_string()).unwrap();
+        assert_eq!(item.value, Value::Text("paris".to_string()));
+        assert_eq!(item.uuid, second_uuid);
+    }
+
+    #[test]
+    fn should_add_modes_respect_key_presence() {
+        let mut pack = Pack::default();
+        let item = Item::new("debug".to_string(), Value::Logical(true)).unwrap();
+        let state = pack.put(item, AddMode::IfPresent).unwrap();
+        assert_eq!(state, PackState::KeyMissing);
+        assert_eq!(pack.get_head(), 0);
+
+        let item = Item::new("debug".to_string(), Value::Logical(true)).unwrap();
+        let state = pack.put(item, AddMode::IfAbsent).unwrap();
+        assert!(matches!(state, PackState::Added(_)));
+
+        let item = Item::new("debug".to_string(), Value::Logical(false)).unwrap();
+        let state = pack.put(item, AddMode::IfAbsent).unwrap();
+        assert_eq!(state, PackState::KeyExists);
+        let item = pack.get("debug".to_string()).unwrap();
+        assert_eq!(item.value, Value::Logical(true));
+
+        let item = Item::new("debug".to_string(), Value::Logical(false)).unwrap();
+        let state = pack.put(item, AddMode::IfPresent).unwrap();
+        assert!(matches!(state, PackState::Updated(_)));
+        let item = pack.get("debug".to_string()).unwrap();
+        assert_eq!(item.value, Value::Logical(false));
+    }
+
+    #[test]
+    fn should_version_mode_replace_only_the_expected_item() {
+        let mut pack = Pack::default();
+        let item = Item::new("config".to_string(), Value::ThinNumber(1)).unwrap();
+        let state = pack
+            .put(item.clone(), AddMode::IfVersion(item.uuid))
+            .unwrap();
+        assert_eq!(state, PackState::KeyMissing);
+        pack.put(item.clone(), AddMode::Upsert);
+
+        let first = Item::new("config".to_string(), Value::ThinNumber(2)).unwrap();
+        let second = Item::new("config".to_string(), Value::ThinNumber(3)).unwrap();
+        let state = pack
+            .put(first.clone(), AddMode::IfVersion(item.uuid))
+            .unwrap();
+        assert_eq!(state, PackState::Updated(first.uuid));
+        // The second writer read the same uuid, but the item has changed since.
+        let state = pack.put(second, AddMode::IfVersion(item.uuid)).unwrap();
+        assert_eq!(state, PackState::Conflict);
+        let stored = pack.get("config".to_string()).unwrap();
+        assert_eq!(stored.value, Value::ThinNumber(2));
+        assert_eq!(stored.uuid, first.uuid);
+    }
+
+    #[test]
+    fn should_full_pack_updates_existing_key() {
+        let mut pack = Pack::default();
+        for i in 0..MAX_ITEM {
+            let item = Item::new(format!("key{}", i), Value::Logical(true)).unwrap();
+            pack.add(item);
+        }
+        let item = Item::new("key0".to_string(), Value::Logical(false)).unwrap();
+        let state = pack.add(item).unwrap();
+        assert!(matches!(state, PackState::Updated(_)));
+    }
+
+    #[test]
+    fn should_index_stays_consistent_after_changes() {
+        let mut pack = Pack::default();
+        for i in 0..10 {
+            let item = Item::new(format!("key{}", i), Value::LargeNumber(i)).unwrap();
+            pack.add(item);
+        }
+        pack.remove("key0".to_string());
+        pack.remove("key5".to_string());
+        pack.remove("key9".to_string());
+        let item = Item::new("key3".to_string(), Value::LargeNumber(33)).unwrap();
+        pack.add(item);
+
+        assert_eq!(pack.index.len(), pack.items.len());
+        for (key, position) in &pack.index {
+            assert_eq!(&pack.items[*position].key, key);
+        }
+        for i in [1, 2, 4, 6, 7, 8] {
+            let item = pack.get(format!("key{}", i)).unwrap();
+            assert_eq!(item.value, Value::LargeNumber(i));
+        }
+        let item = pack.get("key3".to_string()).unwrap();
+        assert_eq!(item.value, Value::LargeNumber(33));
+
+        pack.drop();
+        assert!(pack.index.is_empty());
+        assert!(pack.get("key1".to_string()).is_none());
+    }
+
+    #[test]
+    fn should_expired_items_invisible_and_reaped() {
+        let mut pack = Pack::default();
+        for i in 0..5 {
+            let mut item = Item::new(format!("token{}", i), Value::LargeNumber(i)).unwrap();
+            if i % 2 == 0 {
+                item.expires_at = Some(SystemTime::now() - Duration::from_secs(1));
+            }
+            pack.add(item);
+        }
+        assert!(pack.get("token0".to_string()).is_none());
+        assert_eq!(pack.items.len(), 4);
+        assert!(pack.get("token1".to_string()).is_some());
+
+        let reaped = pack.reap(1);
+        assert_eq!(reaped.len(), 1);
+        let reaped = pack.reap(10);
+        assert_eq!(reaped.len(), 1);
+        assert_eq!(pack.items.len(), 2);
+        assert_eq!(pack.get_head(), 2);
+        assert!(pack.reap(10).is_empty());
+    }
+
+    #[test]
+    fn should_expire_and_persist_change_item_lifetime() {
+        let mut pack = Pack::default();
+        let item = Item::new("token".to_string(), Value::Logical(true)).unwrap();
+        let uuid = item.uuid;
+        pack.add(item);
+
+        assert_eq!(
+            pack.expire("token".to_string(), Some(Duration::from_secs(60))),
+            Some(uuid)
+        );
+        assert!(pack.get("token".to_string()).unwrap().expires_at.is_some());
+        assert_eq!(pack.expire("token".to_string(), None), Some(uuid));
+        assert!(pack.get("token".to_string()).unwrap().expires_at.is_none());
+        assert_eq!(pack.expire("none".to_string(), None), None);
+
+        pack.expire("token".to_string(), Some(Duration::ZERO));
+        assert!(pack.get("token".to_string()).is_none());
+        let item = Item::new("token".to_string(), Value::Logical(false)).unwrap();
+        let state = pack.put(item, AddMode::IfAbsent).unwrap();
+        assert!(matches!(state, PackState::Added(_)));
+    }
+
+    fn full_pack(policy: EvictionPolicy) -> Pack {
+        let mut pack = Pack {
+            policy,
+            ..Default::default()
+        };
+        for i in 0..MAX_ITEM {
+            let item = Item::new(format!("key{}", i), Value::Logical(true)).unwrap();
+            pack.add(item);
+        }
+        pack
+    }
+
+    fn add_lorem(pack: &mut Pack) -> PackState {
+        let item = Item::new("lorem".to_string(), Value::Text("ipsum".to_string())).unwrap();
+        pack.add(item).unwrap()
+    }
+
+    #[test]
+    fn should_reject_policy_keeps_head_at_capacity() {
+        let mut pack = full_pack(EvictionPolicy::Reject);
+        assert_eq!(add_lorem(&mut pack), PackState::CapacityFull);
+        assert_eq!(add_lorem(&mut pack), PackState::CapacityFull);
+        assert_eq!(pack.get_head(), MAX_ITEM);
+    }
+
+    #[test]
+    fn should_lru_policy_evicts_least_recently_used() {
+        let mut pack = full_pack(EvictionPolicy::Lru);
+        pack.get("key0".to_string());
+        let state = add_lorem(&mut pack);
+        assert!(matches!(state, PackState::Evicted { key, .. } if key == "key1"));
+        assert!(pack.get("key0".to_string()).is_some());
+        assert!(pack.get("lorem".to_string()).is_some());
+        assert_eq!(pack.items.len(), MAX_ITEM as usize);
+    }
+
+    #[test]
+    fn should_contains_leave_eviction_order_unchanged() {
+        let mut pack = full_pack(EvictionPolicy::Lru);
+        assert!(pack.contains("key0"));
+        assert!(!pack.contains("lorem"));
+        let state = add_lorem(&mut pack);
+        assert!(matches!(state, PackState::Evicted { key, .. } if key == "key0"));
+        assert!(!pack.contains("key0"));
+    }
+
+    #[test]
+    fn should_lfu_policy_evicts_least_frequently_used() {
+        let mut pack = full_pack(EvictionPolicy::Lfu);
+        for i in 0..MAX_ITEM {
+            if i != 500 {
+                pack.get(format!("key{}", i));
+            }
+        }
+        let state = add_lorem(&mut pack);
+        assert!(matches!(state, PackState::Evicted { key, .. } if key == "key500"));
+    }
+
+    #[test]
+    fn should_fifo_policy_evicts_oldest() {
+        let mut pack = full_pack(EvictionPolicy::Fifo);
+        pack.get("key0".to_string());
+        let state = add_lorem(&mut pack);
+        assert!(matches!(state, PackState::Evicted { key, .. } if key == "key0"));
+    }
+
+    #[test]
+    fn should_random_policy_evicts_one_item() {
+        let mut pack = full_pack(EvictionPolicy::Random);
+        let state = add_lorem(&mut pack);
+        match state {
+            PackState::Evicted { key, .. } => assert!(pack.get(key).is_none()),
+            s => panic!("Unexpected state {:?}", s),
+        }
+        assert_eq!(pack.items.len(), MAX_ITEM as usize);
+        assert_eq!(pack.index.len(), MAX_ITEM as usize);
+    }
+
+    #[test]
+    fn should_collections_created_changed_and_removed_by_operations() {
+        let mut pack = Pack::default();
+        let pop = Operation::ListPop;
+        assert_eq!(
+            pack.apply("flags".to_string(), &pop).unwrap_err(),
+            OperationError::NotFound
+        );
+
+        let push = Operation::ListPush(Value::Text("beta".to_string()));
+        let applied = pack.apply("flags".to_string(), &push).unwrap();
+        assert_eq!(applied.reply, Value::LargeNumber(1));
+        let uuid = match applied.change {
+            Change::Put(item) => item.uuid,
+            c => panic!("Unexpected change {:?}", c),
+        };
+        let applied = pack.apply("flags".to_string(), &push).unwrap();
+        assert_eq!(applied.reply, Value::LargeNumber(2));
+        assert!(matches!(applied.change, Change::Put(item) if item.uuid != uuid));
+
+        let range = Operation::ListRange(0, -1);
+        let applied = pack.apply("flags".to_string(), &range).unwrap();
+        assert!(matches!(applied.change, Change::Unchanged));
+        assert_eq!(applied.reply.payload(), "[beta, beta]");
+
+        pack.apply("flags".to_string(), &pop).unwrap();
+        let applied = pack.apply("flags".to_string(), &pop).unwrap();
+        assert_eq!(applied.reply, Value::Text("beta".to_string()));
+        assert!(matches!(applied.change, Change::Removed(key) if key == "flags"));
+        assert!(pack.get("flags".to_string()).is_none());
+    }
+
+    #[test]
+    fn should_operation_on_other_kind_raise_wrong_type() {
+        let mut pack = Pack::default();
+        let item = Item::new("debug".to_string(), Value::Logical(true)).unwrap();
+        pack.add(item);
+        let add = Operation::SetAdd(Value::ThinNumber(1));
+        assert_eq!(
+            pack.apply("debug".to_string(), &add).unwrap_err(),
+            OperationError::WrongType
+        );
+        pack.apply("tags".to_string(), &add).unwrap();
+        let field = Operation::HashSet("on".to_string(), Value::Logical(true));
+        assert_eq!(
+            pack.apply("tags".to_string(), &field).unwrap_err(),
+            OperationError::WrongType
+        );
+        assert_eq!(
+            pack.get("debug".to_string()).unwrap().value,
+            Value::Logical(true)
+        );
+    }
+
+    #[test]
+    fn should_new_collection_follow_eviction_policy() {
+        let mut pack = full_pack(EvictionPolicy::Reject);
+        let add = Operation::SetAdd(Value::ThinNumber(1));
+        assert_eq!(
+            pack.apply("tags".to_string(), &add).unwrap_err(),
+            OperationError::CapacityFull
+        );
+        let mut pack = full_pack(EvictionPolicy::Fifo);
+        let applied = pack.apply("tags".to_string(), &add).unwrap();
+        assert_eq!(applied.evicted, Some("key0".to_string()));
+    }
+
+    #[test]
+    fn should_full_pack_fit_only_known_keys() {
+        let mut pack = full_pack(EvictionPolicy::Reject);
+        let keys = |k: &[&str]| k.iter().map(|k| k.to_string()).collect::<Vec<String>>();
+        assert!(pack.fits(&keys(&["key1", "key2", "key1"])));
+        assert!(!pack.fits(&keys(&["key1", "lorem"])));
+        pack.expire("key7".to_string(), Some(Duration::ZERO));
+        assert!(pack.fits(&keys(&["lorem", "lorem"])));
+        assert!(!pack.fits(&keys(&["lorem", "ipsum"])));
+        assert!(full_pack(EvictionPolicy::Lru).fits(&keys(&["lorem", "ipsum"])));
+    }
+
+    #[test]
+    fn should_expired_item_leaves_before_eviction() {
+        let mut pack = full_pack(EvictionPolicy::Reject);
+        pack.expire("key7".to_string(), Some(Duration::ZERO));
+        let state = add_lorem(&mut pack);
+        assert!(matches!(state, PackState::Added(_)));
+    }
+}
+
+/// Micro benchmarks for the key lookup of a full pack.
+///
+/// They are ignored by default. Run them in release mode to compare the linear scan and the index.
+///
+/// `cargo test --release bench -- --ignored --nocapture`
+#[cfg(test)]
+mod bench {
+    use super::*;
+    use crate::Value;
+    use std::hint::black_box;
+    use std::time::{Duration, Instant};
+
+    const ROUNDS: u32 = 100;
+
+    fn full_pack() -> Pack {
+        let mut pack = Pack::default();
+        for i in 0..MAX_ITEM {
+            let item = Item::new(format!("key{}", i), Value::MidNumber(i as i16)).unwrap();
+            pack.add(item);
+        }
+        pack
+    }
+
+    /// Finds the item by scanning every item, the way the pack looked keys up before its index.
+    ///
+    /// It takes an owned key like `Pack::get`, so both sides pay for the same allocation.
+    fn linear(pack: &Pack, key: String) -> Option<&Item> {
+        pack.items.iter().find(|i| i.key == key)
+    }
+
+    fn measure<F: FnMut(&str) -> bool>(name: &str, keys: &[String], mut lookup: F) -> Duration {
+        let start = Instant::now();
+        for _ in 0..ROUNDS {
+            for k in keys {
+                assert!(black_box(lookup(black_box(k))));
+            }
+        }
+        let elapsed = start.elapsed();
+        let per_lookup = elapsed / (ROUNDS * keys.len() as u32);
+        println!("{:<8} {:?} per lookup", name, per_lookup);
+        elapsed
+    }
+
+    #[test]
+    #[ignore]
+    fn bench_lookup_at_full_capacity() {
+        let mut pack = full_pack();
+        let keys: Vec<String> = (0..MAX_ITEM).map(|i| format!("key{}", i)).collect();
+
+        let linear = measure("linear", &keys, |k| linear(&pack, k.to_string()).is_some());
+        let indexed = measure("get", &keys, |k| pack.get(k.to_string()).is_some());
+        println!(
+            "index is {:.1}x faster",
+            linear.as_secs_f64() / indexed.as_secs_f64()
+        );
+    }
+
+    #[test]
+    #[ignore]
+    fn bench_missing_key_at_full_capacity() {
+        let mut pack = full_pack();
+        let keys: Vec<String> = (0..MAX_ITEM).map(|i| format!("none{}", i)).collect();
+
+        let linear = measure("linear", &keys, |k| linear(&pack, k.to_string()).is_none());
+        let indexed = measure("get", &keys, |k| pack.get(k.to_string()).is_none());
+        println!(
+            "index is {:.1}x faster",
+            linear.as_secs_f64() / indexed.as_secs_f64()
+        );
     }
 }
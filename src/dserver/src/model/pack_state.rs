@@ -4,5 +4,15 @@ use uuid::Uuid;
 #[derive(Debug, PartialEq)]
 pub enum PackState {
     Added(Uuid),
+    Updated(Uuid),
+    KeyExists,
+    KeyMissing,
+    /// The item is added after the item of the key is evicted.
+    Evicted {
+        added: Uuid,
+        key: String,
+    },
     CapacityFull,
+    /// The item of the key has another uuid than the one that was expected.
+    Conflict,
 }
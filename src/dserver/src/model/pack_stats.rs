@@ -0,0 +1,15 @@
+use std::fmt::{Display, Formatter};
+
+/// Summary information about the content of a pack.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct PackStats {
+    pub id: u32,
+    pub items: usize,
+    pub capacity: usize,
+}
+
+impl Display for PackStats {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "#{} {}/{}", self.id, self.items, self.capacity)
+    }
+}
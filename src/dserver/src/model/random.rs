@@ -0,0 +1,50 @@
+/// A small xorshift generator for tests and benchmarks, the same seed always gives the same numbers.
+///
+/// It is not random enough for anything else. Pack eviction takes its randomness from uuids.
+#[derive(Debug, Clone)]
+pub struct Random(u64);
+
+impl Random {
+    pub fn new(seed: u64) -> Self {
+        Random(seed.wrapping_mul(0x9e3779b97f4a7c15) | 1)
+    }
+
+    pub fn number(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    /// Returns a number from zero up to n, n is not included.
+    pub fn below(&mut self, n: u64) -> u64 {
+        self.number() % n
+    }
+
+    /// Returns a text of the alphabet's characters that is shorter than `limit` characters.
+    pub fn text(&mut self, alphabet: &[char], limit: usize) -> String {
+        let length = self.below(limit as u64);
+        (0..length)
+            .map(|_| alphabet[self.below(alphabet.len() as u64) as usize])
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn should_same_seed_repeat_the_numbers() {
+        let (mut first, mut second) = (Random::new(7), Random::new(7));
+        for _ in 0..100 {
+            assert_eq!(first.number(), second.number());
+        }
+        assert_ne!(Random::new(7).number(), Random::new(8).number());
+        assert!((0..1000).all(|_| first.below(10) < 10));
+
+        let text = first.text(&['a', '|'], 16);
+        assert!(text.chars().count() < 16);
+        assert!(text.chars().all(|c| c == 'a' || c == '|'));
+    }
+}
@@ -1,9 +1,14 @@
 use super::Pack;
+use crate::InformativeEvent;
+use crossbeam::channel::Sender;
 use std::sync::{Arc, Mutex};
 
-/// Search data for item's get operation.
+/// Search data for item's get and delete operations.
+///
+/// The result of the search is sent back over the responder channel of the requester.
 #[derive(Debug)]
 pub struct Search {
     pub pack: Arc<Mutex<Pack>>,
     pub key: String,
+    pub responder: Sender<InformativeEvent>,
 }
@@ -0,0 +1,15 @@
+use super::{Operation, Pack};
+use crate::InformativeEvent;
+use crossbeam::channel::Sender;
+use std::sync::{Arc, Mutex};
+
+/// Update data for the collection commands of a key.
+///
+/// The reply of the operation is sent back over the responder channel of the requester.
+#[derive(Debug)]
+pub struct Update {
+    pub pack: Arc<Mutex<Pack>>,
+    pub key: String,
+    pub operation: Operation,
+    pub responder: Sender<InformativeEvent>,
+}
@@ -0,0 +1,9 @@
+/// Access information of an item that the eviction policies rely on.
+///
+/// The values are ticks of the pack's logical clock, not wall clock times.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Usage {
+    pub inserted: u64,
+    pub accessed: u64,
+    pub hits: u64,
+}
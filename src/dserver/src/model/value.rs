@@ -1,13 +1,19 @@
+use crate::model::format::{encode_base64, format_duration, format_timestamp};
 use std::fmt::{Display, Formatter};
+use std::time::{Duration, SystemTime};
+use uuid::Uuid;
 
 /// Indicates the type of value in the Item object.
 ///
 /// It is designed to work with light weight and low cost values.
 /// For example, with ThinNumber, it is specified to keep an 8-bit integer.
 /// Logical kept boolean values.
-/// Text type that can carry large data is subject to length validation in the Item::new function.
+/// Text and Bytes types that can carry large data are subject to length validation in the Item::new function.
+/// Timestamp is a point in UTC time, Duration is a span of time.
+/// List, Set and Hash are small collections of the other values, they can't be nested.
+/// Sets keep their members and hashes keep their fields in the order they are added.
 ///
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 #[allow(dead_code)]
 pub enum Value {
     ThinNumber(i8),
@@ -15,13 +21,181 @@ pub enum Value {
     LargeNumber(i32),
     ThinFloat(f32),
     LargeFloat(f64),
-    Text(&'static str),
+    Text(String),
     Logical(bool),
+    ThinUnsigned(u8),
+    MidUnsigned(u16),
+    LargeUnsigned(u32),
+    HugeUnsigned(u64),
+    HugeNumber(i64),
+    GiantNumber(i128),
+    Bytes(Vec<u8>),
+    Timestamp(SystemTime),
+    Duration(Duration),
+    Uuid(Uuid),
+    List(Vec<Value>),
+    Set(Vec<Value>),
+    Hash(Vec<(String, Value)>),
     Empty,
 }
 
+impl Value {
+    /// Returns the type tag of the value as it is used in the message protocol.
+    pub fn tag(&self) -> &'static str {
+        match self {
+            Self::ThinNumber(_) => "i8",
+            Self::MidNumber(_) => "i16",
+            Self::LargeNumber(_) => "i32",
+            Self::ThinFloat(_) => "f32",
+            Self::LargeFloat(_) => "f64",
+            Self::Text(_) => "s",
+            Self::Logical(_) => "l",
+            Self::ThinUnsigned(_) => "u8",
+            Self::MidUnsigned(_) => "u16",
+            Self::LargeUnsigned(_) => "u32",
+            Self::HugeUnsigned(_) => "u64",
+            Self::HugeNumber(_) => "i64",
+            Self::GiantNumber(_) => "i128",
+            Self::Bytes(_) => "b",
+            Self::Timestamp(_) => "ts",
+            Self::Duration(_) => "d",
+            Self::Uuid(_) => "uuid",
+            Self::List(_) => "list",
+            Self::Set(_) => "set",
+            Self::Hash(_) => "hash",
+            Self::Empty => "e",
+        }
+    }
+
+    /// Returns the raw content of the value without its type name.
+    ///
+    /// Bytes are written in base64, timestamps in RFC 3339 and durations in seconds.
+    /// Collections are written for reading only, like `[1, 2]`, `{a, b}` or `{name: dory}`,
+    /// the message protocol writes their elements one by one.
+    pub fn payload(&self) -> String {
+        match self {
+            Self::ThinNumber(v) => v.to_string(),
+            Self::MidNumber(v) => v.to_string(),
+            Self::LargeNumber(v) => v.to_string(),
+            Self::ThinFloat(v) => v.to_string(),
+            Self::LargeFloat(v) => v.to_string(),
+            Self::Text(v) => v.clone(),
+            Self::Logical(v) => v.to_string(),
+            Self::ThinUnsigned(v) => v.to_string(),
+            Self::MidUnsigned(v) => v.to_string(),
+            Self::LargeUnsigned(v) => v.to_string(),
+            Self::HugeUnsigned(v) => v.to_string(),
+            Self::HugeNumber(v) => v.to_string(),
+            Self::GiantNumber(v) => v.to_string(),
+            Self::Bytes(v) => encode_base64(v),
+            Self::Timestamp(v) => format_timestamp(*v),
+            Self::Duration(v) => format_duration(*v),
+            Self::Uuid(v) => v.to_hyphenated().to_string(),
+            Self::List(v) => format!("[{}]", joined(v.iter().map(|e| e.payload()))),
+            Self::Set(v) => format!("{{{}}}", joined(v.iter().map(|e| e.payload()))),
+            Self::Hash(v) => format!(
+                "{{{}}}",
+                joined(v.iter().map(|(f, e)| format!("{}: {}", f, e.payload())))
+            ),
+            Self::Empty => String::new(),
+        }
+    }
+
+    /// Returns true for the integer and float variants.
+    pub fn is_number(&self) -> bool {
+        matches!(
+            self,
+            Self::ThinNumber(_)
+                | Self::MidNumber(_)
+                | Self::LargeNumber(_)
+                | Self::HugeNumber(_)
+                | Self::GiantNumber(_)
+                | Self::ThinUnsigned(_)
+                | Self::MidUnsigned(_)
+                | Self::LargeUnsigned(_)
+                | Self::HugeUnsigned(_)
+                | Self::ThinFloat(_)
+                | Self::LargeFloat(_)
+        )
+    }
+
+    /// Returns true for lists, sets and hashes that have no elements.
+    pub fn is_empty_collection(&self) -> bool {
+        match self {
+            Self::List(v) | Self::Set(v) => v.is_empty(),
+            Self::Hash(v) => v.is_empty(),
+            _ => false,
+        }
+    }
+
+    /// Returns true for lists, sets and hashes.
+    pub fn is_collection(&self) -> bool {
+        matches!(self, Self::List(_) | Self::Set(_) | Self::Hash(_))
+    }
+}
+
+fn joined<I: Iterator<Item = String>>(parts: I) -> String {
+    parts.collect::<Vec<String>>().join(", ")
+}
+
 impl Display for Value {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         write!(f, "{:?}", self)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn should_values_return_protocol_tags_and_payloads() {
+        assert_eq!(Value::Text("localhost".to_string()).tag(), "s");
+        assert_eq!(Value::Text("localhost".to_string()).payload(), "localhost");
+        assert_eq!(Value::ThinFloat(2.5).tag(), "f32");
+        assert_eq!(Value::ThinFloat(2.5).payload(), "2.5");
+        assert_eq!(Value::Logical(false).payload(), "false");
+        assert_eq!(Value::Empty.payload(), "");
+    }
+
+    #[test]
+    fn should_extended_values_return_protocol_tags_and_payloads() {
+        assert_eq!(Value::HugeUnsigned(u64::MAX).tag(), "u64");
+        assert_eq!(
+            Value::HugeUnsigned(u64::MAX).payload(),
+            "18446744073709551615"
+        );
+        assert_eq!(Value::GiantNumber(i128::MIN).tag(), "i128");
+        assert_eq!(
+            Value::GiantNumber(i128::MIN).payload(),
+            "-170141183460469231731687303715884105728"
+        );
+        assert_eq!(Value::Bytes(vec![0, 255]).tag(), "b");
+        assert_eq!(Value::Bytes(vec![0, 255]).payload(), "AP8=");
+        let time = std::time::UNIX_EPOCH + Duration::from_secs(86_400);
+        assert_eq!(Value::Timestamp(time).tag(), "ts");
+        assert_eq!(Value::Timestamp(time).payload(), "1970-01-02T00:00:00Z");
+        assert_eq!(
+            Value::Duration(Duration::from_millis(250)).payload(),
+            "0.25"
+        );
+        assert_eq!(Value::Uuid(Uuid::nil()).tag(), "uuid");
+        assert_eq!(
+            Value::Uuid(Uuid::nil()).payload(),
+            "00000000-0000-0000-0000-000000000000"
+        );
+    }
+
+    #[test]
+    fn should_collections_return_readable_payloads() {
+        let list = Value::List(vec![Value::ThinNumber(1), Value::Text("a".to_string())]);
+        assert_eq!(list.tag(), "list");
+        assert_eq!(list.payload(), "[1, a]");
+        assert!(list.is_collection());
+        assert_eq!(Value::Set(vec![Value::Logical(true)]).payload(), "{true}");
+        let hash = Value::Hash(vec![("name".to_string(), Value::Text("dory".to_string()))]);
+        assert_eq!(hash.tag(), "hash");
+        assert_eq!(hash.payload(), "{name: dory}");
+        assert!(!Value::Text("a".to_string()).is_collection());
+    }
+}
@@ -0,0 +1,377 @@
+use crate::derror::codec_error::CodecError;
+use crate::{Item, Value};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use uuid::Uuid;
+
+/// Binary tags of the Value variants.
+const TAG_EMPTY: u8 = 0;
+const TAG_THIN_NUMBER: u8 = 1;
+const TAG_MID_NUMBER: u8 = 2;
+const TAG_LARGE_NUMBER: u8 = 3;
+const TAG_THIN_FLOAT: u8 = 4;
+const TAG_LARGE_FLOAT: u8 = 5;
+const TAG_TEXT: u8 = 6;
+const TAG_LOGICAL: u8 = 7;
+const TAG_THIN_UNSIGNED: u8 = 8;
+const TAG_MID_UNSIGNED: u8 = 9;
+const TAG_LARGE_UNSIGNED: u8 = 10;
+const TAG_HUGE_UNSIGNED: u8 = 11;
+const TAG_HUGE_NUMBER: u8 = 12;
+const TAG_GIANT_NUMBER: u8 = 13;
+const TAG_BYTES: u8 = 14;
+const TAG_TIMESTAMP: u8 = 15;
+const TAG_DURATION: u8 = 16;
+const TAG_UUID: u8 = 17;
+const TAG_LIST: u8 = 18;
+const TAG_SET: u8 = 19;
+const TAG_HASH: u8 = 20;
+
+/// Appends the binary form of the value. Numbers are written in little endian.
+///
+/// Bytes start with their length as u16. Timestamps are written as the duration since the unix epoch,
+/// durations as seconds (u64) and nanoseconds (u32).
+/// Collections start with their element count as u16, every hash element is its field text and value.
+pub fn write_value(buffer: &mut Vec<u8>, value: &Value) {
+    match value {
+        Value::Empty => buffer.push(TAG_EMPTY),
+        Value::ThinNumber(v) => {
+            buffer.push(TAG_THIN_NUMBER);
+            buffer.extend_from_slice(&v.to_le_bytes());
+        }
+        Value::MidNumber(v) => {
+            buffer.push(TAG_MID_NUMBER);
+            buffer.extend_from_slice(&v.to_le_bytes());
+        }
+        Value::LargeNumber(v) => {
+            buffer.push(TAG_LARGE_NUMBER);
+            buffer.extend_from_slice(&v.to_le_bytes());
+        }
+        Value::ThinFloat(v) => {
+            buffer.push(TAG_THIN_FLOAT);
+            buffer.extend_from_slice(&v.to_le_bytes());
+        }
+        Value::LargeFloat(v) => {
+            buffer.push(TAG_LARGE_FLOAT);
+            buffer.extend_from_slice(&v.to_le_bytes());
+        }
+        Value::Text(v) => {
+            buffer.push(TAG_TEXT);
+            write_text(buffer, v);
+        }
+        Value::Logical(v) => {
+            buffer.push(TAG_LOGICAL);
+            buffer.push(u8::from(*v));
+        }
+        Value::ThinUnsigned(v) => {
+            buffer.push(TAG_THIN_UNSIGNED);
+            buffer.push(*v);
+        }
+        Value::MidUnsigned(v) => {
+            buffer.push(TAG_MID_UNSIGNED);
+            buffer.extend_from_slice(&v.to_le_bytes());
+        }
+        Value::LargeUnsigned(v) => {
+            buffer.push(TAG_LARGE_UNSIGNED);
+            buffer.extend_from_slice(&v.to_le_bytes());
+        }
+        Value::HugeUnsigned(v) => {
+            buffer.push(TAG_HUGE_UNSIGNED);
+            buffer.extend_from_slice(&v.to_le_bytes());
+        }
+        Value::HugeNumber(v) => {
+            buffer.push(TAG_HUGE_NUMBER);
+            buffer.extend_from_slice(&v.to_le_bytes());
+        }
+        Value::GiantNumber(v) => {
+            buffer.push(TAG_GIANT_NUMBER);
+            buffer.extend_from_slice(&v.to_le_bytes());
+        }
+        Value::Bytes(v) => {
+            buffer.push(TAG_BYTES);
+            write_bytes(buffer, v);
+        }
+        Value::Timestamp(v) => {
+            buffer.push(TAG_TIMESTAMP);
+            write_duration(buffer, v.duration_since(UNIX_EPOCH).unwrap_or_default());
+        }
+        Value::Duration(v) => {
+            buffer.push(TAG_DURATION);
+            write_duration(buffer, *v);
+        }
+        Value::Uuid(v) => {
+            buffer.push(TAG_UUID);
+            buffer.extend_from_slice(v.as_bytes());
+        }
+        Value::List(v) => {
+            buffer.push(TAG_LIST);
+            write_elements(buffer, v);
+        }
+        Value::Set(v) => {
+            buffer.push(TAG_SET);
+            write_elements(buffer, v);
+        }
+        Value::Hash(v) => {
+            buffer.push(TAG_HASH);
+            buffer.extend_from_slice(&(v.len() as u16).to_le_bytes());
+            for (field, element) in v {
+                write_text(buffer, field);
+                write_value(buffer, element);
+            }
+        }
+    }
+}
+
+fn write_elements(buffer: &mut Vec<u8>, elements: &[Value]) {
+    buffer.extend_from_slice(&(elements.len() as u16).to_le_bytes());
+    for element in elements {
+        write_value(buffer, element);
+    }
+}
+
+fn write_duration(buffer: &mut Vec<u8>, duration: Duration) {
+    buffer.extend_from_slice(&duration.as_secs().to_le_bytes());
+    buffer.extend_from_slice(&duration.subsec_nanos().to_le_bytes());
+}
+
+/// Appends the bytes with their length as a u16 prefix.
+pub fn write_bytes(buffer: &mut Vec<u8>, bytes: &[u8]) {
+    buffer.extend_from_slice(&(bytes.len() as u16).to_le_bytes());
+    buffer.extend_from_slice(bytes);
+}
+
+/// Appends the text with its length as a u16 prefix.
+pub fn write_text(buffer: &mut Vec<u8>, text: &str) {
+    write_bytes(buffer, text.as_bytes());
+}
+
+/// Appends the key, uuid, expiry time and value of the item.
+///
+/// The expiry time is kept as milliseconds since the unix epoch, 0 means the item never expires.
+pub fn write_item(buffer: &mut Vec<u8>, item: &Item) {
+    write_text(buffer, &item.key);
+    buffer.extend_from_slice(item.uuid.as_bytes());
+    let expires_at = item
+        .expires_at
+        .and_then(|e| e.duration_since(UNIX_EPOCH).ok())
+        .map(|d| (d.as_millis() as u64).max(1))
+        .unwrap_or(0);
+    buffer.extend_from_slice(&expires_at.to_le_bytes());
+    write_value(buffer, &item.value);
+}
+
+/// Reads the binary data that is written by the write functions of this module.
+pub struct Reader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Reader<'a> {
+    pub fn new(bytes: &'a [u8]) -> Self {
+        Reader { bytes, position: 0 }
+    }
+
+    /// Returns true if all bytes are read.
+    pub fn is_end(&self) -> bool {
+        self.position >= self.bytes.len()
+    }
+
+    pub fn bytes(&mut self, count: usize) -> Result<&'a [u8], CodecError> {
+        let end = self
+            .position
+            .checked_add(count)
+            .filter(|e| *e <= self.bytes.len())
+            .ok_or(CodecError::UnexpectedEnd)?;
+        let part = &self.bytes[self.position..end];
+        self.position = end;
+        Ok(part)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], CodecError> {
+        let mut array = [0_u8; N];
+        array.copy_from_slice(self.bytes(N)?);
+        Ok(array)
+    }
+
+    pub fn u8(&mut self) -> Result<u8, CodecError> {
+        Ok(self.array::<1>()?[0])
+    }
+
+    pub fn u16(&mut self) -> Result<u16, CodecError> {
+        Ok(u16::from_le_bytes(self.array()?))
+    }
+
+    pub fn u32(&mut self) -> Result<u32, CodecError> {
+        Ok(u32::from_le_bytes(self.array()?))
+    }
+
+    pub fn u64(&mut self) -> Result<u64, CodecError> {
+        Ok(u64::from_le_bytes(self.array()?))
+    }
+
+    /// Reads bytes that start with their length as u16.
+    pub fn blob(&mut self) -> Result<Vec<u8>, CodecError> {
+        let len = self.u16()? as usize;
+        Ok(self.bytes(len)?.to_vec())
+    }
+
+    pub fn text(&mut self) -> Result<String, CodecError> {
+        String::from_utf8(self.blob()?).map_err(|_| CodecError::Encoding)
+    }
+
+    fn duration(&mut self) -> Result<Duration, CodecError> {
+        let seconds = self.u64()?;
+        match self.u32()? {
+            nanos if nanos < 1_000_000_000 => Ok(Duration::new(seconds, nanos)),
+            _ => Err(CodecError::Range),
+        }
+    }
+
+    fn timestamp(&mut self) -> Result<SystemTime, CodecError> {
+        UNIX_EPOCH
+            .checked_add(self.duration()?)
+            .ok_or(CodecError::Range)
+    }
+
+    /// Reads a value, the elements of a collection can't be collections again.
+    pub fn value(&mut self) -> Result<Value, CodecError> {
+        let value = match self.u8()? {
+            TAG_LIST => Value::List(self.elements()?),
+            TAG_SET => Value::Set(self.elements()?),
+            TAG_HASH => {
+                let count = self.u16()?;
+                let mut fields = Vec::with_capacity(count as usize);
+                for _ in 0..count {
+                    fields.push((self.text()?, self.element()?));
+                }
+                Value::Hash(fields)
+            }
+            tag => self.scalar(tag)?,
+        };
+        Ok(value)
+    }
+
+    fn elements(&mut self) -> Result<Vec<Value>, CodecError> {
+        let count = self.u16()?;
+        (0..count).map(|_| self.element()).collect()
+    }
+
+    fn element(&mut self) -> Result<Value, CodecError> {
+        let tag = self.u8()?;
+        self.scalar(tag)
+    }
+
+    fn scalar(&mut self, tag: u8) -> Result<Value, CodecError> {
+        let value = match tag {
+            TAG_EMPTY => Value::Empty,
+            TAG_THIN_NUMBER => Value::ThinNumber(i8::from_le_bytes(self.array()?)),
+            TAG_MID_NUMBER => Value::MidNumber(i16::from_le_bytes(self.array()?)),
+            TAG_LARGE_NUMBER => Value::LargeNumber(i32::from_le_bytes(self.array()?)),
+            TAG_THIN_FLOAT => Value::ThinFloat(f32::from_le_bytes(self.array()?)),
+            TAG_LARGE_FLOAT => Value::LargeFloat(f64::from_le_bytes(self.array()?)),
+            TAG_TEXT => Value::Text(self.text()?),
+            TAG_LOGICAL => Value::Logical(self.u8()? != 0),
+            TAG_THIN_UNSIGNED => Value::ThinUnsigned(self.u8()?),
+            TAG_MID_UNSIGNED => Value::MidUnsigned(self.u16()?),
+            TAG_LARGE_UNSIGNED => Value::LargeUnsigned(self.u32()?),
+            TAG_HUGE_UNSIGNED => Value::HugeUnsigned(self.u64()?),
+            TAG_HUGE_NUMBER => Value::HugeNumber(i64::from_le_bytes(self.array()?)),
+            TAG_GIANT_NUMBER => Value::GiantNumber(i128::from_le_bytes(self.array()?)),
+            TAG_BYTES => Value::Bytes(self.blob()?),
+            TAG_TIMESTAMP => Value::Timestamp(self.timestamp()?),
+            TAG_DURATION => Value::Duration(self.duration()?),
+            TAG_UUID => Value::Uuid(Uuid::from_bytes(self.array()?)),
+            tag => return Err(CodecError::UnknownTag(tag)),
+        };
+        Ok(value)
+    }
+
+    pub fn item(&mut self) -> Result<Item, CodecError> {
+        let key = self.text()?;
+        let uuid = Uuid::from_bytes(self.array()?);
+        let expires_at = match self.u64()? {
+            0 => None,
+            millis => Some(UNIX_EPOCH + Duration::from_millis(millis)),
+        };
+        let value = self.value()?;
+        let mut item = Item::new(key, value).map_err(|e| CodecError::Item(e.to_string()))?;
+        item.uuid = uuid;
+        item.expires_at = expires_at;
+        Ok(item)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn should_every_value_survive_round_trip() {
+        let values = vec![
+            Value::Empty,
+            Value::ThinNumber(-8),
+            Value::MidNumber(1024),
+            Value::LargeNumber(-70000),
+            Value::ThinFloat(2.5),
+            Value::LargeFloat(-1.25e100),
+            Value::Text("localhost".to_string()),
+            Value::Logical(true),
+            Value::ThinUnsigned(200),
+            Value::MidUnsigned(60_000),
+            Value::LargeUnsigned(4_000_000_000),
+            Value::HugeUnsigned(u64::MAX),
+            Value::HugeNumber(-(1 << 40)),
+            Value::GiantNumber(i128::MIN),
+            Value::Bytes(vec![0, 1, 255]),
+            Value::Timestamp(UNIX_EPOCH + Duration::new(1_714_566_600, 5)),
+            Value::Duration(Duration::from_millis(1500)),
+            Value::Uuid(Uuid::new_v4()),
+            Value::List(vec![Value::ThinNumber(1), Value::Text("a".to_string())]),
+            Value::Set(vec![]),
+            Value::Hash(vec![("name".to_string(), Value::Logical(false))]),
+        ];
+        let mut buffer = Vec::new();
+        for v in &values {
+            write_value(&mut buffer, v);
+        }
+        let mut reader = Reader::new(&buffer);
+        for v in &values {
+            assert_eq!(&reader.value().unwrap(), v);
+        }
+        assert!(reader.is_end());
+    }
+
+    #[test]
+    fn should_item_survive_round_trip() {
+        let mut item = Item::new("token".to_string(), Value::Text("abc".to_string())).unwrap();
+        item.expire_after(Duration::from_secs(60));
+        let mut buffer = Vec::new();
+        write_item(&mut buffer, &item);
+
+        let restored = Reader::new(&buffer).item().unwrap();
+        assert_eq!(restored.key, item.key);
+        assert_eq!(restored.uuid, item.uuid);
+        assert_eq!(restored.value, item.value);
+        let difference = item
+            .expires_at
+            .unwrap()
+            .duration_since(restored.expires_at.unwrap())
+            .unwrap();
+        assert!(difference < Duration::from_millis(1));
+    }
+
+    #[test]
+    fn should_broken_data_raise_an_error() {
+        let mut buffer = Vec::new();
+        write_value(&mut buffer, &Value::LargeNumber(7));
+        assert_eq!(
+            Reader::new(&buffer[..3]).value(),
+            Err(CodecError::UnexpectedEnd)
+        );
+        assert_eq!(Reader::new(&[99]).value(), Err(CodecError::UnknownTag(99)));
+        let nested = [TAG_LIST, 1, 0, TAG_LIST, 0, 0];
+        assert_eq!(
+            Reader::new(&nested).value(),
+            Err(CodecError::UnknownTag(TAG_LIST))
+        );
+    }
+}
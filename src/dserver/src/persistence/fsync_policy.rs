@@ -0,0 +1,46 @@
+use crate::derror::fsync_policy_error::FsyncPolicyError;
+use std::str::FromStr;
+
+/// Decides when the records of the write log are flushed to the disk.
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub enum FsyncPolicy {
+    /// Every record is flushed before the reply. The safest and the slowest.
+    Always,
+    /// Records are flushed once a second. At most a second of changes can be lost.
+    #[default]
+    EverySecond,
+    /// Flushing is left to the operating system.
+    Never,
+}
+
+impl FromStr for FsyncPolicy {
+    type Err = FsyncPolicyError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "always" => Ok(Self::Always),
+            "everysec" => Ok(Self::EverySecond),
+            "never" => Ok(Self::Never),
+            _ => Err(FsyncPolicyError::Unknown(s.to_string())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn should_fsync_policy_names_could_be_parse() {
+        assert_eq!(
+            FsyncPolicy::from_str("Always").unwrap(),
+            FsyncPolicy::Always
+        );
+        assert_eq!(
+            FsyncPolicy::from_str("everysec").unwrap(),
+            FsyncPolicy::EverySecond
+        );
+        assert_eq!(FsyncPolicy::default(), FsyncPolicy::EverySecond);
+        assert!(FsyncPolicy::from_str("sometimes").is_err());
+    }
+}
@@ -0,0 +1,33 @@
+pub mod codec;
+pub mod fsync_policy;
+pub mod snapshot;
+pub mod write_log;
+
+use std::path::{Path, PathBuf};
+
+/// Returns the path that a new file is written to before it replaces the file of the path.
+///
+/// `.tmp` follows the whole file name, so `dory.wal` and `dory.snapshot` never share a temporary file.
+pub fn temporary_file(path: &Path) -> PathBuf {
+    let mut name = path.as_os_str().to_os_string();
+    name.push(".tmp");
+    PathBuf::from(name)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::constant::constant::{DEFAULT_SNAPSHOT_FILE, DEFAULT_WRITE_LOG_FILE};
+
+    #[test]
+    fn should_snapshot_and_write_log_have_their_own_temporary_files() {
+        assert_eq!(
+            temporary_file(Path::new(DEFAULT_WRITE_LOG_FILE)),
+            PathBuf::from("dory.wal.tmp")
+        );
+        assert_ne!(
+            temporary_file(Path::new(DEFAULT_SNAPSHOT_FILE)),
+            temporary_file(Path::new(DEFAULT_WRITE_LOG_FILE))
+        );
+    }
+}
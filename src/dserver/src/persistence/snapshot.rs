@@ -0,0 +1,277 @@
+use crate::derror::codec_error::CodecError;
+use crate::derror::snapshot_error::SnapshotError;
+use crate::persistence::codec::{write_item, Reader};
+use crate::persistence::temporary_file;
+use crate::server::router::Router;
+use crate::Item;
+use crossbeam::channel::{Receiver, RecvTimeoutError};
+use log::{error, info};
+use std::fs;
+use std::fs::File;
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+/// The first bytes of every snapshot file.
+const MAGIC: &[u8; 4] = b"DORY";
+/// The version of the snapshot format.
+///
+/// Version 2 added the value tags from 11 to 20. Older snapshots are still loaded, newer ones are rejected.
+const VERSION: u8 = 2;
+
+/// Items of a pack at the moment of the snapshot.
+#[derive(Debug)]
+pub struct PackImage {
+    pub id: u32,
+    pub items: Vec<Item>,
+}
+
+/// Saves the content of all packs to a file and restores it.
+///
+/// # Format
+///
+/// `DORY` | version (u8) | pack count (u32) | packs | crc32 (u32)
+///
+/// Every pack is written as id (u32) | item count (u32) | items.
+/// The checksum covers all bytes before it. Numbers are written in little endian.
+///
+/// Clones share the same lock, so the SAV command and the periodic saves never write the file together.
+#[derive(Debug, Clone)]
+pub struct Snapshot {
+    path: PathBuf,
+    saving: Arc<Mutex<()>>,
+}
+
+impl Snapshot {
+    pub fn new<P: Into<PathBuf>>(path: P) -> Self {
+        Snapshot {
+            path: path.into(),
+            saving: Arc::new(Mutex::new(())),
+        }
+    }
+
+    /// Writes the items of all packs to the snapshot file and returns the number of items.
+    ///
+    /// The packs are locked one by one. The file is replaced only after the new content is completely written.
+    /// A save waits until the previous one has replaced the file, so an older image never replaces a newer one.
+    pub fn save(&self, router: &Router) -> Result<usize, SnapshotError> {
+        let _saving = self.saving.lock().unwrap();
+        let now = SystemTime::now();
+        let images: Vec<PackImage> = router
+            .routes()
+            .iter()
+            .map(|r| {
+                let pack = r.pack.lock().unwrap();
+                PackImage {
+                    id: pack.id,
+                    items: pack
+                        .items
+                        .iter()
+                        .filter(|i| !i.is_expired(now))
+                        .cloned()
+                        .collect(),
+                }
+            })
+            .collect();
+        let count = images.iter().map(|i| i.items.len()).sum();
+        let bytes = encode(&images);
+
+        let temporary = temporary_file(&self.path);
+        let mut file = File::create(&temporary)?;
+        file.write_all(&bytes)?;
+        file.sync_all()?;
+        fs::rename(&temporary, &self.path)?;
+        info!("{} items saved to {:?}", count, self.path);
+        Ok(count)
+    }
+
+    /// Loads the snapshot file into the packs of the router and returns the number of items.
+    ///
+    /// Items are placed by their keys, so a snapshot can be loaded with a different number of packs.
+    /// Expired items are skipped. Nothing is loaded if there is no snapshot file.
+    pub fn restore(&self, router: &Router) -> Result<usize, SnapshotError> {
+        if !self.path.exists() {
+            info!("There is no snapshot at {:?}", self.path);
+            return Ok(0);
+        }
+        let images = decode(&fs::read(&self.path)?)?;
+        let now = SystemTime::now();
+        let mut count = 0;
+        for item in images.into_iter().flat_map(|i| i.items) {
+            if item.is_expired(now) {
+                continue;
+            }
+            let route = router.route(&item.key);
+            route.pack.lock().unwrap().add(item);
+            count += 1;
+        }
+        info!("{} items restored from {:?}", count, self.path);
+        Ok(count)
+    }
+}
+
+/// Saves a snapshot periodically until the shutdown channel is closed.
+pub fn snapshotter(snapshot: Snapshot, router: Router, interval: Duration, shutdown: Receiver<()>) {
+    info!("Snapshots are saved every {:?}", interval);
+    while let Err(RecvTimeoutError::Timeout) = shutdown.recv_timeout(interval) {
+        if let Err(e) = snapshot.save(&router) {
+            error!("{}", e);
+        }
+    }
+}
+
+/// Returns the binary form of the packs.
+pub fn encode(images: &[PackImage]) -> Vec<u8> {
+    let mut buffer = Vec::new();
+    buffer.extend_from_slice(MAGIC);
+    buffer.push(VERSION);
+    buffer.extend_from_slice(&(images.len() as u32).to_le_bytes());
+    for image in images {
+        buffer.extend_from_slice(&image.id.to_le_bytes());
+        buffer.extend_from_slice(&(image.items.len() as u32).to_le_bytes());
+        for item in &image.items {
+            write_item(&mut buffer, item);
+        }
+    }
+    let checksum = crc32fast::hash(&buffer);
+    buffer.extend_from_slice(&checksum.to_le_bytes());
+    buffer
+}
+
+/// Checks and reads the binary form of the packs.
+pub fn decode(bytes: &[u8]) -> Result<Vec<PackImage>, SnapshotError> {
+    if bytes.len() < MAGIC.len() + 1 + 4 || &bytes[..MAGIC.len()] != MAGIC {
+        return Err(SnapshotError::Magic);
+    }
+    let (content, checksum) = bytes.split_at(bytes.len() - 4);
+    if crc32fast::hash(content).to_le_bytes() != checksum {
+        return Err(SnapshotError::Checksum);
+    }
+    let mut reader = Reader::new(&content[MAGIC.len()..]);
+    let version = reader.u8()?;
+    if version == 0 || version > VERSION {
+        return Err(SnapshotError::Version(version));
+    }
+    let pack_count = reader.u32()?;
+    let mut images = Vec::new();
+    for _ in 0..pack_count {
+        let id = reader.u32()?;
+        let item_count = reader.u32()?;
+        let mut items = Vec::new();
+        for _ in 0..item_count {
+            items.push(reader.item()?);
+        }
+        images.push(PackImage { id, items });
+    }
+    if !reader.is_end() {
+        return Err(CodecError::TrailingBytes.into());
+    }
+    Ok(images)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::model::EvictionPolicy;
+    use crate::Value;
+    use crossbeam::channel;
+    use std::env;
+
+    fn temporary_path(name: &str) -> PathBuf {
+        env::temp_dir().join(format!("dory-{}-{}.snapshot", name, std::process::id()))
+    }
+
+    #[test]
+    fn should_packs_restored_from_snapshot() {
+        let (informative, _receiver) = channel::unbounded();
+        let router = Router::new(4, EvictionPolicy::Reject, &informative);
+        let mut uuids = Vec::new();
+        for i in 0..50 {
+            let key = format!("key{}", i);
+            let mut item = Item::new(key.clone(), Value::LargeNumber(i)).unwrap();
+            if i == 0 {
+                item.expire_after(Duration::from_secs(600));
+            }
+            uuids.push(item.uuid);
+            router.route(&key).pack.lock().unwrap().add(item);
+        }
+        let path = temporary_path("restore");
+        let snapshot = Snapshot::new(&path);
+        assert_eq!(snapshot.save(&router).unwrap(), 50);
+
+        let other = Router::new(16, EvictionPolicy::Reject, &informative);
+        assert_eq!(snapshot.restore(&other).unwrap(), 50);
+        for i in 0..50 {
+            let key = format!("key{}", i);
+            let mut pack = other.route(&key).pack.lock().unwrap();
+            let item = pack.get(key).unwrap();
+            assert_eq!(item.value, Value::LargeNumber(i));
+            assert_eq!(item.uuid, uuids[i as usize]);
+            assert_eq!(item.expires_at.is_some(), i == 0);
+        }
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn should_corrupted_snapshot_rejected() {
+        let images = vec![PackImage {
+            id: 1,
+            items: vec![Item::new("debug".to_string(), Value::Logical(true)).unwrap()],
+        }];
+        let mut bytes = encode(&images);
+        assert_eq!(decode(&bytes).unwrap()[0].items.len(), 1);
+
+        let middle = bytes.len() / 2;
+        bytes[middle] ^= 0xff;
+        assert!(matches!(decode(&bytes), Err(SnapshotError::Checksum)));
+        assert!(matches!(decode(b"REDIS0009"), Err(SnapshotError::Magic)));
+
+        let mut bytes = encode(&images);
+        let mut with_version = |version: u8| {
+            bytes[4] = version;
+            let length = bytes.len() - 4;
+            let checksum = crc32fast::hash(&bytes[..length]);
+            bytes[length..].copy_from_slice(&checksum.to_le_bytes());
+            decode(&bytes)
+        };
+        assert!(matches!(with_version(1), Ok(i) if i[0].items.len() == 1));
+        assert!(matches!(
+            with_version(VERSION + 1),
+            Err(SnapshotError::Version(v)) if v == VERSION + 1
+        ));
+    }
+
+    #[test]
+    fn should_concurrent_saves_leave_a_whole_snapshot() {
+        let (informative, _receiver) = channel::unbounded();
+        let router = Router::new(2, EvictionPolicy::Reject, &informative);
+        for i in 0..200 {
+            let key = format!("key{}", i);
+            let item = Item::new(key.clone(), Value::Text("x".repeat(64))).unwrap();
+            router.route(&key).pack.lock().unwrap().add(item);
+        }
+        let path = temporary_path("concurrent");
+        let snapshot = Snapshot::new(&path);
+        let savers: Vec<_> = (0..8)
+            .map(|_| {
+                let (snapshot, router) = (snapshot.clone(), router.clone());
+                std::thread::spawn(move || snapshot.save(&router).unwrap())
+            })
+            .collect();
+        for saver in savers {
+            assert_eq!(saver.join().unwrap(), 200);
+        }
+        let other = Router::new(1, EvictionPolicy::Reject, &informative);
+        assert_eq!(snapshot.restore(&other).unwrap(), 200);
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn should_missing_snapshot_load_nothing() {
+        let (informative, _receiver) = channel::unbounded();
+        let router = Router::new(1, EvictionPolicy::Reject, &informative);
+        let snapshot = Snapshot::new(temporary_path("missing"));
+        assert_eq!(snapshot.restore(&router).unwrap(), 0);
+    }
+}
@@ -0,0 +1,410 @@
+use crate::derror::write_log_error::WriteLogError;
+use crate::persistence::codec::{write_item, write_text, Reader};
+use crate::persistence::fsync_policy::FsyncPolicy;
+use crate::persistence::temporary_file;
+use crate::server::router::Router;
+use crate::Item;
+use crossbeam::channel::{Receiver, RecvTimeoutError};
+use log::{error, info, warn};
+use std::fs;
+use std::fs::{File, OpenOptions};
+use std::io::{Seek, SeekFrom, Write};
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime};
+
+/// The first bytes of every write log file.
+const MAGIC: &[u8; 4] = b"DLOG";
+/// The version of the write log format.
+///
+/// Version 2 added the value tags from 11 to 20 and the batch record.
+/// Older logs are still replayed and take the new version before anything is appended, newer ones are rejected.
+const VERSION: u8 = 2;
+/// The length of the header of every record, length (u32) and crc32 (u32).
+const RECORD_HEADER_LEN: usize = 8;
+
+const RECORD_PUT: u8 = 1;
+const RECORD_REMOVE: u8 = 2;
+const RECORD_BATCH: u8 = 3;
+
+/// A change of a pack that is kept in the write log.
+#[derive(Debug)]
+pub enum Record {
+    /// The item is added, updated or its expiry is changed.
+    Put(Item),
+    /// The key is deleted or evicted.
+    Remove(String),
+    /// The items of an all-or-nothing MSET, they are replayed together or not at all.
+    Batch(Vec<Item>),
+}
+
+impl Record {
+    fn encode(&self) -> Vec<u8> {
+        let mut body = Vec::new();
+        match self {
+            Record::Put(item) => {
+                body.push(RECORD_PUT);
+                write_item(&mut body, item);
+            }
+            Record::Remove(key) => {
+                body.push(RECORD_REMOVE);
+                write_text(&mut body, key);
+            }
+            Record::Batch(items) => {
+                body.push(RECORD_BATCH);
+                body.extend_from_slice(&(items.len() as u32).to_le_bytes());
+                for item in items {
+                    write_item(&mut body, item);
+                }
+            }
+        }
+        let mut buffer = Vec::with_capacity(RECORD_HEADER_LEN + body.len());
+        buffer.extend_from_slice(&(body.len() as u32).to_le_bytes());
+        buffer.extend_from_slice(&crc32fast::hash(&body).to_le_bytes());
+        buffer.extend_from_slice(&body);
+        buffer
+    }
+
+    fn decode(body: &[u8]) -> Result<Self, WriteLogError> {
+        let mut reader = Reader::new(body);
+        match reader.u8()? {
+            RECORD_PUT => Ok(Record::Put(reader.item()?)),
+            RECORD_REMOVE => Ok(Record::Remove(reader.text()?)),
+            RECORD_BATCH => {
+                let count = reader.u32()?;
+                let mut items = Vec::new();
+                for _ in 0..count {
+                    items.push(reader.item()?);
+                }
+                Ok(Record::Batch(items))
+            }
+            kind => Err(WriteLogError::UnknownRecord(kind)),
+        }
+    }
+}
+
+/// Append-only log of the changes that the pack workers accept.
+///
+/// # Format
+///
+/// `DLOG` | version (u8) | records
+///
+/// Every record is written as length (u32) | crc32 (u32) | body.
+/// A record that is cut off or doesn't match its checksum is taken as the torn tail of a crash,
+/// it is removed from the file with everything after it.
+pub struct WriteLog {
+    path: PathBuf,
+    policy: FsyncPolicy,
+    file: Mutex<File>,
+    created: bool,
+}
+
+impl WriteLog {
+    /// Opens the write log for appending. The file is created if it doesn't exist.
+    pub fn open<P: Into<PathBuf>>(path: P, policy: FsyncPolicy) -> Result<Self, WriteLogError> {
+        let path = path.into();
+        let bytes = match fs::read(&path) {
+            Ok(b) => b,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => return Err(e.into()),
+        };
+        let mut file = OpenOptions::new().create(true).append(true).open(&path)?;
+        let created = bytes.is_empty();
+        if created {
+            file.write_all(&header())?;
+            file.sync_all()?;
+        } else {
+            let (_, end) = scan(&bytes)?;
+            if bytes[MAGIC.len()] < VERSION {
+                let mut header = OpenOptions::new().write(true).open(&path)?;
+                header.seek(SeekFrom::Start(MAGIC.len() as u64))?;
+                header.write_all(&[VERSION])?;
+                header.sync_all()?;
+                info!("{:?} is upgraded to write log version {}", path, VERSION);
+            }
+            if end < bytes.len() {
+                warn!(
+                    "Torn record at the end of {:?}, {} bytes are dropped",
+                    path,
+                    bytes.len() - end
+                );
+                file.set_len(end as u64)?;
+                file.sync_all()?;
+            }
+        }
+        Ok(WriteLog {
+            path,
+            policy,
+            file: Mutex::new(file),
+            created,
+        })
+    }
+
+    /// Returns true if the log had no content when it was opened.
+    ///
+    /// Such a log holds none of the earlier changes, every other log holds all of them since its creation.
+    pub fn is_new(&self) -> bool {
+        self.created
+    }
+
+    /// Returns the fsync policy of the log.
+    pub fn policy(&self) -> FsyncPolicy {
+        self.policy
+    }
+
+    /// Appends the record to the end of the log and flushes it if the policy is `Always`.
+    pub fn append(&self, record: &Record) -> Result<(), WriteLogError> {
+        let mut file = self.file.lock().unwrap();
+        file.write_all(&record.encode())?;
+        if self.policy == FsyncPolicy::Always {
+            file.sync_data()?;
+        }
+        Ok(())
+    }
+
+    /// Flushes the appended records to the disk.
+    pub fn sync(&self) -> Result<(), WriteLogError> {
+        self.file.lock().unwrap().sync_data()?;
+        Ok(())
+    }
+
+    /// Applies the records of the log to the packs of the router and returns the number of records.
+    ///
+    /// Items are placed by their keys, so the log can be replayed with a different number of packs.
+    pub fn replay(&self, router: &Router) -> Result<usize, WriteLogError> {
+        let _file = self.file.lock().unwrap();
+        let (records, _) = scan(&fs::read(&self.path)?)?;
+        let now = SystemTime::now();
+        let count = records.len();
+        let put = |item: Item| {
+            let key = item.key.clone();
+            let mut pack = router.route(&key).pack.lock().unwrap();
+            if item.is_expired(now) {
+                pack.remove(key);
+            } else {
+                pack.add(item);
+            }
+        };
+        for record in records {
+            match record {
+                Record::Put(item) => put(item),
+                Record::Batch(items) => items.into_iter().for_each(put),
+                Record::Remove(key) => {
+                    router.route(&key).pack.lock().unwrap().remove(key);
+                }
+            }
+        }
+        info!("{} records replayed from {:?}", count, self.path);
+        Ok(count)
+    }
+
+    /// Replaces the log with the current items of the packs and returns the number of items.
+    ///
+    /// Appends wait until the new log takes the place of the old one.
+    pub fn rewrite(&self, router: &Router) -> Result<usize, WriteLogError> {
+        let mut file = self.file.lock().unwrap();
+        let now = SystemTime::now();
+        let mut buffer = header();
+        let mut count = 0;
+        for route in router.routes() {
+            let pack = route.pack.lock().unwrap();
+            for item in pack.items.iter().filter(|i| !i.is_expired(now)) {
+                buffer.extend_from_slice(&Record::Put(item.clone()).encode());
+                count += 1;
+            }
+        }
+
+        let temporary = temporary_file(&self.path);
+        let mut compacted = File::create(&temporary)?;
+        compacted.write_all(&buffer)?;
+        compacted.sync_all()?;
+        fs::rename(&temporary, &self.path)?;
+        *file = OpenOptions::new().append(true).open(&self.path)?;
+        info!("{:?} is rewritten with {} items", self.path, count);
+        Ok(count)
+    }
+}
+
+/// Flushes the write log once a second until the shutdown channel is closed.
+pub fn syncer(log: &WriteLog, shutdown: Receiver<()>) {
+    info!("Write log is flushed every second");
+    while let Err(RecvTimeoutError::Timeout) = shutdown.recv_timeout(Duration::from_secs(1)) {
+        if let Err(e) = log.sync() {
+            error!("{}", e);
+        }
+    }
+}
+
+fn header() -> Vec<u8> {
+    let mut buffer = MAGIC.to_vec();
+    buffer.push(VERSION);
+    buffer
+}
+
+/// Reads the records of the log and returns them with the length of the valid part of the file.
+fn scan(bytes: &[u8]) -> Result<(Vec<Record>, usize), WriteLogError> {
+    let start = MAGIC.len() + 1;
+    if bytes.len() < start || &bytes[..MAGIC.len()] != MAGIC {
+        return Err(WriteLogError::Magic);
+    }
+    if bytes[MAGIC.len()] == 0 || bytes[MAGIC.len()] > VERSION {
+        return Err(WriteLogError::Version(bytes[MAGIC.len()]));
+    }
+    let mut records = Vec::new();
+    let mut position = start;
+    while bytes.len() - position >= RECORD_HEADER_LEN {
+        let mut reader = Reader::new(&bytes[position..]);
+        let len = reader.u32()? as usize;
+        let checksum = reader.u32()?;
+        let body = match reader.bytes(len) {
+            Ok(b) if crc32fast::hash(b) == checksum => b,
+            _ => break,
+        };
+        records.push(Record::decode(body)?);
+        position += RECORD_HEADER_LEN + len;
+    }
+    Ok((records, position))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::model::EvictionPolicy;
+    use crate::Value;
+    use crossbeam::channel;
+    use std::env;
+
+    fn temporary_path(name: &str) -> PathBuf {
+        env::temp_dir().join(format!("dory-{}-{}.wal", name, std::process::id()))
+    }
+
+    fn item(key: &str, value: i32) -> Item {
+        Item::new(key.to_string(), Value::LargeNumber(value)).unwrap()
+    }
+
+    #[test]
+    fn should_packs_rebuilt_from_replayed_log() {
+        let path = temporary_path("replay");
+        let log = WriteLog::open(&path, FsyncPolicy::Always).unwrap();
+        log.append(&Record::Put(item("alpha", 1))).unwrap();
+        log.append(&Record::Put(item("beta", 2))).unwrap();
+        log.append(&Record::Put(item("alpha", 3))).unwrap();
+        log.append(&Record::Remove("beta".to_string())).unwrap();
+        let mut expired = item("gamma", 4);
+        expired.expires_at = Some(SystemTime::now() - Duration::from_secs(1));
+        log.append(&Record::Put(expired)).unwrap();
+        drop(log);
+
+        let (informative, _receiver) = channel::unbounded();
+        let router = Router::new(4, EvictionPolicy::Reject, &informative);
+        let log = WriteLog::open(&path, FsyncPolicy::Never).unwrap();
+        assert_eq!(log.replay(&router).unwrap(), 5);
+        let mut pack = router.route("alpha").pack.lock().unwrap();
+        assert_eq!(
+            pack.get("alpha".to_string()).unwrap().value,
+            Value::LargeNumber(3)
+        );
+        drop(pack);
+        assert!(router
+            .route("beta")
+            .pack
+            .lock()
+            .unwrap()
+            .get("beta".to_string())
+            .is_none());
+        assert_eq!(router.stats().iter().map(|s| s.items).sum::<usize>(), 1);
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn should_torn_batch_leave_none_of_its_items() {
+        let path = temporary_path("batch");
+        let log = WriteLog::open(&path, FsyncPolicy::Always).unwrap();
+        log.append(&Record::Batch(vec![item("alpha", 1), item("beta", 2)]))
+            .unwrap();
+        drop(log);
+        let torn = Record::Batch(vec![item("alpha", 3), item("gamma", 4)]).encode();
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(&torn[..torn.len() - 3]).unwrap();
+        drop(file);
+
+        let (informative, _receiver) = channel::unbounded();
+        let router = Router::new(2, EvictionPolicy::Reject, &informative);
+        let log = WriteLog::open(&path, FsyncPolicy::Never).unwrap();
+        assert_eq!(log.replay(&router).unwrap(), 1);
+        let mut pack = router.route("alpha").pack.lock().unwrap();
+        assert_eq!(
+            pack.get("alpha".to_string()).unwrap().value,
+            Value::LargeNumber(1)
+        );
+        drop(pack);
+        assert_eq!(router.stats().iter().map(|s| s.items).sum::<usize>(), 2);
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn should_torn_record_truncated() {
+        let path = temporary_path("torn");
+        let log = WriteLog::open(&path, FsyncPolicy::Always).unwrap();
+        log.append(&Record::Put(item("alpha", 1))).unwrap();
+        drop(log);
+        let valid = fs::metadata(&path).unwrap().len();
+        let torn = Record::Put(item("beta", 2)).encode();
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(&torn[..torn.len() - 3]).unwrap();
+        drop(file);
+
+        let log = WriteLog::open(&path, FsyncPolicy::Always).unwrap();
+        assert_eq!(fs::metadata(&path).unwrap().len(), valid);
+        log.append(&Record::Remove("alpha".to_string())).unwrap();
+        let (records, _) = scan(&fs::read(&path).unwrap()).unwrap();
+        assert_eq!(records.len(), 2);
+        assert!(matches!(&records[1], Record::Remove(k) if k == "alpha"));
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn should_rewrite_collapse_log_into_current_items() {
+        let path = temporary_path("rewrite");
+        let log = WriteLog::open(&path, FsyncPolicy::Never).unwrap();
+        let (informative, _receiver) = channel::unbounded();
+        let router = Router::new(2, EvictionPolicy::Reject, &informative);
+        for i in 0..20 {
+            let key = format!("key{}", i % 5);
+            log.append(&Record::Put(item(&key, i))).unwrap();
+            router.route(&key).pack.lock().unwrap().add(item(&key, i));
+        }
+        assert_eq!(log.rewrite(&router).unwrap(), 5);
+        log.append(&Record::Remove("key0".to_string())).unwrap();
+
+        let (records, _) = scan(&fs::read(&path).unwrap()).unwrap();
+        assert_eq!(records.len(), 6);
+        assert!(matches!(fs::read(&path), Ok(b) if b.starts_with(MAGIC)));
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn should_foreign_file_rejected() {
+        assert!(matches!(scan(b"DORY\x01"), Err(WriteLogError::Magic)));
+        assert!(matches!(scan(b"DLOG\x07"), Err(WriteLogError::Version(7))));
+        assert!(matches!(scan(b"DLOG\x03"), Err(WriteLogError::Version(3))));
+    }
+
+    #[test]
+    fn should_older_log_be_replayed_and_upgraded() {
+        let path = temporary_path("older");
+        let mut bytes = b"DLOG\x01".to_vec();
+        bytes.extend_from_slice(&Record::Put(item("alpha", 1)).encode());
+        fs::write(&path, &bytes).unwrap();
+
+        let log = WriteLog::open(&path, FsyncPolicy::Always).unwrap();
+        log.append(&Record::Batch(vec![item("beta", 2)])).unwrap();
+        let bytes = fs::read(&path).unwrap();
+        assert_eq!(bytes[MAGIC.len()], VERSION);
+        let (informative, _receiver) = channel::unbounded();
+        let router = Router::new(1, EvictionPolicy::Reject, &informative);
+        assert_eq!(log.replay(&router).unwrap(), 2);
+        assert_eq!(router.stats()[0].items, 2);
+        fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,663 @@
+use crate::constant::constant::{
+    MAX_BYTES_LEN, MAX_KEY_LEN, MAX_MESSAGE_LEN, MAX_TTL_SECS, MAX_VALUE_LEN,
+};
+use crate::derror::codec_error::CodecError;
+use crate::derror::message_parse_error::MessageParseError;
+use crate::model::format::MAX_TIMESTAMP_SECS;
+use crate::model::{Command, Message};
+use crate::persistence::codec::{write_text, write_value, Reader};
+use crate::server::connection::Frame;
+use crate::server::response::{Code, Response};
+use crate::server::router::Router;
+use crate::server::server::execute;
+use crate::Value;
+use log::{error, info, warn};
+use std::io::{BufReader, ErrorKind, Read, Write};
+use std::net::TcpStream;
+use std::time::{Duration, UNIX_EPOCH};
+use uuid::Uuid;
+
+/// The first byte of a binary connection. It can't be the start of a text message.
+pub const MAGIC: u8 = 0xD0;
+/// The version of the binary protocol.
+///
+/// Version 2 added the results of multi-key commands, value flag 2 of the response.
+pub const VERSION: u8 = 2;
+
+/// Command code that closes the connection.
+pub const QUIT: u8 = 0;
+
+/// Returns the code of the command in the binary protocol.
+pub fn command_code(command: &Command) -> u8 {
+    match command {
+        Command::Add => 1,
+        Command::Anx => 2,
+        Command::Axx => 3,
+        Command::Get => 4,
+        Command::Del => 5,
+        Command::Inf => 6,
+        Command::Exp => 7,
+        Command::Ttl => 8,
+        Command::Prs => 9,
+        Command::Sav => 10,
+        Command::Rwl => 11,
+        Command::Lpush => 12,
+        Command::Lpop => 13,
+        Command::Lrange => 14,
+        Command::Sadd => 15,
+        Command::Srem => 16,
+        Command::Smembers => 17,
+        Command::Hset => 18,
+        Command::Hget => 19,
+        Command::Hdel => 20,
+        Command::Hgetall => 21,
+        Command::Inc => 22,
+        Command::Dec => 23,
+        Command::Iby => 24,
+        Command::Cas => 25,
+        Command::Mget => 26,
+        Command::Mset => 27,
+        Command::Mdel => 28,
+        Command::Exs => 29,
+    }
+}
+
+fn command_from(code: u8) -> Result<Command, MessageParseError> {
+    let command = match code {
+        1 => Command::Add,
+        2 => Command::Anx,
+        3 => Command::Axx,
+        4 => Command::Get,
+        5 => Command::Del,
+        6 => Command::Inf,
+        7 => Command::Exp,
+        8 => Command::Ttl,
+        9 => Command::Prs,
+        10 => Command::Sav,
+        11 => Command::Rwl,
+        12 => Command::Lpush,
+        13 => Command::Lpop,
+        14 => Command::Lrange,
+        15 => Command::Sadd,
+        16 => Command::Srem,
+        17 => Command::Smembers,
+        18 => Command::Hset,
+        19 => Command::Hget,
+        20 => Command::Hdel,
+        21 => Command::Hgetall,
+        22 => Command::Inc,
+        23 => Command::Dec,
+        24 => Command::Iby,
+        25 => Command::Cas,
+        26 => Command::Mget,
+        27 => Command::Mset,
+        28 => Command::Mdel,
+        29 => Command::Exs,
+        _ => return Err(MessageParseError::Command),
+    };
+    Ok(command)
+}
+
+/// Writes the frame as its length (u32) and body with a single write call.
+pub fn write_frame<W: Write>(stream: &mut W, body: &[u8]) -> std::io::Result<()> {
+    let mut frame = Vec::with_capacity(4 + body.len());
+    frame.extend_from_slice(&(body.len() as u32).to_le_bytes());
+    frame.extend_from_slice(body);
+    stream.write_all(&frame)
+}
+
+/// Reads the next frame of the stream. None is returned when the stream is closed between frames.
+///
+/// The body of a frame longer than the limit is not read.
+pub fn read_frame<R: Read>(stream: &mut R) -> std::io::Result<Option<Frame>> {
+    let mut length = [0_u8; 4];
+    match stream.read_exact(&mut length) {
+        Ok(_) => {}
+        Err(e) if e.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e),
+    }
+    let length = u32::from_le_bytes(length) as usize;
+    if length > MAX_MESSAGE_LEN {
+        return Ok(Some(Frame::TooLong));
+    }
+    let mut body = vec![0_u8; length];
+    stream.read_exact(&mut body)?;
+    Ok(Some(Frame::Message(body)))
+}
+
+/// Returns the body of a request frame.
+///
+/// `id (u32) | command (u8) | key | value flag (u8) [| value] | ttl in seconds (u64), 0 is none`
+///
+/// Keys and text values are written with their length as u16, values start with their type tag.
+/// The hash field, the list positions, the flags and the uuid that CAS expects follow the ttl,
+/// `field flag (u8) [| field] | range flag (u8) [| start (i64) | stop (i64)] | flags (u8) | uuid flag (u8) [| uuid]`.
+/// The flags are 1 for counters that create a missing key, 2 for GET that replies the uuid,
+/// 4 for an all-or-nothing MSET and 8 for counters that count integer text. The keys of multi-key commands come last,
+/// `count (u16) [| key | value flag (u8) [| value]]*`, with the value of every key for MSET.
+/// They are left out when the message has none of them.
+pub fn encode_request(id: u32, message: &Message) -> Vec<u8> {
+    let mut body = Vec::new();
+    body.extend_from_slice(&id.to_le_bytes());
+    body.push(command_code(&message.command));
+    write_text(&mut body, &message.key);
+    write_optional_value(&mut body, message.value.as_ref());
+    let ttl = message.ttl.map(|t| t.as_secs()).unwrap_or(0);
+    body.extend_from_slice(&ttl.to_le_bytes());
+    if message.field.is_none()
+        && message.range.is_none()
+        && !message.create
+        && !message.text
+        && !message.versioned
+        && message.version.is_none()
+        && message.batch.is_empty()
+        && !message.atomic
+    {
+        return body;
+    }
+    match &message.field {
+        Some(f) => {
+            body.push(1);
+            write_text(&mut body, f);
+        }
+        None => body.push(0),
+    }
+    match message.range {
+        Some((start, stop)) => {
+            body.push(1);
+            body.extend_from_slice(&start.to_le_bytes());
+            body.extend_from_slice(&stop.to_le_bytes());
+        }
+        None => body.push(0),
+    }
+    body.push(
+        u8::from(message.create)
+            | u8::from(message.versioned) << 1
+            | u8::from(message.atomic) << 2
+            | u8::from(message.text) << 3,
+    );
+    match message.version {
+        Some(u) => {
+            body.push(1);
+            body.extend_from_slice(u.as_bytes());
+        }
+        None => body.push(0),
+    }
+    if !message.batch.is_empty() {
+        body.extend_from_slice(&(message.batch.len() as u16).to_le_bytes());
+        for m in &message.batch {
+            write_text(&mut body, &m.key);
+            write_optional_value(&mut body, m.value.as_ref());
+        }
+    }
+    body
+}
+
+/// Returns the body of a quit request.
+pub fn encode_quit(id: u32) -> Vec<u8> {
+    let mut body = id.to_le_bytes().to_vec();
+    body.push(QUIT);
+    body
+}
+
+/// Reads the id and the message of a request body and checks the message like the text parser does.
+///
+/// The id is 0 if the body is too short to carry one.
+pub fn decode_request(body: &[u8]) -> (u32, Result<Message, MessageParseError>) {
+    let mut reader = Reader::new(body);
+    let id = match reader.u32() {
+        Ok(id) => id,
+        Err(_) => return (0, Err(MessageParseError::Pattern)),
+    };
+    (id, read_message(&mut reader))
+}
+
+fn read_message(reader: &mut Reader) -> Result<Message, MessageParseError> {
+    let command = command_from(reader.u8()?)?;
+    let key = reader.text()?;
+    if key.chars().count() > MAX_KEY_LEN {
+        return Err(MessageParseError::KeyNameTooLong);
+    }
+    let value = match reader.u8()? {
+        0 => None,
+        _ => Some(checked(reader.value()?)?),
+    };
+    let ttl = match reader.u64()? {
+        0 => None,
+        seconds if seconds > MAX_TTL_SECS => {
+            return Err(MessageParseError::out_of_range("ttl", &seconds.to_string()))
+        }
+        seconds => Some(Duration::from_secs(seconds)),
+    };
+    let (mut field, mut range, mut flags, mut version) = (None, None, 0, None);
+    let mut batch = Vec::new();
+    if !reader.is_end() {
+        if reader.u8()? != 0 {
+            let f = reader.text()?;
+            if f.chars().count() > MAX_KEY_LEN {
+                return Err(MessageParseError::KeyNameTooLong);
+            }
+            field = Some(f);
+        }
+        if reader.u8()? != 0 {
+            range = Some((reader.u64()? as i64, reader.u64()? as i64));
+        }
+        // The flags and the uuid came after the first collection commands, so they may be missing.
+        if !reader.is_end() {
+            flags = reader.u8()?;
+        }
+        if !reader.is_end() && reader.u8()? != 0 {
+            version =
+                Some(Uuid::from_slice(reader.bytes(16)?).map_err(|_| MessageParseError::Pattern)?);
+        }
+        if !reader.is_end() {
+            let per_key = command.per_key().ok_or(MessageParseError::Pattern)?;
+            for _ in 0..reader.u16()? {
+                let k = reader.text()?;
+                if k.chars().count() > MAX_KEY_LEN {
+                    return Err(MessageParseError::KeyNameTooLong);
+                }
+                let v = match reader.u8()? {
+                    0 => None,
+                    _ => Some(checked(reader.value()?)?),
+                };
+                batch.push(Message::new(per_key.clone(), k, v));
+            }
+        }
+    }
+    if !reader.is_end() {
+        return Err(MessageParseError::Pattern);
+    }
+    let message = Message::new(command, key, value)
+        .with_ttl(ttl)
+        .with_field(field)
+        .with_range(range)
+        .with_create(flags & 1 != 0)
+        .with_versioned(flags & 2 != 0)
+        .with_version(version)
+        .with_atomic(flags & 4 != 0)
+        .with_text(flags & 8 != 0)
+        .with_batch(batch);
+    let complete = match message.command {
+        Command::Mget | Command::Mdel => {
+            !message.batch.is_empty() && message.batch.iter().all(|m| m.value.is_none())
+        }
+        Command::Mset => {
+            !message.batch.is_empty() && message.batch.iter().all(|m| m.value.is_some())
+        }
+        Command::Add | Command::Anx | Command::Axx => message.value.is_some(),
+        Command::Cas => message.value.is_some() && message.version.is_some(),
+        Command::Exp => message.ttl.is_some(),
+        Command::Get
+        | Command::Del
+        | Command::Inf
+        | Command::Ttl
+        | Command::Exs
+        | Command::Prs
+        | Command::Sav
+        | Command::Rwl => true,
+        _ => message.operation().is_some(),
+    };
+    if !complete {
+        return Err(MessageParseError::Pattern);
+    }
+    Ok(message)
+}
+
+/// Applies the limits of the text protocol to a decoded value.
+fn checked(value: Value) -> Result<Value, MessageParseError> {
+    check(&value)?;
+    Ok(value)
+}
+
+fn check(value: &Value) -> Result<(), MessageParseError> {
+    match value {
+        Value::List(v) | Value::Set(v) => v.iter().try_for_each(check),
+        Value::Hash(v) => v.iter().try_for_each(|(_, e)| check(e)),
+        Value::Text(t) if t.len() > MAX_VALUE_LEN => Err(MessageParseError::ValueTooLong),
+        Value::Bytes(b) if b.len() > MAX_BYTES_LEN => Err(MessageParseError::ValueTooLong),
+        Value::Timestamp(t)
+            if t.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs() > MAX_TIMESTAMP_SECS =>
+        {
+            Err(MessageParseError::out_of_range("ts", &value.payload()))
+        }
+        Value::ThinFloat(f) if !f.is_finite() => {
+            Err(MessageParseError::out_of_range("f32", &f.to_string()))
+        }
+        Value::LargeFloat(f) if !f.is_finite() => {
+            Err(MessageParseError::out_of_range("f64", &f.to_string()))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Returns the body of a response frame.
+///
+/// `id (u32) | code (u16) | value flag (u8) [| value] [| uuid]`
+///
+/// The uuid of the item is only there if the request asked for it.
+/// Multi-key commands reply with value flag 2 and the result of every key,
+/// `count (u16) [| code (u16) | value flag (u8) [| value]]*`.
+pub fn encode_response(id: u32, response: &Response) -> Vec<u8> {
+    let mut body = Vec::new();
+    body.extend_from_slice(&id.to_le_bytes());
+    body.extend_from_slice(&(response.code() as u16).to_le_bytes());
+    if !response.results().is_empty() {
+        body.push(2);
+        body.extend_from_slice(&(response.results().len() as u16).to_le_bytes());
+        for r in response.results() {
+            body.extend_from_slice(&(r.code() as u16).to_le_bytes());
+            write_optional_value(&mut body, r.value());
+        }
+        return body;
+    }
+    write_optional_value(&mut body, response.value());
+    if let Some(u) = response.version() {
+        body.extend_from_slice(u.as_bytes());
+    }
+    body
+}
+
+/// Reads the id and the response of a response body.
+pub fn decode_response(body: &[u8]) -> Result<(u32, Response), CodecError> {
+    let mut reader = Reader::new(body);
+    let id = reader.u32()?;
+    let code = read_code(&mut reader)?;
+    let response = match reader.u8()? {
+        0 => Response::new(code),
+        2 => {
+            let mut results = Vec::new();
+            for _ in 0..reader.u16()? {
+                let code = read_code(&mut reader)?;
+                results.push(match reader.u8()? {
+                    0 => Response::new(code),
+                    _ => Response::with_value(code, reader.value()?),
+                });
+            }
+            Response::new(code).with_results(results)
+        }
+        _ => Response::with_value(code, reader.value()?),
+    };
+    let response = match reader.is_end() {
+        true => response,
+        false => {
+            let version = Uuid::from_slice(reader.bytes(16)?)
+                .map_err(|_| CodecError::Item("uuid".to_string()))?;
+            response.with_version(Some(version))
+        }
+    };
+    if !reader.is_end() {
+        return Err(CodecError::TrailingBytes);
+    }
+    Ok((id, response))
+}
+
+fn read_code(reader: &mut Reader) -> Result<Code, CodecError> {
+    let number = reader.u16()?;
+    Code::try_from(number).map_err(|n| CodecError::Item(format!("code {}", n)))
+}
+
+fn write_optional_value(body: &mut Vec<u8>, value: Option<&Value>) {
+    match value {
+        Some(v) => {
+            body.push(1);
+            write_value(body, v);
+        }
+        None => body.push(0),
+    }
+}
+
+impl From<CodecError> for MessageParseError {
+    fn from(e: CodecError) -> Self {
+        match e {
+            CodecError::Encoding => Self::Encoding,
+            CodecError::UnknownTag(t) => Self::UnknownType(t.to_string()),
+            _ => Self::Pattern,
+        }
+    }
+}
+
+/// Serves a client of the binary protocol.
+///
+/// The client starts with the magic byte and the version. The server repeats them if it speaks that version,
+/// otherwise it replies the magic byte with version 0 and closes the connection.
+/// After the handshake every request frame is answered with a response frame that carries the same id.
+pub fn serve(mut stream: TcpStream, router: &Router) {
+    let mut reader = match stream.try_clone() {
+        Ok(s) => BufReader::new(s),
+        Err(e) => {
+            error!("Stream couldn't clone, {}", e);
+            return;
+        }
+    };
+    let mut hello = [0_u8; 2];
+    if let Err(e) = reader.read_exact(&mut hello) {
+        error!("Handshake couldn't read, {}", e);
+        return;
+    }
+    if hello[1] != VERSION {
+        warn!("Binary protocol version {} is not supported", hello[1]);
+        let _ = stream.write_all(&[MAGIC, 0]);
+        return;
+    }
+    if let Err(e) = stream.write_all(&[MAGIC, VERSION]) {
+        error!("Handshake couldn't write, {}", e);
+        return;
+    }
+    info!("Client speaks binary protocol v{}", VERSION);
+
+    loop {
+        let (id, response, last) = match read_frame(&mut reader) {
+            Ok(Some(Frame::Message(body))) if body.get(4) == Some(&QUIT) && body.len() == 5 => {
+                info!("Client quit.");
+                let (id, _) = decode_request(&body);
+                (id, Response::new(Code::Success), true)
+            }
+            Ok(Some(Frame::Message(body))) => {
+                let (id, message) = decode_request(&body);
+                let response = match message {
+                    Ok(m) => execute(m, router),
+                    Err(e) => {
+                        error!("Parsing error, {:?}", e);
+                        Response::from(e)
+                    }
+                };
+                (id, response, false)
+            }
+            Ok(Some(_)) => {
+                error!("Frame is longer than {} bytes", MAX_MESSAGE_LEN);
+                (0, Response::new(Code::MessageTooLong), true)
+            }
+            Ok(None) => {
+                info!("Client closed the connection.");
+                break;
+            }
+            Err(e) => {
+                error!("Read error, {}", e);
+                break;
+            }
+        };
+        if let Err(e) = write_frame(&mut stream, &encode_response(id, &response)) {
+            error!("Response couldn't write, {}", e);
+            break;
+        }
+        if last {
+            break;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::model::EvictionPolicy;
+    use crate::server::server::handle;
+    use crossbeam::channel;
+    use std::net::TcpListener;
+    use std::thread;
+
+    #[test]
+    fn should_request_survive_round_trip() {
+        let message = Message::new(
+            Command::Add,
+            "motd".to_string(),
+            Some(Value::Text("a|b\nc".to_string())),
+        )
+        .with_ttl(Some(Duration::from_secs(60)));
+        let (id, decoded) = decode_request(&encode_request(42, &message));
+        assert_eq!(id, 42);
+        assert_eq!(decoded.unwrap(), message);
+
+        let hset = Message::new(
+            Command::Hset,
+            "flags".to_string(),
+            Some(Value::Logical(true)),
+        )
+        .with_field(Some("beta".to_string()));
+        let (_, decoded) = decode_request(&encode_request(43, &hset));
+        assert_eq!(decoded.unwrap(), hset);
+        let lrange =
+            Message::new(Command::Lrange, "flags".to_string(), None).with_range(Some((0, -1)));
+        let (_, decoded) = decode_request(&encode_request(44, &lrange));
+        assert_eq!(decoded.unwrap(), lrange);
+        let hget = Message::new(Command::Hget, "flags".to_string(), None);
+        let (_, decoded) = decode_request(&encode_request(45, &hget));
+        assert_eq!(decoded, Err(MessageParseError::Pattern));
+        let iby = Message::new(
+            Command::Iby,
+            "hits".to_string(),
+            Some(Value::HugeNumber(-3)),
+        )
+        .with_create(true);
+        let (_, decoded) = decode_request(&encode_request(46, &iby));
+        assert_eq!(decoded.unwrap(), iby);
+        let inc = Message::new(Command::Inc, "visits".to_string(), None).with_text(true);
+        let (_, decoded) = decode_request(&encode_request(46, &inc));
+        assert_eq!(decoded.unwrap(), inc);
+        let cas = Message::new(Command::Cas, "hits".to_string(), Some(Value::ThinNumber(1)))
+            .with_version(Some(Uuid::new_v4()));
+        let (_, decoded) = decode_request(&encode_request(47, &cas));
+        assert_eq!(decoded.unwrap(), cas);
+        let get = Message::new(Command::Get, "hits".to_string(), None).with_versioned(true);
+        let (_, decoded) = decode_request(&encode_request(48, &get));
+        assert_eq!(decoded.unwrap(), get);
+        let mset = Message::new(Command::Mset, String::new(), None)
+            .with_batch(vec![
+                Message::new(Command::Add, "a".to_string(), Some(Value::ThinNumber(1))),
+                Message::new(Command::Add, "b".to_string(), Some(Value::Logical(true))),
+            ])
+            .with_atomic(true);
+        let (_, decoded) = decode_request(&encode_request(49, &mset));
+        assert_eq!(decoded.unwrap(), mset);
+        let mget = Message::new(Command::Mget, String::new(), None).with_batch(vec![Message::new(
+            Command::Get,
+            "a".to_string(),
+            None,
+        )]);
+        let (_, decoded) = decode_request(&encode_request(50, &mget));
+        assert_eq!(decoded.unwrap(), mget);
+        let mdel = Message::new(Command::Mdel, String::new(), None);
+        let (_, decoded) = decode_request(&encode_request(51, &mdel));
+        assert_eq!(decoded, Err(MessageParseError::Pattern));
+
+        let response = Response::with_value(Code::Success, Value::LargeFloat(-2.5));
+        let (id, decoded) = decode_response(&encode_response(7, &response)).unwrap();
+        assert_eq!(id, 7);
+        assert_eq!(decoded, response);
+        let response = Response::with_value(Code::Success, Value::Logical(true))
+            .with_version(Some(Uuid::new_v4()));
+        let (_, decoded) = decode_response(&encode_response(8, &response)).unwrap();
+        assert_eq!(decoded, response);
+        let response = Response::new(Code::Success).with_results(vec![
+            Response::with_value(Code::Success, Value::Text("on".to_string())),
+            Response::new(Code::NotFound),
+        ]);
+        let (_, decoded) = decode_response(&encode_response(9, &response)).unwrap();
+        assert_eq!(decoded, response);
+    }
+
+    #[test]
+    fn should_wrong_requests_raise_parse_errors() {
+        let long_key = Message::new(Command::Get, "k".repeat(20), None);
+        let (_, decoded) = decode_request(&encode_request(1, &long_key));
+        assert_eq!(decoded, Err(MessageParseError::KeyNameTooLong));
+
+        let huge_ttl = Message::new(Command::Exp, "level".to_string(), None)
+            .with_ttl(Some(Duration::from_secs(u64::MAX)));
+        let (_, decoded) = decode_request(&encode_request(4, &huge_ttl));
+        assert_eq!(
+            decoded,
+            Err(MessageParseError::out_of_range(
+                "ttl",
+                &u64::MAX.to_string()
+            ))
+        );
+
+        let missing_value = Message::new(Command::Add, "level".to_string(), None);
+        let (_, decoded) = decode_request(&encode_request(2, &missing_value));
+        assert_eq!(decoded, Err(MessageParseError::Pattern));
+
+        let mut body = encode_request(3, &Message::new(Command::Get, "k".to_string(), None));
+        body[4] = 99;
+        assert_eq!(decode_request(&body), (3, Err(MessageParseError::Command)));
+        assert_eq!(
+            decode_request(&body[..2]),
+            (0, Err(MessageParseError::Pattern))
+        );
+    }
+
+    #[test]
+    fn should_binary_and_text_clients_share_the_server() {
+        let (informative_transmitter, _informative_receiver) = channel::unbounded();
+        let router = Router::new(2, EvictionPolicy::Reject, &informative_transmitter);
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        thread::spawn(move || {
+            for stream in listener.incoming().take(3) {
+                handle(stream.unwrap(), &router);
+            }
+        });
+
+        let mut older = TcpStream::connect(address).unwrap();
+        older.write_all(&[MAGIC, 1]).unwrap();
+        let mut answer = Vec::new();
+        older.read_to_end(&mut answer).unwrap();
+        assert_eq!(answer, [MAGIC, 0]);
+
+        let mut binary = TcpStream::connect(address).unwrap();
+        binary.write_all(&[MAGIC, VERSION]).unwrap();
+        let mut hello = [0_u8; 2];
+        binary.read_exact(&mut hello).unwrap();
+        assert_eq!(hello, [MAGIC, VERSION]);
+        let add = Message::new(
+            Command::Add,
+            "motd".to_string(),
+            Some(Value::Text("a|b".to_string())),
+        );
+        write_frame(&mut binary, &encode_request(1, &add)).unwrap();
+        let get = Message::new(Command::Get, "motd".to_string(), None);
+        write_frame(&mut binary, &encode_request(2, &get)).unwrap();
+        write_frame(&mut binary, &encode_quit(3)).unwrap();
+        let mut replies = Vec::new();
+        while let Some(Frame::Message(body)) = read_frame(&mut binary).unwrap() {
+            replies.push(decode_response(&body).unwrap());
+        }
+        assert_eq!(
+            replies,
+            vec![
+                (1, Response::new(Code::Success)),
+                (
+                    2,
+                    Response::with_value(Code::Success, Value::Text("a|b".to_string()))
+                ),
+                (3, Response::new(Code::Success)),
+            ]
+        );
+
+        let mut text = TcpStream::connect(address).unwrap();
+        text.write_all(b"GET|motd|\nQUIT\n").unwrap();
+        let mut reply = String::new();
+        text.read_to_string(&mut reply).unwrap();
+        assert_eq!(reply, "200 Success|s|a\\|b|\r\n200 Success|\r\n");
+    }
+}
@@ -0,0 +1,126 @@
+use crate::constant::constant::MAX_MESSAGE_LEN;
+use std::io::{BufRead, BufReader, Read};
+
+/// The message that closes the connection of the client.
+const QUIT: &[u8] = b"QUIT";
+
+/// A single unit read from the client's stream.
+#[derive(Debug, PartialEq)]
+pub enum Frame {
+    Message(Vec<u8>),
+    Quit,
+    TooLong,
+}
+
+/// Splits the incoming bytes of a client into line framed messages.
+///
+/// Every message ends with `\n` (an optional `\r` before it is ignored).
+/// A message can arrive in several reads, and the last message may end with EOF instead of a line break.
+pub struct Connection<R: Read> {
+    reader: BufReader<R>,
+}
+
+impl<R: Read> Connection<R> {
+    pub fn new(stream: R) -> Self {
+        Connection {
+            reader: BufReader::new(stream),
+        }
+    }
+
+    /// Returns the next frame of the stream.
+    ///
+    /// None is returned when the client closes the stream.
+    /// Empty lines are skipped.
+    pub fn next_frame(&mut self) -> std::io::Result<Option<Frame>> {
+        loop {
+            let mut line = Vec::new();
+            let limit = (MAX_MESSAGE_LEN + 2) as u64;
+            let l = (&mut self.reader)
+                .take(limit)
+                .read_until(b'\n', &mut line)?;
+            if l == 0 {
+                return Ok(None);
+            }
+            let terminated = line.ends_with(b"\n");
+            if terminated {
+                line.pop();
+                if line.ends_with(b"\r") {
+                    line.pop();
+                }
+            }
+            if line.len() > MAX_MESSAGE_LEN || (!terminated && l as u64 == limit) {
+                return Ok(Some(Frame::TooLong));
+            }
+            if line.iter().all(|b| b.is_ascii_whitespace()) {
+                continue;
+            }
+            if line == QUIT || line == b"QUIT|" {
+                return Ok(Some(Frame::Quit));
+            }
+            return Ok(Some(Frame::Message(line)));
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::io::Cursor;
+
+    /// Returns the content in small pieces to simulate messages split across reads.
+    struct Chunked {
+        content: Vec<u8>,
+        position: usize,
+    }
+
+    impl Read for Chunked {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            let end = (self.position + 3).min(self.content.len());
+            let l = (end - self.position).min(buf.len());
+            buf[..l].copy_from_slice(&self.content[self.position..self.position + l]);
+            self.position += l;
+            Ok(l)
+        }
+    }
+
+    #[test]
+    fn should_multiple_messages_read_from_one_stream() {
+        let stream = Cursor::new(b"ADD|Logs|l|true|\r\nGET|Logs|\n\nDEL|Logs|".to_vec());
+        let mut connection = Connection::new(stream);
+        assert_eq!(
+            connection.next_frame().unwrap(),
+            Some(Frame::Message(b"ADD|Logs|l|true|".to_vec()))
+        );
+        assert_eq!(
+            connection.next_frame().unwrap(),
+            Some(Frame::Message(b"GET|Logs|".to_vec()))
+        );
+        assert_eq!(
+            connection.next_frame().unwrap(),
+            Some(Frame::Message(b"DEL|Logs|".to_vec()))
+        );
+        assert_eq!(connection.next_frame().unwrap(), None);
+    }
+
+    #[test]
+    fn should_split_and_large_messages_read_as_a_whole() {
+        let value = "x".repeat(600);
+        let content = format!("ADD|Note|s|{}|\nQUIT\nGET|Note|\n", value);
+        let mut connection = Connection::new(Chunked {
+            content: content.into_bytes(),
+            position: 0,
+        });
+        match connection.next_frame().unwrap() {
+            Some(Frame::Message(m)) => assert_eq!(m.len(), 612),
+            f => panic!("Unexpected frame {:?}", f),
+        }
+        assert_eq!(connection.next_frame().unwrap(), Some(Frame::Quit));
+    }
+
+    #[test]
+    fn should_too_long_message_detected() {
+        let content = "x".repeat(MAX_MESSAGE_LEN + 10);
+        let mut connection = Connection::new(Cursor::new(content.into_bytes()));
+        assert_eq!(connection.next_frame().unwrap(), Some(Frame::TooLong));
+    }
+}
@@ -1,2 +1,9 @@
+pub mod binary;
+pub mod connection;
+pub mod pool;
+pub mod reaper;
+pub mod resp;
 pub mod response;
+pub mod router;
+#[allow(clippy::module_inception)]
 pub mod server;
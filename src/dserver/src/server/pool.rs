@@ -0,0 +1,160 @@
+use crate::server::response::{Code, Response};
+use crate::server::router::Router;
+use crate::server::server::handle;
+use crossbeam::channel;
+use crossbeam::channel::{Receiver, Sender, TrySendError};
+use log::{info, warn};
+use std::net::TcpStream;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+
+/// A fixed number of threads that serve the client connections concurrently.
+///
+/// A connection that arrives while all threads are busy waits in the queue until one of them is free.
+/// If the number of served and waiting connections reaches the limit, the new client is rejected
+/// with a TooManyConnections response.
+pub struct ConnectionPool {
+    streams: Sender<TcpStream>,
+    active: Arc<AtomicUsize>,
+    max_connections: usize,
+}
+
+impl ConnectionPool {
+    /// Starts the worker threads of the pool.
+    pub fn new(workers: usize, max_connections: usize, router: &Router) -> Self {
+        let (streams, queue) = channel::bounded(max_connections);
+        let active = Arc::new(AtomicUsize::new(0));
+        for id in 0..workers {
+            let queue = queue.clone();
+            let router = router.clone();
+            let active = active.clone();
+            thread::spawn(move || connection_worker(id, queue, router, active));
+        }
+        info!("{} connection workers started", workers);
+        ConnectionPool {
+            streams,
+            active,
+            max_connections,
+        }
+    }
+
+    /// Queues the stream for the worker threads.
+    ///
+    /// The stream is rejected if the pool is full.
+    pub fn dispatch(&self, mut stream: TcpStream) {
+        if self.active.load(Ordering::SeqCst) >= self.max_connections {
+            warn!("Connection limit {} reached", self.max_connections);
+            Response::new(Code::TooManyConnections).write(&mut stream);
+            return;
+        }
+        self.active.fetch_add(1, Ordering::SeqCst);
+        if let Err(e) = self.streams.try_send(stream) {
+            self.active.fetch_sub(1, Ordering::SeqCst);
+            let mut stream = match e {
+                TrySendError::Full(s) | TrySendError::Disconnected(s) => s,
+            };
+            warn!("Connection couldn't queue");
+            Response::new(Code::TooManyConnections).write(&mut stream);
+        }
+    }
+}
+
+fn connection_worker(
+    id: usize,
+    queue: Receiver<TcpStream>,
+    router: Router,
+    active: Arc<AtomicUsize>,
+) {
+    for stream in queue {
+        info!("Connection worker #{} serves {:?}", id, stream.peer_addr());
+        handle(stream, &router);
+        active.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::model::EvictionPolicy;
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+    use std::time::Duration;
+
+    fn start(workers: usize, max_connections: usize) -> std::net::SocketAddr {
+        let (informative_transmitter, informative_receiver) = channel::unbounded();
+        let router = Router::new(1, EvictionPolicy::Reject, &informative_transmitter);
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        thread::spawn(move || {
+            let _informative_receiver = informative_receiver;
+            let pool = ConnectionPool::new(workers, max_connections, &router);
+            for stream in listener.incoming() {
+                pool.dispatch(stream.unwrap());
+            }
+        });
+        address
+    }
+
+    fn request(stream: &mut TcpStream, message: &str) -> String {
+        stream.write_all(message.as_bytes()).unwrap();
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+        let mut line = String::new();
+        reader.read_line(&mut line).unwrap();
+        line
+    }
+
+    #[test]
+    fn should_slow_client_not_block_others() {
+        let address = start(2, 4);
+        let mut slow = TcpStream::connect(address).unwrap();
+        assert_eq!(request(&mut slow, "ADD|Logs|l|true|\n"), "200 Success|\r\n");
+
+        let mut other = TcpStream::connect(address).unwrap();
+        assert_eq!(
+            request(&mut other, "GET|Logs|\n"),
+            "200 Success|l|true|\r\n"
+        );
+    }
+
+    #[test]
+    fn should_overflow_client_rejected() {
+        let address = start(1, 1);
+        let mut first = TcpStream::connect(address).unwrap();
+        assert_eq!(request(&mut first, "GET|Logs|\n"), "404 NotFound|\r\n");
+
+        let mut second = TcpStream::connect(address).unwrap();
+        let mut reply = String::new();
+        second.read_to_string(&mut reply).unwrap();
+        assert_eq!(reply, "503 TooManyConnections|\r\n");
+
+        assert_eq!(request(&mut first, "QUIT\n"), "200 Success|\r\n");
+    }
+
+    #[test]
+    fn should_queued_client_served_when_a_worker_is_free() {
+        let address = start(1, 2);
+        let mut held = TcpStream::connect(address).unwrap();
+        assert_eq!(request(&mut held, "GET|Logs|\n"), "404 NotFound|\r\n");
+
+        let mut queued = TcpStream::connect(address).unwrap();
+        queued.write_all(b"GET|Logs|\n").unwrap();
+
+        let mut rejected = TcpStream::connect(address).unwrap();
+        rejected
+            .set_read_timeout(Some(Duration::from_secs(1)))
+            .unwrap();
+        let mut reply = String::new();
+        rejected.read_to_string(&mut reply).unwrap();
+        assert_eq!(reply, "503 TooManyConnections|\r\n");
+
+        assert_eq!(request(&mut held, "QUIT\n"), "200 Success|\r\n");
+        queued
+            .set_read_timeout(Some(Duration::from_secs(1)))
+            .unwrap();
+        let mut line = String::new();
+        BufReader::new(queued).read_line(&mut line).unwrap();
+        assert_eq!(line, "404 NotFound|\r\n");
+    }
+}
@@ -0,0 +1,83 @@
+use crate::constant::constant::REAPER_BATCH;
+use crate::server::router::Router;
+use crate::InformativeEvent;
+use crossbeam::channel::{Receiver, RecvTimeoutError, Sender};
+use log::{info, warn};
+use std::time::Duration;
+
+/// Periodically removes the expired items from every pack.
+///
+/// The lock of a pack is held for a single batch only,
+/// so the pack workers can go on between the batches of a crowded pack.
+/// The reaper stops when the shutdown channel is closed.
+pub fn reaper(
+    router: Router,
+    informative: Sender<InformativeEvent>,
+    interval: Duration,
+    shutdown: Receiver<()>,
+) {
+    info!("Reaper started with {:?} interval", interval);
+    while let Err(RecvTimeoutError::Timeout) = shutdown.recv_timeout(interval) {
+        for route in router.routes() {
+            loop {
+                let reaped = route.pack.lock().unwrap().reap(REAPER_BATCH);
+                let count = reaped.len();
+                for item in reaped {
+                    if informative
+                        .send(InformativeEvent::Expired(item.key))
+                        .is_err()
+                    {
+                        warn!("Reaper stopped.");
+                        return;
+                    }
+                }
+                if count < REAPER_BATCH {
+                    break;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::model::EvictionPolicy;
+    use crate::Item;
+    use crate::Value;
+    use crossbeam::channel;
+    use std::thread;
+    use std::time::SystemTime;
+
+    #[test]
+    fn should_reaper_evicts_expired_items_of_all_packs() {
+        let (informative_transmitter, informative_receiver) = channel::unbounded();
+        let router = Router::new(4, EvictionPolicy::Reject, &informative_transmitter);
+        for i in 0..300 {
+            let key = format!("token{}", i);
+            let mut item = Item::new(key.clone(), Value::LargeNumber(i)).unwrap();
+            if i % 3 != 0 {
+                item.expires_at = Some(SystemTime::now() - Duration::from_secs(1));
+            }
+            router.route(&key).pack.lock().unwrap().add(item);
+        }
+
+        let (reaper_router, reaper_informative) = (router.clone(), informative_transmitter.clone());
+        let (_shutdown, shutdown_receiver) = channel::bounded(0);
+        thread::spawn(move || {
+            reaper(
+                reaper_router,
+                reaper_informative,
+                Duration::from_millis(10),
+                shutdown_receiver,
+            )
+        });
+
+        let expired: Vec<InformativeEvent> = informative_receiver.iter().take(200).collect();
+        assert!(expired
+            .iter()
+            .all(|e| matches!(e, InformativeEvent::Expired(_))));
+        let remaining: usize = router.stats().iter().map(|s| s.items).sum();
+        assert_eq!(remaining, 100);
+    }
+}
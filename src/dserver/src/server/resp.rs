@@ -0,0 +1,891 @@
+use crate::constant::constant::{MAX_KEY_LEN, MAX_MESSAGE_LEN, MAX_TTL_SECS};
+use crate::derror::message_parse_error::MessageParseError;
+use crate::derror::resp_error::RespError;
+use crate::model::message::parse_value;
+use crate::model::{Command, Message};
+use crate::server::response::{Code, Response};
+use crate::server::router::Router;
+use crate::server::server::execute;
+use crate::Value;
+use log::{error, info};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpStream;
+use std::str::from_utf8;
+use std::time::Duration;
+
+/// The first byte of a RESP request, the array of its arguments.
+pub const ARRAY: u8 = b'*';
+
+/// The maximum number of arguments in a request.
+const MAX_ARGUMENTS: usize = 256;
+/// The maximum length of a header line like `$12`.
+const MAX_HEADER_LEN: u64 = 32;
+
+/// A reply in the RESP types. It is written in the RESP version of the connection.
+#[derive(Debug, PartialEq)]
+pub enum Reply {
+    Simple(String),
+    Error(String),
+    Integer(i64),
+    BigNumber(i128),
+    Bulk(Vec<u8>),
+    Double(f64),
+    Boolean(bool),
+    Null,
+    Array(Vec<Reply>),
+    Map(Vec<(Reply, Reply)>),
+}
+
+impl Reply {
+    fn ok() -> Self {
+        Reply::Simple("OK".to_string())
+    }
+
+    fn bulk(text: &str) -> Self {
+        Reply::Bulk(text.as_bytes().to_vec())
+    }
+
+    /// Returns the reply in the wire format of the version.
+    ///
+    /// RESP2 has no doubles, big numbers, booleans, maps and nulls of its own,
+    /// so they are written as bulk strings, integers, flat arrays and the null bulk string.
+    pub fn encode(&self, version: u8) -> Vec<u8> {
+        let mut buffer = Vec::new();
+        self.write(version, &mut buffer);
+        buffer
+    }
+
+    fn write(&self, version: u8, buffer: &mut Vec<u8>) {
+        let resp3 = version >= 3;
+        match self {
+            Reply::Simple(s) => buffer.extend_from_slice(format!("+{}\r\n", s).as_bytes()),
+            Reply::Error(e) => buffer.extend_from_slice(format!("-{}\r\n", e).as_bytes()),
+            Reply::Integer(n) => buffer.extend_from_slice(format!(":{}\r\n", n).as_bytes()),
+            Reply::BigNumber(n) if resp3 => {
+                buffer.extend_from_slice(format!("({}\r\n", n).as_bytes())
+            }
+            Reply::BigNumber(n) => Reply::bulk(&n.to_string()).write(version, buffer),
+            Reply::Bulk(b) => {
+                buffer.extend_from_slice(format!("${}\r\n", b.len()).as_bytes());
+                buffer.extend_from_slice(b);
+                buffer.extend_from_slice(b"\r\n");
+            }
+            Reply::Double(d) if resp3 => buffer.extend_from_slice(format!(",{}\r\n", d).as_bytes()),
+            Reply::Double(d) => Reply::bulk(&d.to_string()).write(version, buffer),
+            Reply::Boolean(b) if resp3 => {
+                buffer.extend_from_slice(if *b { b"#t\r\n" } else { b"#f\r\n" })
+            }
+            Reply::Boolean(b) => Reply::Integer(i64::from(*b)).write(version, buffer),
+            Reply::Null if resp3 => buffer.extend_from_slice(b"_\r\n"),
+            Reply::Null => buffer.extend_from_slice(b"$-1\r\n"),
+            Reply::Array(items) => {
+                buffer.extend_from_slice(format!("*{}\r\n", items.len()).as_bytes());
+                items.iter().for_each(|i| i.write(version, buffer));
+            }
+            Reply::Map(pairs) => {
+                let header = match resp3 {
+                    true => format!("%{}\r\n", pairs.len()),
+                    false => format!("*{}\r\n", pairs.len() * 2),
+                };
+                buffer.extend_from_slice(header.as_bytes());
+                for (k, v) in pairs {
+                    k.write(version, buffer);
+                    v.write(version, buffer);
+                }
+            }
+        }
+    }
+}
+
+impl From<&Value> for Reply {
+    fn from(value: &Value) -> Self {
+        match value {
+            Value::Empty => Reply::Null,
+            Value::ThinNumber(n) => Reply::Integer(i64::from(*n)),
+            Value::MidNumber(n) => Reply::Integer(i64::from(*n)),
+            Value::LargeNumber(n) => Reply::Integer(i64::from(*n)),
+            Value::ThinFloat(f) => Reply::Double(f64::from(*f)),
+            Value::LargeFloat(f) => Reply::Double(*f),
+            Value::Text(t) => Reply::bulk(t),
+            Value::Logical(b) => Reply::Boolean(*b),
+            Value::ThinUnsigned(n) => Reply::Integer(i64::from(*n)),
+            Value::MidUnsigned(n) => Reply::Integer(i64::from(*n)),
+            Value::LargeUnsigned(n) => Reply::Integer(i64::from(*n)),
+            Value::HugeNumber(n) => Reply::Integer(*n),
+            Value::HugeUnsigned(n) => match i64::try_from(*n) {
+                Ok(n) => Reply::Integer(n),
+                Err(_) => Reply::BigNumber(i128::from(*n)),
+            },
+            Value::GiantNumber(n) => match i64::try_from(*n) {
+                Ok(n) => Reply::Integer(n),
+                Err(_) => Reply::BigNumber(*n),
+            },
+            Value::Bytes(b) => Reply::Bulk(b.clone()),
+            Value::Timestamp(_) | Value::Duration(_) | Value::Uuid(_) => {
+                Reply::bulk(&value.payload())
+            }
+            Value::List(v) | Value::Set(v) => Reply::Array(v.iter().map(Reply::from).collect()),
+            Value::Hash(v) => Reply::Map(
+                v.iter()
+                    .map(|(f, e)| (Reply::bulk(f), Reply::from(e)))
+                    .collect(),
+            ),
+        }
+    }
+}
+
+/// Turns an error code of dory into a RESP error.
+///
+/// WrongType and Overflow are written like Redis does, so clients can tell them apart.
+fn failure(code: Code) -> Reply {
+    match code {
+        Code::WrongType => Reply::Error(
+            "WRONGTYPE Operation against a key holding the wrong kind of value".to_string(),
+        ),
+        Code::Overflow => Reply::Error("ERR increment or decrement would overflow".to_string()),
+        code => Reply::Error(format!("ERR {}", code)),
+    }
+}
+
+/// Reads the arguments of the next request, like `*2\r\n$3\r\nGET\r\n$4\r\nmotd\r\n`.
+///
+/// None is returned when the client closes the stream between requests.
+pub fn read_request<R: BufRead>(reader: &mut R) -> Result<Option<Vec<Vec<u8>>>, RespError> {
+    let line = match read_line(reader)? {
+        Some(l) => l,
+        None => return Ok(None),
+    };
+    let count = header(&line, ARRAY)?;
+    if count == 0 || count > MAX_ARGUMENTS {
+        return Err(RespError::Protocol("invalid multibulk length"));
+    }
+    let mut args = Vec::with_capacity(count);
+    for _ in 0..count {
+        let line = read_line(reader)?.ok_or(RespError::Protocol("unexpected end"))?;
+        let length = header(&line, b'$')?;
+        if length > MAX_MESSAGE_LEN {
+            return Err(RespError::Protocol("invalid bulk length"));
+        }
+        let mut arg = vec![0_u8; length + 2];
+        reader.read_exact(&mut arg)?;
+        if !arg.ends_with(b"\r\n") {
+            return Err(RespError::Protocol("expected CRLF"));
+        }
+        arg.truncate(length);
+        args.push(arg);
+    }
+    Ok(Some(args))
+}
+
+fn read_line<R: BufRead>(reader: &mut R) -> Result<Option<Vec<u8>>, RespError> {
+    let mut line = Vec::new();
+    if reader
+        .by_ref()
+        .take(MAX_HEADER_LEN)
+        .read_until(b'\n', &mut line)?
+        == 0
+    {
+        return Ok(None);
+    }
+    match line.strip_suffix(b"\r\n") {
+        Some(l) => Ok(Some(l.to_vec())),
+        None => Err(RespError::Protocol("expected CRLF")),
+    }
+}
+
+/// Returns the length that the header line of the type announces.
+fn header(line: &[u8], prefix: u8) -> Result<usize, RespError> {
+    match line.split_first() {
+        Some((p, n)) if *p == prefix => from_utf8(n)
+            .ok()
+            .and_then(|n| n.parse().ok())
+            .ok_or(RespError::Protocol("invalid length")),
+        _ => Err(RespError::Protocol("unexpected type")),
+    }
+}
+
+/// State of a RESP connection.
+pub struct Session {
+    /// The RESP version of the replies, HELLO switches it.
+    version: u8,
+}
+
+impl Default for Session {
+    fn default() -> Self {
+        Session { version: 2 }
+    }
+}
+
+impl Session {
+    /// Runs the request on the packs and returns its reply.
+    pub fn dispatch(&mut self, args: &[Vec<u8>], router: &Router) -> Reply {
+        let (name, args) = match args.split_first() {
+            Some((n, a)) => (String::from_utf8_lossy(n).to_uppercase(), a),
+            None => return Reply::Error("ERR empty request".to_string()),
+        };
+        let result = match (name.as_str(), args) {
+            ("PING", []) => Ok(Reply::Simple("PONG".to_string())),
+            ("PING", [m]) => Ok(Reply::Bulk(m.clone())),
+            ("HELLO", []) => Ok(self.hello()),
+            ("HELLO", [v, ..]) => match v.as_slice() {
+                b"2" | b"3" => {
+                    self.version = v[0] - b'0';
+                    Ok(self.hello())
+                }
+                _ => Err(Reply::Error(
+                    "NOPROTO unsupported protocol version".to_string(),
+                )),
+            },
+            // Clients ask for the command docs and set their name on connect, dory has nothing to tell.
+            ("COMMAND", _) => Ok(Reply::Array(Vec::new())),
+            ("CLIENT", _) => Ok(Reply::ok()),
+            ("SET", [k, v, options @ ..]) => set(k, v, options, router),
+            ("GET", [k]) => get(k, router),
+            ("MGET", keys) if !keys.is_empty() => mget(keys, router),
+            ("MSET", pairs) if !pairs.is_empty() && pairs.len() % 2 == 0 => mset(pairs, router),
+            ("DEL", keys) if !keys.is_empty() => count(Command::Del, keys, router),
+            ("EXISTS", keys) if !keys.is_empty() => count(Command::Exs, keys, router),
+            ("EXPIRE", [k, s]) => expire(k, s, router),
+            ("INFO", [] | [_]) => Ok(info(router)),
+            ("LPUSH", [k, v]) => collection(Command::Lpush, k, None, Some(v), router),
+            ("LPOP", [k]) => collection(Command::Lpop, k, None, None, router),
+            ("LRANGE", [k, start, stop]) => listed(Command::Lrange, k, &[start, stop], router),
+            ("SADD", [k, v]) => collection(Command::Sadd, k, None, Some(v), router),
+            ("SREM", [k, v]) => collection(Command::Srem, k, None, Some(v), router),
+            ("SMEMBERS", [k]) => listed(Command::Smembers, k, &[], router),
+            ("HSET", [k, f, v]) => collection(Command::Hset, k, Some(f), Some(v), router),
+            ("HGET", [k, f]) => collection(Command::Hget, k, Some(f), None, router),
+            ("HDEL", [k, f]) => collection(Command::Hdel, k, Some(f), None, router),
+            ("HGETALL", [k]) => listed(Command::Hgetall, k, &[], router),
+            ("INCR", [k]) => counter(k, 1, router),
+            ("DECR", [k]) => counter(k, -1, router),
+            ("INCRBY", [k, n]) => integer(n).and_then(|n| counter(k, n, router)),
+            (
+                "PING" | "SET" | "GET" | "MGET" | "MSET" | "DEL" | "EXISTS" | "EXPIRE" | "INFO"
+                | "LPUSH" | "LPOP" | "LRANGE" | "SADD" | "SREM" | "SMEMBERS" | "HSET" | "HGET"
+                | "HDEL" | "HGETALL" | "INCR" | "DECR" | "INCRBY",
+                _,
+            ) => Err(Reply::Error(format!(
+                "ERR wrong number of arguments for '{}' command",
+                name.to_lowercase()
+            ))),
+            _ => Err(Reply::Error(format!("ERR unknown command '{}'", name))),
+        };
+        result.unwrap_or_else(|e| e)
+    }
+
+    fn hello(&self) -> Reply {
+        Reply::Map(vec![
+            (Reply::bulk("server"), Reply::bulk("dory")),
+            (
+                Reply::bulk("version"),
+                Reply::bulk(env!("CARGO_PKG_VERSION")),
+            ),
+            (
+                Reply::bulk("proto"),
+                Reply::Integer(i64::from(self.version)),
+            ),
+            (Reply::bulk("mode"), Reply::bulk("standalone")),
+        ])
+    }
+}
+
+fn key(k: &[u8]) -> Result<String, Reply> {
+    let key = from_utf8(k).map_err(|_| failure(Code::Encoding))?;
+    if key.chars().count() > MAX_KEY_LEN {
+        return Err(failure(Code::KeyTooLong));
+    }
+    Ok(key.to_string())
+}
+
+fn integer(v: &[u8]) -> Result<i64, Reply> {
+    from_utf8(v)
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .ok_or_else(|| Reply::Error("ERR value is not an integer or out of range".to_string()))
+}
+
+/// `SET key value [NX|XX] [EX seconds|PX milliseconds]`, values are stored as text.
+fn set(k: &[u8], v: &[u8], options: &[Vec<u8>], router: &Router) -> Result<Reply, Reply> {
+    let key = key(k)?;
+    let text = from_utf8(v).map_err(|_| failure(Code::Encoding))?;
+    let value =
+        parse_value("s", text).map_err(|e: MessageParseError| failure(Response::from(e).code()))?;
+    let mut command = Command::Add;
+    let mut ttl = None;
+    let mut options = options.iter();
+    while let Some(option) = options.next() {
+        let option = String::from_utf8_lossy(option).to_uppercase();
+        let mut amount = || -> Result<u64, Reply> {
+            match options.next().map(|n| integer(n)).transpose()? {
+                Some(n) if n > 0 => Ok(n as u64),
+                _ => Err(Reply::Error(
+                    "ERR invalid expire time in 'set' command".to_string(),
+                )),
+            }
+        };
+        match option.as_str() {
+            "NX" => command = Command::Anx,
+            "XX" => command = Command::Axx,
+            "EX" => ttl = Some(Duration::from_secs(amount()?)),
+            "PX" => ttl = Some(Duration::from_millis(amount()?)),
+            _ => return Err(Reply::Error("ERR syntax error".to_string())),
+        }
+    }
+    if matches!(ttl, Some(t) if t.as_secs() > MAX_TTL_SECS) {
+        return Err(Reply::Error(
+            "ERR invalid expire time in 'set' command".to_string(),
+        ));
+    }
+    let message = Message::new(command, key, Some(value)).with_ttl(ttl);
+    match execute(message, router).code() {
+        Code::Success => Ok(Reply::ok()),
+        Code::KeyExists | Code::NotFound => Ok(Reply::Null),
+        code => Err(failure(code)),
+    }
+}
+
+fn get(k: &[u8], router: &Router) -> Result<Reply, Reply> {
+    let response = execute(Message::new(Command::Get, key(k)?, None), router);
+    match (response.code(), response.value()) {
+        (Code::Success, Some(v)) => Ok(Reply::from(v)),
+        (Code::NotFound, _) => Ok(Reply::Null),
+        (code, _) => Err(failure(code)),
+    }
+}
+
+/// `MGET key [key ...]`, keys that are missing or hold a collection are null like Redis does.
+fn mget(keys: &[Vec<u8>], router: &Router) -> Result<Reply, Reply> {
+    let batch = keys
+        .iter()
+        .map(|k| Ok(Message::new(Command::Get, key(k)?, None)))
+        .collect::<Result<Vec<Message>, Reply>>()?;
+    let message = Message::new(Command::Mget, String::new(), None).with_batch(batch);
+    let response = execute(message, router);
+    match response.code() {
+        Code::Success => Ok(Reply::Array(
+            response
+                .results()
+                .iter()
+                .map(|r| match (r.code(), r.value()) {
+                    (_, Some(Value::List(_) | Value::Set(_) | Value::Hash(_))) => Reply::Null,
+                    (Code::Success, Some(v)) => Reply::from(v),
+                    _ => Reply::Null,
+                })
+                .collect(),
+        )),
+        code => Err(failure(code)),
+    }
+}
+
+/// `MSET key value [key value ...]`, values are stored as text and all of them are set or none is.
+fn mset(pairs: &[Vec<u8>], router: &Router) -> Result<Reply, Reply> {
+    let batch = pairs
+        .chunks(2)
+        .map(|p| {
+            let text = from_utf8(&p[1]).map_err(|_| failure(Code::Encoding))?;
+            let value = parse_value("s", text)
+                .map_err(|e: MessageParseError| failure(Response::from(e).code()))?;
+            Ok(Message::new(Command::Add, key(&p[0])?, Some(value)))
+        })
+        .collect::<Result<Vec<Message>, Reply>>()?;
+    let message = Message::new(Command::Mset, String::new(), None)
+        .with_batch(batch)
+        .with_atomic(true);
+    let response = execute(message, router);
+    let failed = response
+        .results()
+        .iter()
+        .map(Response::code)
+        .find(|c| *c != Code::Success);
+    match (response.code(), failed) {
+        (Code::Success, None) => Ok(Reply::ok()),
+        (Code::Success, Some(code)) | (code, _) => Err(failure(code)),
+    }
+}
+
+/// Runs the command for every key and returns the number of keys that were found.
+fn count(command: Command, keys: &[Vec<u8>], router: &Router) -> Result<Reply, Reply> {
+    let mut found = 0;
+    for k in keys {
+        let message = Message::new(command.clone(), key(k)?, None);
+        match execute(message, router).code() {
+            Code::Success => found += 1,
+            Code::NotFound => {}
+            code => return Err(failure(code)),
+        }
+    }
+    Ok(Reply::Integer(found))
+}
+
+/// `EXPIRE key seconds`, a time that is not positive deletes the key like Redis does.
+fn expire(k: &[u8], s: &[u8], router: &Router) -> Result<Reply, Reply> {
+    let key = key(k)?;
+    let seconds = integer(s)?;
+    let message = match seconds {
+        s if s > 0 && s as u64 > MAX_TTL_SECS => {
+            return Err(Reply::Error(
+                "ERR invalid expire time in 'expire' command".to_string(),
+            ))
+        }
+        s if s > 0 => {
+            Message::new(Command::Exp, key, None).with_ttl(Some(Duration::from_secs(s as u64)))
+        }
+        _ => Message::new(Command::Del, key, None),
+    };
+    match execute(message, router).code() {
+        Code::Success => Ok(Reply::Integer(1)),
+        Code::NotFound => Ok(Reply::Integer(0)),
+        code => Err(failure(code)),
+    }
+}
+
+/// Runs a collection command, elements and fields are stored as text like SET does.
+///
+/// A missing key or field is a null reply, and removing from a missing key removes nothing.
+fn collection(
+    command: Command,
+    k: &[u8],
+    f: Option<&Vec<u8>>,
+    v: Option<&Vec<u8>>,
+    router: &Router,
+) -> Result<Reply, Reply> {
+    let removes = matches!(command, Command::Srem | Command::Hdel);
+    let text = |b: &[u8]| {
+        from_utf8(b)
+            .map(str::to_string)
+            .map_err(|_| failure(Code::Encoding))
+    };
+    let field = f.map(|f| text(f)).transpose()?;
+    let value = match v {
+        Some(v) => {
+            Some(parse_value("s", &text(v)?).map_err(|e| failure(Response::from(e).code()))?)
+        }
+        None => None,
+    };
+    let message = Message::new(command, key(k)?, value).with_field(field);
+    let response = execute(message, router);
+    match (response.code(), response.value()) {
+        (Code::Success, Some(v)) => Ok(Reply::from(v)),
+        (Code::NotFound, _) if removes => Ok(Reply::Integer(0)),
+        (Code::NotFound, _) => Ok(Reply::Null),
+        (code, _) => Err(failure(code)),
+    }
+}
+
+/// Runs a command that returns the elements of a collection, a missing key has no elements.
+///
+/// The positions are the start and stop of LRANGE.
+fn listed(
+    command: Command,
+    k: &[u8],
+    positions: &[&Vec<u8>],
+    router: &Router,
+) -> Result<Reply, Reply> {
+    let empty = match command {
+        Command::Hgetall => Reply::Map(Vec::new()),
+        _ => Reply::Array(Vec::new()),
+    };
+    let range = match positions {
+        [start, stop] => Some((integer(start)?, integer(stop)?)),
+        _ => None,
+    };
+    let message = Message::new(command, key(k)?, None).with_range(range);
+    let response = execute(message, router);
+    match (response.code(), response.value()) {
+        (Code::Success, Some(v)) => Ok(Reply::from(v)),
+        (Code::NotFound, _) => Ok(empty),
+        (code, _) => Err(failure(code)),
+    }
+}
+
+/// `INCR`, `DECR` and `INCRBY` add to a 64-bit number, a missing key starts from zero like Redis does.
+///
+/// Text that SET stored counts as a number if it holds one and stays text, like `SET k 5` then `INCR k`.
+/// Keys holding another kind of value are not integers for Redis clients.
+fn counter(k: &[u8], amount: i64, router: &Router) -> Result<Reply, Reply> {
+    let message = Message::new(Command::Iby, key(k)?, Some(Value::HugeNumber(amount)))
+        .with_create(true)
+        .with_text(true);
+    let response = execute(message, router);
+    match (response.code(), response.value()) {
+        (Code::Success, Some(v)) => Ok(Reply::from(v)),
+        (Code::WrongType, _) => Err(Reply::Error(
+            "ERR value is not an integer or out of range".to_string(),
+        )),
+        (code, _) => Err(failure(code)),
+    }
+}
+
+fn info(router: &Router) -> Reply {
+    let stats = router.stats();
+    let mut text = format!(
+        "# Server\r\nserver:dory\r\nversion:{}\r\n# Packs\r\npacks:{}\r\n",
+        env!("CARGO_PKG_VERSION"),
+        stats.len()
+    );
+    for s in stats {
+        text.push_str(&format!("pack{}:{}/{}\r\n", s.id, s.items, s.capacity));
+    }
+    Reply::bulk(&text)
+}
+
+/// Serves a client that speaks RESP, like redis-cli or a Redis client library.
+///
+/// The connection starts with RESP2, HELLO 3 switches it to RESP3.
+pub fn serve(mut stream: TcpStream, router: &Router) {
+    let mut reader = match stream.try_clone() {
+        Ok(s) => BufReader::new(s),
+        Err(e) => {
+            error!("Stream couldn't clone, {}", e);
+            return;
+        }
+    };
+    info!("Client speaks RESP");
+    let mut session = Session::default();
+    loop {
+        let (reply, last) = match read_request(&mut reader) {
+            Ok(Some(args)) if args[0].eq_ignore_ascii_case(b"QUIT") => (Reply::ok(), true),
+            Ok(Some(args)) => (session.dispatch(&args, router), false),
+            Ok(None) => {
+                info!("Client closed the connection.");
+                break;
+            }
+            Err(RespError::Io(e)) => {
+                error!("Read error, {}", e);
+                break;
+            }
+            Err(e) => {
+                error!("{}", e);
+                (Reply::Error(format!("ERR {}", e)), true)
+            }
+        };
+        if let Err(e) = stream.write_all(&reply.encode(session.version)) {
+            error!("Response couldn't write, {}", e);
+            break;
+        }
+        if last {
+            break;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::model::EvictionPolicy;
+    use crate::server::server::handle;
+    use crate::InformativeEvent;
+    use crossbeam::channel;
+    use crossbeam::channel::Receiver;
+    use std::io::Cursor;
+    use std::net::TcpListener;
+    use std::thread;
+
+    fn request(args: &[&str]) -> Vec<u8> {
+        let mut buffer = format!("*{}\r\n", args.len());
+        for a in args {
+            buffer.push_str(&format!("${}\r\n{}\r\n", a.len(), a));
+        }
+        buffer.into_bytes()
+    }
+
+    fn router() -> (Router, Receiver<InformativeEvent>) {
+        let (informative_transmitter, informative_receiver) = channel::unbounded();
+        let router = Router::new(2, EvictionPolicy::Reject, &informative_transmitter);
+        (router, informative_receiver)
+    }
+
+    fn call(session: &mut Session, args: &[&str], router: &Router) -> Reply {
+        let args: Vec<Vec<u8>> = args.iter().map(|a| a.as_bytes().to_vec()).collect();
+        session.dispatch(&args, router)
+    }
+
+    #[test]
+    fn should_requests_read_as_arguments() {
+        let mut reader = Cursor::new(request(&["SET", "motd", "hello\r\nworld"]));
+        assert_eq!(
+            read_request(&mut reader).unwrap(),
+            Some(vec![
+                b"SET".to_vec(),
+                b"motd".to_vec(),
+                b"hello\r\nworld".to_vec()
+            ])
+        );
+        assert_eq!(read_request(&mut reader).unwrap(), None);
+
+        for wrong in ["*0\r\n", "*1\r\n$3\r\nGETX\r\n", "*1\n", "$3\r\nGET\r\n"] {
+            let mut reader = Cursor::new(wrong.as_bytes());
+            assert!(matches!(
+                read_request(&mut reader),
+                Err(RespError::Protocol(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn should_replies_written_in_the_version() {
+        let map = Reply::Map(vec![(Reply::bulk("proto"), Reply::Integer(3))]);
+        assert_eq!(map.encode(2), b"*2\r\n$5\r\nproto\r\n:3\r\n");
+        assert_eq!(map.encode(3), b"%1\r\n$5\r\nproto\r\n:3\r\n");
+        assert_eq!(Reply::Null.encode(2), b"$-1\r\n");
+        assert_eq!(Reply::Null.encode(3), b"_\r\n");
+        assert_eq!(
+            Reply::from(&Value::LargeFloat(2.5)).encode(2),
+            b"$3\r\n2.5\r\n"
+        );
+        assert_eq!(Reply::from(&Value::LargeFloat(2.5)).encode(3), b",2.5\r\n");
+        assert_eq!(Reply::from(&Value::Logical(true)).encode(2), b":1\r\n");
+        assert_eq!(Reply::from(&Value::Logical(true)).encode(3), b"#t\r\n");
+        assert_eq!(Reply::from(&Value::MidNumber(-7)).encode(3), b":-7\r\n");
+        let big = Reply::from(&Value::HugeUnsigned(u64::MAX));
+        assert_eq!(big.encode(3), b"(18446744073709551615\r\n");
+        assert_eq!(big.encode(2), b"$20\r\n18446744073709551615\r\n");
+        assert_eq!(
+            Reply::from(&Value::Bytes(vec![0, 13])).encode(2),
+            b"$2\r\n\0\r\r\n"
+        );
+    }
+
+    #[test]
+    fn should_commands_mapped_to_packs() {
+        let (router, _informative_receiver) = router();
+        let mut session = Session::default();
+        assert_eq!(
+            call(&mut session, &["ping"], &router),
+            Reply::Simple("PONG".to_string())
+        );
+        assert_eq!(
+            call(&mut session, &["SET", "motd", "hi"], &router),
+            Reply::ok()
+        );
+        assert_eq!(
+            call(&mut session, &["SET", "motd", "x", "NX"], &router),
+            Reply::Null
+        );
+        assert_eq!(
+            call(&mut session, &["SET", "none", "x", "XX"], &router),
+            Reply::Null
+        );
+        assert_eq!(
+            call(&mut session, &["GET", "motd"], &router),
+            Reply::bulk("hi")
+        );
+        assert_eq!(call(&mut session, &["GET", "none"], &router), Reply::Null);
+        assert_eq!(
+            call(&mut session, &["EXISTS", "motd", "none", "motd"], &router),
+            Reply::Integer(2)
+        );
+        let hits = |router: &Router| {
+            let pack = router.route("motd").pack.lock().unwrap();
+            pack.items[pack.index["motd"]].usage.hits
+        };
+        let before = hits(&router);
+        call(&mut session, &["EXISTS", "motd"], &router);
+        assert_eq!(hits(&router), before);
+        assert_eq!(
+            call(&mut session, &["EXPIRE", "motd", "60"], &router),
+            Reply::Integer(1)
+        );
+        assert_eq!(
+            call(&mut session, &["EXPIRE", "none", "60"], &router),
+            Reply::Integer(0)
+        );
+        let max = i64::MAX.to_string();
+        assert_eq!(
+            call(&mut session, &["EXPIRE", "motd", max.as_str()], &router),
+            Reply::Error("ERR invalid expire time in 'expire' command".to_string())
+        );
+        assert_eq!(
+            call(
+                &mut session,
+                &["SET", "motd", "hi", "EX", max.as_str()],
+                &router
+            ),
+            Reply::Error("ERR invalid expire time in 'set' command".to_string())
+        );
+        assert_eq!(
+            call(&mut session, &["DEL", "motd", "none"], &router),
+            Reply::Integer(1)
+        );
+        assert!(matches!(
+            call(&mut session, &["INFO"], &router),
+            Reply::Bulk(_)
+        ));
+
+        assert_eq!(
+            call(&mut session, &["SET", "VeryVeryLongKeyName", "x"], &router),
+            Reply::Error("ERR 424 KeyTooLong".to_string())
+        );
+        assert_eq!(
+            call(&mut session, &["SET", "motd", "x", "EX", "0"], &router),
+            Reply::Error("ERR invalid expire time in 'set' command".to_string())
+        );
+        assert_eq!(
+            call(&mut session, &["GET"], &router),
+            Reply::Error("ERR wrong number of arguments for 'get' command".to_string())
+        );
+        assert_eq!(
+            call(&mut session, &["FLUSHALL"], &router),
+            Reply::Error("ERR unknown command 'FLUSHALL'".to_string())
+        );
+        assert!(matches!(
+            call(&mut session, &["HELLO", "4"], &router),
+            Reply::Error(_)
+        ));
+        assert!(matches!(
+            call(&mut session, &["HELLO", "3"], &router),
+            Reply::Map(_)
+        ));
+        assert_eq!(session.version, 3);
+    }
+
+    #[test]
+    fn should_collection_commands_mapped_to_packs() {
+        let (router, _informative_receiver) = router();
+        let mut session = Session::default();
+        assert_eq!(
+            call(&mut session, &["LPUSH", "queue", "a"], &router),
+            Reply::Integer(1)
+        );
+        call(&mut session, &["LPUSH", "queue", "b"], &router);
+        assert_eq!(
+            call(&mut session, &["LRANGE", "queue", "0", "-1"], &router),
+            Reply::Array(vec![Reply::bulk("b"), Reply::bulk("a")])
+        );
+        assert_eq!(
+            call(&mut session, &["LPOP", "queue"], &router),
+            Reply::bulk("b")
+        );
+        assert_eq!(call(&mut session, &["LPOP", "none"], &router), Reply::Null);
+
+        assert_eq!(
+            call(&mut session, &["SADD", "tags", "x"], &router),
+            Reply::Integer(1)
+        );
+        assert_eq!(
+            call(&mut session, &["SADD", "tags", "x"], &router),
+            Reply::Integer(0)
+        );
+        assert_eq!(
+            call(&mut session, &["SREM", "none", "x"], &router),
+            Reply::Integer(0)
+        );
+        assert_eq!(
+            call(&mut session, &["SMEMBERS", "none"], &router),
+            Reply::Array(Vec::new())
+        );
+
+        call(&mut session, &["HSET", "user", "name", "dory"], &router);
+        assert_eq!(
+            call(&mut session, &["HGET", "user", "name"], &router),
+            Reply::bulk("dory")
+        );
+        assert_eq!(
+            call(&mut session, &["HGETALL", "user"], &router),
+            Reply::Map(vec![(Reply::bulk("name"), Reply::bulk("dory"))])
+        );
+        assert_eq!(
+            call(&mut session, &["HDEL", "user", "name"], &router),
+            Reply::Integer(1)
+        );
+        assert_eq!(
+            call(&mut session, &["HGETALL", "user"], &router),
+            Reply::Map(Vec::new())
+        );
+        assert!(matches!(
+            call(&mut session, &["HGET", "tags", "x"], &router),
+            Reply::Error(e) if e.starts_with("WRONGTYPE")
+        ));
+    }
+
+    #[test]
+    fn should_counters_start_from_zero() {
+        let (router, _informative_receiver) = router();
+        let mut session = Session::default();
+        assert_eq!(
+            call(&mut session, &["INCR", "hits"], &router),
+            Reply::Integer(1)
+        );
+        assert_eq!(
+            call(&mut session, &["INCRBY", "hits", "41"], &router),
+            Reply::Integer(42)
+        );
+        assert_eq!(
+            call(&mut session, &["DECR", "misses"], &router),
+            Reply::Integer(-1)
+        );
+        let max = i64::MAX.to_string();
+        assert_eq!(
+            call(&mut session, &["INCRBY", "hits", max.as_str()], &router),
+            Reply::Error("ERR increment or decrement would overflow".to_string())
+        );
+        call(&mut session, &["SET", "motd", "hello"], &router);
+        assert_eq!(
+            call(&mut session, &["INCR", "motd"], &router),
+            Reply::Error("ERR value is not an integer or out of range".to_string())
+        );
+        call(&mut session, &["SET", "visits", "5"], &router);
+        assert_eq!(
+            call(&mut session, &["INCR", "visits"], &router),
+            Reply::Integer(6)
+        );
+        assert_eq!(
+            call(&mut session, &["GET", "visits"], &router),
+            Reply::bulk("6")
+        );
+        call(&mut session, &["SET", "zip", "01234"], &router);
+        assert_eq!(
+            call(&mut session, &["DECR", "zip"], &router),
+            Reply::Error("ERR value is not an integer or out of range".to_string())
+        );
+    }
+
+    #[test]
+    fn should_multi_key_commands_reply_like_redis() {
+        let (router, _informative_receiver) = router();
+        let mut session = Session::default();
+        assert_eq!(
+            call(&mut session, &["MSET", "a", "1", "b", "2"], &router),
+            Reply::ok()
+        );
+        call(&mut session, &["LPUSH", "queue", "x"], &router);
+        assert_eq!(
+            call(&mut session, &["MGET", "b", "none", "queue", "a"], &router),
+            Reply::Array(vec![
+                Reply::bulk("2"),
+                Reply::Null,
+                Reply::Null,
+                Reply::bulk("1")
+            ])
+        );
+        assert_eq!(
+            call(&mut session, &["MSET", "a"], &router),
+            Reply::Error("ERR wrong number of arguments for 'mset' command".to_string())
+        );
+        assert_eq!(
+            call(&mut session, &["MGET"], &router),
+            Reply::Error("ERR wrong number of arguments for 'mget' command".to_string())
+        );
+    }
+
+    #[test]
+    fn should_resp_clients_detected_on_the_same_port() {
+        let (router, _informative_receiver) = router();
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        thread::spawn(move || {
+            let stream = listener.incoming().next().unwrap();
+            handle(stream.unwrap(), &router);
+        });
+
+        let mut stream = TcpStream::connect(address).unwrap();
+        let mut requests = request(&["SET", "level", "3"]);
+        requests.extend(request(&["GET", "level"]));
+        requests.extend(request(&["QUIT"]));
+        stream.write_all(&requests).unwrap();
+        let mut replies = String::new();
+        stream.read_to_string(&mut replies).unwrap();
+        assert_eq!(replies, "+OK\r\n$1\r\n3\r\n+OK\r\n");
+    }
+}
@@ -1,25 +1,286 @@
+use crate::derror::message_parse_error::MessageParseError;
+use crate::derror::message_send_error::MessageSendError;
+use crate::derror::new_item_error::NewItemError;
+use crate::derror::snapshot_error::SnapshotError;
+use crate::derror::write_log_error::WriteLogError;
+use crate::model::message::{next_value, value_parts};
+use crate::{InformativeEvent, Value};
+use log::error;
 use std::fmt::{Display, Formatter};
 use std::io::Write;
-use std::net::TcpStream;
+use uuid::Uuid;
 
+/// The reply that is written to the client's TCP stream.
+///
+/// If the response carries a value, its type tag and escaped payload are appended after the code.
+/// For example `200 Success|s|localhost|` or `200 Success|s|a\|b|`
+/// Collections carry their element count and elements, like `200 Success|list|2|i8|1|s|on|`
+/// The uuid of the item follows the value if it is asked, like `200 Success|s|on|936da01f-...|`
+/// Multi-key commands carry the count and the code number and value of every key, like `200 Success|2|200|s|on|404|`
+#[derive(Debug, Clone, PartialEq)]
 pub struct Response {
     code: Code,
+    value: Option<Value>,
+    version: Option<Uuid>,
+    results: Vec<Response>,
 }
 
 impl Response {
     pub fn new(code: Code) -> Self {
-        Response { code }
+        Response {
+            code,
+            value: None,
+            version: None,
+            results: Vec::new(),
+        }
     }
 
-    pub fn write(&self, stream: &mut TcpStream) {
-        write!(stream, "{}|\r\n", self.code.to_string());
+    pub fn with_value(code: Code, value: Value) -> Self {
+        Response {
+            code,
+            value: Some(value),
+            version: None,
+            results: Vec::new(),
+        }
+    }
+
+    /// Sets the results of the keys of a multi-key command.
+    pub fn with_results(mut self, results: Vec<Response>) -> Self {
+        self.results = results;
+        self
+    }
+
+    /// Sets the uuid of the item that the value belongs to.
+    pub fn with_version(mut self, version: Option<Uuid>) -> Self {
+        self.version = version;
+        self
+    }
+
+    pub fn code(&self) -> Code {
+        self.code
+    }
+
+    pub fn value(&self) -> Option<&Value> {
+        self.value.as_ref()
+    }
+
+    pub fn version(&self) -> Option<Uuid> {
+        self.version
+    }
+
+    pub fn results(&self) -> &[Response] {
+        &self.results
+    }
+
+    /// Writes the response with a single write call,
+    /// so small replies don't wait for each other on the TCP stream.
+    pub fn write<W: Write>(&self, stream: &mut W) {
+        let mut parts = vec![self.code.to_string()];
+        if let Some(v) = &self.value {
+            parts.extend(value_parts(v));
+        }
+        if let Some(u) = self.version {
+            parts.push(u.to_string());
+        }
+        if !self.results.is_empty() {
+            parts.push(self.results.len().to_string());
+            for r in &self.results {
+                parts.push((r.code as u16).to_string());
+                if let Some(v) = &r.value {
+                    parts.extend(value_parts(v));
+                }
+            }
+        }
+        let reply = format!("{}|\r\n", parts.join("|"));
+        if let Err(e) = stream.write_all(reply.as_bytes()) {
+            error!("Response couldn't write, {}", e);
+        }
+    }
+}
+
+/// Reads the results of a multi-key reply after its code, like `2|200|s|on|404|`.
+///
+/// Every result is a code number and the value if it carries one. Type tags are never numbers, so they are told apart.
+pub fn parse_results(parts: &[String]) -> Result<Vec<Response>, MessageParseError> {
+    let number = |p: &String| p.bytes().all(|b| b.is_ascii_digit());
+    let (count, mut rest) = parts.split_first().ok_or(MessageParseError::Pattern)?;
+    let count: u16 = count.parse().map_err(|_| MessageParseError::Pattern)?;
+    let mut results = Vec::with_capacity(count as usize);
+    for _ in 0..count {
+        let (code, after) = rest.split_first().ok_or(MessageParseError::Pattern)?;
+        let code = code
+            .parse::<u16>()
+            .ok()
+            .and_then(|n| Code::try_from(n).ok())
+            .ok_or(MessageParseError::Pattern)?;
+        rest = after;
+        match rest.first() {
+            Some(tag) if !number(tag) => {
+                let (value, after) = next_value(rest, true)?;
+                results.push(Response::with_value(code, value));
+                rest = after;
+            }
+            _ => results.push(Response::new(code)),
+        }
+    }
+    match rest.is_empty() {
+        true => Ok(results),
+        false => Err(MessageParseError::Pattern),
     }
 }
 
-#[derive(Copy, Clone)]
+impl From<InformativeEvent> for Response {
+    fn from(event: InformativeEvent) -> Self {
+        match event {
+            InformativeEvent::Found(item) => {
+                Response::with_value(Code::Success, item.value.clone())
+            }
+            InformativeEvent::Added(_)
+            | InformativeEvent::Present
+            | InformativeEvent::Updated(_)
+            | InformativeEvent::Deleted(_) => Response::new(Code::Success),
+            InformativeEvent::KeyExists => Response::new(Code::KeyExists),
+            InformativeEvent::ExpiryChanged(_) => Response::new(Code::Success),
+            InformativeEvent::TimeToLive(ttl) => {
+                let seconds = match ttl {
+                    Some(t) => i64::try_from(t.as_secs() + u64::from(t.subsec_nanos() > 0))
+                        .unwrap_or(i64::MAX),
+                    None => -1,
+                };
+                Response::with_value(Code::Success, Value::HugeNumber(seconds))
+            }
+            InformativeEvent::Applied(value) => Response::with_value(Code::Success, value),
+            InformativeEvent::WrongType => Response::new(Code::WrongType),
+            InformativeEvent::CollectionFull => Response::new(Code::CollectionFull),
+            InformativeEvent::Overflow => Response::new(Code::Overflow),
+            InformativeEvent::Conflict => Response::new(Code::Conflict),
+            InformativeEvent::NotFound
+            | InformativeEvent::Expired(_)
+            | InformativeEvent::Evicted(_) => Response::new(Code::NotFound),
+            InformativeEvent::AddError
+            | InformativeEvent::GetError
+            | InformativeEvent::DelError => Response::new(Code::Error),
+        }
+    }
+}
+
+impl From<MessageParseError> for Response {
+    fn from(e: MessageParseError) -> Self {
+        let code = match e {
+            MessageParseError::Empty => Code::EmptyMessage,
+            MessageParseError::Encoding => Code::Encoding,
+            MessageParseError::Command => Code::UnknownCommand,
+            MessageParseError::Pattern => Code::Pattern,
+            MessageParseError::KeyNameTooLong => Code::KeyTooLong,
+            MessageParseError::UnknownType(_) => Code::UnknownType,
+            MessageParseError::InvalidValue { .. } => Code::InvalidValue,
+            MessageParseError::ValueOutOfRange { .. } => Code::ValueOutOfRange,
+            MessageParseError::ValueTooLong => Code::ValueTooLong,
+            MessageParseError::Escape => Code::Escape,
+        };
+        Response::new(code)
+    }
+}
+
+impl From<MessageSendError> for Response {
+    fn from(e: MessageSendError) -> Self {
+        match e {
+            MessageSendError::Item(NewItemError::InvalidKeyLen) => Response::new(Code::KeyTooLong),
+            MessageSendError::Item(NewItemError::InvalidValueLen) => {
+                Response::new(Code::ValueTooLong)
+            }
+            MessageSendError::Item(NewItemError::TooManyElements) => {
+                Response::new(Code::CollectionFull)
+            }
+            MessageSendError::Item(
+                NewItemError::NestedCollection
+                | NewItemError::DuplicateElement
+                | NewItemError::NotNumber,
+            ) => Response::new(Code::InvalidValue),
+            _ => Response::new(Code::Error),
+        }
+    }
+}
+
+impl From<SnapshotError> for Response {
+    fn from(e: SnapshotError) -> Self {
+        error!("{}", e);
+        Response::new(Code::Persistence)
+    }
+}
+
+impl From<WriteLogError> for Response {
+    fn from(e: WriteLogError) -> Self {
+        error!("{}", e);
+        Response::new(Code::Persistence)
+    }
+}
+
+/// Status codes of the responses.
+///
+/// 410 and 413 tell that a collection command targets another kind of value or a full collection,
+/// 411 tells that a counter command would overflow the type of the number,
+/// 412 tells that CAS found the item changed since its uuid was read,
+/// 414 tells that an all-or-nothing MSET found a pack without room for its keys.
+/// 4xx codes starting from 420 describe why the incoming message couldn't be parsed.
+#[derive(Debug, Copy, Clone, PartialEq)]
 pub enum Code {
     Success = 200,
     Error = 400,
+    NotFound = 404,
+    KeyExists = 409,
+    WrongType = 410,
+    Overflow = 411,
+    Conflict = 412,
+    CollectionFull = 413,
+    PackFull = 414,
+    EmptyMessage = 420,
+    Encoding = 421,
+    UnknownCommand = 422,
+    Pattern = 423,
+    KeyTooLong = 424,
+    UnknownType = 425,
+    InvalidValue = 426,
+    ValueOutOfRange = 427,
+    ValueTooLong = 428,
+    MessageTooLong = 429,
+    Escape = 430,
+    Persistence = 500,
+    TooManyConnections = 503,
+}
+
+impl TryFrom<u16> for Code {
+    type Error = u16;
+
+    /// Converts the number at the start of a reply back to the code.
+    fn try_from(value: u16) -> Result<Self, u16> {
+        let code = match value {
+            200 => Self::Success,
+            400 => Self::Error,
+            404 => Self::NotFound,
+            409 => Self::KeyExists,
+            410 => Self::WrongType,
+            411 => Self::Overflow,
+            412 => Self::Conflict,
+            413 => Self::CollectionFull,
+            414 => Self::PackFull,
+            420 => Self::EmptyMessage,
+            421 => Self::Encoding,
+            422 => Self::UnknownCommand,
+            423 => Self::Pattern,
+            424 => Self::KeyTooLong,
+            425 => Self::UnknownType,
+            426 => Self::InvalidValue,
+            427 => Self::ValueOutOfRange,
+            428 => Self::ValueTooLong,
+            429 => Self::MessageTooLong,
+            430 => Self::Escape,
+            500 => Self::Persistence,
+            503 => Self::TooManyConnections,
+            _ => return Err(value),
+        };
+        Ok(code)
+    }
 }
 
 impl Display for Code {
@@ -28,6 +289,115 @@ impl Display for Code {
         match self {
             Self::Success => write!(f, "{} Success", c),
             Self::Error => write!(f, "{} Error", c),
+            Self::NotFound => write!(f, "{} NotFound", c),
+            Self::KeyExists => write!(f, "{} KeyExists", c),
+            Self::WrongType => write!(f, "{} WrongType", c),
+            Self::Overflow => write!(f, "{} Overflow", c),
+            Self::Conflict => write!(f, "{} Conflict", c),
+            Self::CollectionFull => write!(f, "{} CollectionFull", c),
+            Self::PackFull => write!(f, "{} PackFull", c),
+            Self::EmptyMessage => write!(f, "{} EmptyMessage", c),
+            Self::Encoding => write!(f, "{} Encoding", c),
+            Self::UnknownCommand => write!(f, "{} UnknownCommand", c),
+            Self::Pattern => write!(f, "{} Pattern", c),
+            Self::KeyTooLong => write!(f, "{} KeyTooLong", c),
+            Self::UnknownType => write!(f, "{} UnknownType", c),
+            Self::InvalidValue => write!(f, "{} InvalidValue", c),
+            Self::ValueOutOfRange => write!(f, "{} ValueOutOfRange", c),
+            Self::ValueTooLong => write!(f, "{} ValueTooLong", c),
+            Self::MessageTooLong => write!(f, "{} MessageTooLong", c),
+            Self::Escape => write!(f, "{} Escape", c),
+            Self::Persistence => write!(f, "{} Persistence", c),
+            Self::TooManyConnections => write!(f, "{} TooManyConnections", c),
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::model::message::split;
+    use crate::Item;
+    use std::sync::Arc;
+
+    #[test]
+    fn should_found_event_write_value_with_type_tag() {
+        let item = Item::new("server".to_string(), Value::Text("localhost".to_string())).unwrap();
+        let mut buffer = Vec::new();
+        Response::from(InformativeEvent::Found(Arc::new(item))).write(&mut buffer);
+        assert_eq!(buffer, b"200 Success|s|localhost|\r\n");
+    }
+
+    #[test]
+    fn should_not_found_event_write_not_found_code() {
+        let mut buffer = Vec::new();
+        Response::from(InformativeEvent::NotFound).write(&mut buffer);
+        assert_eq!(buffer, b"404 NotFound|\r\n");
+    }
+
+    #[test]
+    fn should_parse_errors_write_distinct_codes() {
+        let mut buffer = Vec::new();
+        Response::from(MessageParseError::out_of_range("i8", "999")).write(&mut buffer);
+        assert_eq!(buffer, b"427 ValueOutOfRange|\r\n");
+
+        let mut buffer = Vec::new();
+        Response::from(MessageParseError::UnknownType("u64".to_string())).write(&mut buffer);
+        assert_eq!(buffer, b"425 UnknownType|\r\n");
+
+        let mut buffer = Vec::new();
+        Response::from(MessageSendError::Item(NewItemError::InvalidKeyLen)).write(&mut buffer);
+        assert_eq!(buffer, b"424 KeyTooLong|\r\n");
+
+        let mut buffer = Vec::new();
+        Response::from(MessageParseError::Escape).write(&mut buffer);
+        assert_eq!(buffer, b"430 Escape|\r\n");
+    }
+
+    #[test]
+    fn should_collections_written_element_by_element() {
+        let mut buffer = Vec::new();
+        let list = Value::List(vec![Value::ThinNumber(1), Value::Text("a|b".to_string())]);
+        Response::with_value(Code::Success, list).write(&mut buffer);
+        assert_eq!(buffer, b"200 Success|list|2|i8|1|s|a\\|b|\r\n");
+
+        let mut buffer = Vec::new();
+        let hash = Value::Hash(vec![("on".to_string(), Value::Logical(true))]);
+        Response::from(InformativeEvent::Applied(hash)).write(&mut buffer);
+        assert_eq!(buffer, b"200 Success|hash|1|on|l|true|\r\n");
+
+        let mut buffer = Vec::new();
+        Response::from(InformativeEvent::WrongType).write(&mut buffer);
+        assert_eq!(buffer, b"410 WrongType|\r\n");
+    }
+
+    #[test]
+    fn should_results_survive_write_and_parse() {
+        let results = vec![
+            Response::new(Code::NotFound),
+            Response::with_value(Code::Success, Value::ThinNumber(7)),
+            Response::with_value(Code::Success, Value::Text("1|2".to_string())),
+            Response::new(Code::Success),
+        ];
+        let mut buffer = Vec::new();
+        Response::new(Code::Success)
+            .with_results(results.clone())
+            .write(&mut buffer);
+        assert_eq!(buffer, b"200 Success|4|404|200|i8|7|200|s|1\\|2|200|\r\n");
+        let line = String::from_utf8(buffer).unwrap();
+        let parts = split(line.trim_end()).unwrap();
+        assert_eq!(parse_results(&parts[1..]).unwrap(), results);
+        assert_eq!(
+            parse_results(&["2".to_string(), "200".to_string()]),
+            Err(MessageParseError::Pattern)
+        );
+    }
+
+    #[test]
+    fn should_text_payload_written_escaped() {
+        let mut buffer = Vec::new();
+        Response::with_value(Code::Success, Value::Text("a|b\\\r\n".to_string()))
+            .write(&mut buffer);
+        assert_eq!(buffer, b"200 Success|s|a\\|b\\\\\\r\\n|\r\n");
+    }
+}
@@ -0,0 +1,171 @@
+use crate::model::{EvictionPolicy, PackStats};
+use crate::persistence::snapshot::Snapshot;
+use crate::persistence::write_log::WriteLog;
+use crate::server::server::pack_worker;
+use crate::{InformativeEvent, Pack, TransmitterEvent};
+use crossbeam::channel;
+use crossbeam::channel::Sender;
+use log::info;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread;
+
+/// A pack and the channel of the worker thread that owns it.
+#[derive(Clone)]
+pub struct Route {
+    pub pack: Arc<Mutex<Pack>>,
+    pub event: Sender<TransmitterEvent>,
+}
+
+/// Holds all packs of the server and decides which pack keeps a key.
+///
+/// Every pack is owned by its own pack_worker thread.
+/// Keys are distributed by their hash so the same key always goes to the same pack.
+#[derive(Clone)]
+pub struct Router {
+    routes: Vec<Route>,
+    snapshot: Option<Snapshot>,
+    log: Option<Arc<WriteLog>>,
+    /// Lets one all-or-nothing MSET run at a time, so their packs never wait for each other.
+    batch: Arc<Mutex<()>>,
+}
+
+impl Router {
+    /// Creates the packs with their worker threads.
+    ///
+    /// Pack ids start from 1. All packs use the same eviction policy.
+    pub fn new(count: u32, policy: EvictionPolicy, informative: &Sender<InformativeEvent>) -> Self {
+        Self::build(count, policy, informative, None)
+    }
+
+    /// Creates the packs with their worker threads that append every accepted change to the write log.
+    pub fn logged(
+        count: u32,
+        policy: EvictionPolicy,
+        informative: &Sender<InformativeEvent>,
+        log: Arc<WriteLog>,
+    ) -> Self {
+        Self::build(count, policy, informative, Some(log))
+    }
+
+    fn build(
+        count: u32,
+        policy: EvictionPolicy,
+        informative: &Sender<InformativeEvent>,
+        log: Option<Arc<WriteLog>>,
+    ) -> Self {
+        let routes = (1..=count.max(1))
+            .map(|id| {
+                let (event, events) = channel::unbounded();
+                let pack = Pack {
+                    id,
+                    policy,
+                    ..Default::default()
+                };
+                info!("Pack #{} initialized", &pack.id);
+                let (informative, log) = (informative.clone(), log.clone());
+                thread::spawn(move || pack_worker(events, informative, log));
+                Route {
+                    pack: Arc::new(Mutex::new(pack)),
+                    event,
+                }
+            })
+            .collect();
+        Router {
+            routes,
+            snapshot: None,
+            log,
+            batch: Arc::new(Mutex::new(())),
+        }
+    }
+
+    /// Sets the snapshot that the SAV command writes to.
+    pub fn with_snapshot(mut self, snapshot: Snapshot) -> Self {
+        self.snapshot = Some(snapshot);
+        self
+    }
+
+    /// Returns the snapshot of the packs if it is enabled.
+    pub fn snapshot(&self) -> Option<&Snapshot> {
+        self.snapshot.as_ref()
+    }
+
+    /// Returns the write log of the packs if it is enabled.
+    pub fn log(&self) -> Option<&WriteLog> {
+        self.log.as_deref()
+    }
+
+    /// Returns the route of the pack that keeps the key.
+    pub fn route(&self, key: &str) -> &Route {
+        &self.routes[self.position(key)]
+    }
+
+    /// Returns the position of the pack that keeps the key in the routes.
+    pub fn position(&self, key: &str) -> usize {
+        slot(key, self.routes.len())
+    }
+
+    /// Waits until no other all-or-nothing MSET runs.
+    pub fn lock_batch(&self) -> MutexGuard<'_, ()> {
+        self.batch.lock().unwrap()
+    }
+
+    /// Returns the routes of all packs.
+    pub fn routes(&self) -> &[Route] {
+        &self.routes
+    }
+
+    /// Returns the statistics of every pack.
+    pub fn stats(&self) -> Vec<PackStats> {
+        self.routes
+            .iter()
+            .map(|r| r.pack.lock().unwrap().stats())
+            .collect()
+    }
+}
+
+/// Returns the index of the pack for the key by using the FNV-1a hash.
+///
+/// The hash doesn't depend on the process, so a key is placed in the same pack after restarts.
+fn slot(key: &str, count: usize) -> usize {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for b in key.as_bytes() {
+        hash ^= *b as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    (hash % count as u64) as usize
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn should_same_key_routed_to_same_pack() {
+        let (informative, _receiver) = channel::unbounded();
+        let router = Router::new(16, EvictionPolicy::Reject, &informative);
+        let first = router.route("ServerName").pack.lock().unwrap().id;
+        let second = router.route("ServerName").pack.lock().unwrap().id;
+        assert_eq!(first, second);
+        assert_eq!(slot("ServerName", 16), slot("ServerName", 16));
+    }
+
+    #[test]
+    fn should_keys_spread_over_packs() {
+        let mut used = [false; 16];
+        for i in 0..1000 {
+            used[slot(&format!("key{}", i), 16)] = true;
+        }
+        assert!(used.iter().all(|u| *u));
+    }
+
+    #[test]
+    fn should_stats_report_every_pack() {
+        let (informative, _receiver) = channel::unbounded();
+        let router = Router::new(4, EvictionPolicy::Reject, &informative);
+        let stats = router.stats();
+        assert_eq!(stats.len(), 4);
+        assert_eq!(stats[0].id, 1);
+        assert_eq!(stats[3].id, 4);
+        assert!(stats.iter().all(|s| s.items == 0));
+    }
+}
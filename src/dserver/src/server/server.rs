@@ -1,24 +1,103 @@
-use crate::model::Message;
+use crate::constant::constant::{
+    BASIC_PACK_COUNT, DEFAULT_MAX_CONNECTIONS, DEFAULT_WORKER_COUNT, MAX_MESSAGE_LEN,
+    REAPER_INTERVAL_MS,
+};
+use crate::derror::message_send_error::MessageSendError;
+use crate::derror::operation_error::OperationError;
+use crate::derror::server_error::ServerError;
+use crate::derror::snapshot_error::SnapshotError;
+use crate::derror::write_log_error::WriteLogError;
+use crate::model::{AddMode, Batch, Change, Command, EvictionPolicy, Item, Message, PackState};
+use crate::persistence::fsync_policy::FsyncPolicy;
+use crate::persistence::snapshot::{snapshotter, Snapshot};
+use crate::persistence::write_log::{syncer, Record, WriteLog};
+use crate::server::connection::{Connection, Frame};
+use crate::server::pool::ConnectionPool;
+use crate::server::reaper::reaper;
 use crate::server::response::{Code, Response};
-use crate::{InformativeEvent, Pack, TransmitterEvent};
+use crate::server::router::Router;
+use crate::server::{binary, resp};
+use crate::{InformativeEvent, TransmitterEvent, Value};
 use crossbeam::channel;
 use crossbeam::channel::{Receiver, Sender};
 use log::{error, info, warn};
-use std::io::Read;
-use std::net::TcpListener;
-use std::sync::{Arc, Mutex};
+use std::net::{Ipv4Addr, SocketAddr, TcpListener, TcpStream};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 use std::thread;
+use std::thread::JoinHandle;
+use std::time::{Duration, SystemTime};
 
 /// It holds the basic information for the TCP server.
 pub struct Server<'a> {
     root: &'a str,
     port: u16,
+    packs: u32,
+    policy: EvictionPolicy,
+    workers: usize,
+    max_connections: usize,
+    snapshot: Option<(PathBuf, Duration)>,
+    log: Option<(PathBuf, FsyncPolicy)>,
 }
 
 impl<'a> Server<'a> {
     /// Creates a new TCP Server object.
     pub fn new(root: &'a str, port: u16) -> Self {
-        Server { root, port }
+        Server {
+            root,
+            port,
+            packs: BASIC_PACK_COUNT,
+            policy: EvictionPolicy::default(),
+            workers: DEFAULT_WORKER_COUNT,
+            max_connections: DEFAULT_MAX_CONNECTIONS,
+            snapshot: None,
+            log: None,
+        }
+    }
+
+    /// Sets the number of packs. Every pack is owned by its own worker thread.
+    pub fn packs(mut self, count: u32) -> Self {
+        self.packs = count.max(1);
+        self
+    }
+
+    /// Sets the policy that chooses the item to evict when a pack is full.
+    pub fn eviction(mut self, policy: EvictionPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Sets the number of threads that serve client connections.
+    /// A client that arrives while all of them are busy waits until one of them is free.
+    pub fn workers(mut self, count: usize) -> Self {
+        self.workers = count.max(1);
+        self
+    }
+
+    /// Sets the number of connections that can be served or wait for a thread at the same time.
+    /// Clients over this limit are rejected.
+    pub fn max_connections(mut self, count: usize) -> Self {
+        self.max_connections = count.max(1);
+        self
+    }
+
+    /// Enables snapshots of the packs.
+    ///
+    /// The snapshot file is saved at every interval or with the SAV command.
+    /// It is loaded when the server starts, unless a write log that holds the whole state is enabled.
+    pub fn snapshot<P: Into<PathBuf>>(mut self, path: P, interval: Duration) -> Self {
+        self.snapshot = Some((path.into(), interval));
+        self
+    }
+
+    /// Enables the append-only write log of the packs.
+    ///
+    /// The log is replayed when the server starts. A new log starts with the items of the snapshot,
+    /// so an existing log is the whole state and the snapshot isn't loaded. RWL command compacts it.
+    pub fn write_log<P: Into<PathBuf>>(mut self, path: P, policy: FsyncPolicy) -> Self {
+        self.log = Some((path.into(), policy));
+        self
     }
 
     /// Returns the server address:port information.
@@ -29,78 +108,108 @@ impl<'a> Server<'a> {
     /// It starts the TCP server and listens for incoming requests.
     /// It leaves the necessary message to the channel according to the suitability of the requests.
     /// Using these messages, it adds, reads, and deletes objects in the packets.
-    pub fn run(self) {
-        let (event_transmitter, event_receiver) = channel::unbounded();
+    ///
+    /// The server runs on its own threads, the returned handle stops it.
+    /// Port 0 binds an ephemeral port, the handle tells which one.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use dserver::Server;
+    /// use std::io::{BufRead, BufReader, Write};
+    /// use std::net::TcpStream;
+    ///
+    /// let handle = Server::new("127.0.0.1", 0).start().unwrap();
+    /// let mut stream = TcpStream::connect(handle.local_addr()).unwrap();
+    /// stream.write_all(b"ADD|server|s|london|\n").unwrap();
+    /// let mut reply = String::new();
+    /// BufReader::new(stream).read_line(&mut reply).unwrap();
+    /// assert_eq!(reply, "200 Success|\r\n");
+    /// handle.stop();
+    /// ```
+    pub fn start(self) -> Result<ServerHandle, ServerError> {
         let (informative_transmitter, informative_receiver) = channel::unbounded();
+        let (shutdown, shutdown_receiver) = channel::bounded::<()>(0);
 
-        let pack = Pack {
-            id: 1,
-            ..Default::default()
+        let mut router = match &self.log {
+            Some((path, policy)) => Router::logged(
+                self.packs,
+                self.policy,
+                &informative_transmitter,
+                Arc::new(WriteLog::open(path, *policy)?),
+            ),
+            None => Router::new(self.packs, self.policy, &informative_transmitter),
         };
-        info!("Pack #{} initialized", &pack.id);
-        let pack_ref = Arc::new(Mutex::new(pack));
+        if let Some((path, interval)) = &self.snapshot {
+            let snapshot = Snapshot::new(path);
+            match router.log() {
+                // A write log holds every change since it was created, the snapshot may still hold deleted keys.
+                Some(log) if !log.is_new() => info!("Write log takes the place of the snapshot"),
+                _ => {
+                    snapshot.restore(&router)?;
+                }
+            }
+            router = router.with_snapshot(snapshot.clone());
+            let (snapshot_router, interval) = (router.clone(), *interval);
+            let shutdown = shutdown_receiver.clone();
+            let _ =
+                thread::spawn(move || snapshotter(snapshot, snapshot_router, interval, shutdown));
+        }
+        if let Some(log) = router.log() {
+            if log.is_new() {
+                // The items of the snapshot are written to the new log, so it holds the whole state from now on.
+                log.rewrite(&router)?;
+            } else {
+                log.replay(&router)?;
+            }
+            if log.policy() == FsyncPolicy::EverySecond {
+                let (syncer_router, shutdown) = (router.clone(), shutdown_receiver.clone());
+                let _ = thread::spawn(move || {
+                    if let Some(l) = syncer_router.log() {
+                        syncer(l, shutdown)
+                    }
+                });
+            }
+        }
 
-        let _ = thread::spawn(|| pack_worker(event_receiver, informative_transmitter));
+        let listener = TcpListener::bind(self.address())?;
+        let address = listener.local_addr()?;
+        let (reaper_router, reaper_informative) = (router.clone(), informative_transmitter);
+        let _ = thread::spawn(move || {
+            reaper(
+                reaper_router,
+                reaper_informative,
+                Duration::from_millis(REAPER_INTERVAL_MS),
+                shutdown_receiver,
+            )
+        });
         let _ = thread::spawn(|| {
             for info in informative_receiver {
                 info!("\t{:?}", info);
-                match info {
-                    InformativeEvent::Found(v) => {
-                        //TODO How can I write this value to Tcp Stream
-                        info!("Item Founded, {:?}", v);
-                    }
-                    _ => {}
-                }
             }
         });
-        info!("{} is running", self.address());
+        info!("{} is running", address);
 
-        let listener = TcpListener::bind(&self.address());
-        match listener {
-            Ok(l) => {
+        let stopped = Arc::new(AtomicBool::new(false));
+        let pool = ConnectionPool::new(self.workers, self.max_connections, &router);
+        let acceptor = {
+            let stopped = stopped.clone();
+            thread::spawn(move || accept(listener, pool, stopped))
+        };
+        Ok(ServerHandle {
+            address,
+            stopped,
+            shutdown: Some(shutdown),
+            acceptor: Some(acceptor),
+        })
+    }
+
+    /// Starts the server and blocks the current thread while it is running.
+    pub fn run(self) {
+        match self.start() {
+            Ok(handle) => {
                 info!("Server started.");
-                loop {
-                    match l.accept() {
-                        Ok((mut stream, addrees)) => {
-                            info!("Connected client is {:?}", addrees);
-                            let mut buffer = [0_u8; 512];
-                            match stream.read(&mut buffer) {
-                                Ok(l) => {
-                                    let msg = String::from_utf8(buffer[0..l].to_vec());
-                                    info!("Request, {:?}", msg.unwrap());
-                                    let message = Message::try_from(&buffer[0..l]);
-                                    match message {
-                                        Ok(m) => {
-                                            info!("{:?}", m);
-                                            //TODO If there is a key being searched, how do I send the result to the current client?
-                                            // I need to move current tcp stream to informative event
-                                            let r = m.send(&pack_ref, &event_transmitter);
-                                            match r {
-                                                Ok(_) => {
-                                                    Response::new(Code::Success).write(&mut stream);
-                                                }
-                                                Err(e) => {
-                                                    error!("{:?}", e);
-                                                }
-                                            }
-                                        }
-                                        Err(e) => {
-                                            error!("Parsing error, {:?}", e);
-                                            Response::new(Code::Error).write(&mut stream);
-                                        }
-                                    }
-                                }
-                                Err(e) => {
-                                    error!("Read error, {}", e);
-                                    Response::new(Code::Error).write(&mut stream);
-                                }
-                            }
-                        }
-                        Err(e) => {
-                            error!("Server error -> {}", e);
-                        }
-                    }
-                }
+                handle.wait();
             }
             Err(e) => {
                 error!("Internal server error-> {}", e);
@@ -109,41 +218,1205 @@ impl<'a> Server<'a> {
     }
 }
 
-pub fn pack_worker(events: Receiver<TransmitterEvent>, informative: Sender<InformativeEvent>) {
+/// Accepts the clients until the server is stopped.
+fn accept(listener: TcpListener, pool: ConnectionPool, stopped: Arc<AtomicBool>) {
+    for stream in listener.incoming() {
+        if stopped.load(Ordering::SeqCst) {
+            break;
+        }
+        match stream {
+            Ok(s) => {
+                info!("Connected client is {:?}", s.peer_addr());
+                pool.dispatch(s);
+            }
+            Err(e) => {
+                error!("Server error -> {}", e);
+            }
+        }
+    }
+    info!("Server stopped.");
+}
+
+/// Controls a server that is started in the process.
+///
+/// Dropping the handle stops the server as well.
+pub struct ServerHandle {
+    address: SocketAddr,
+    stopped: Arc<AtomicBool>,
+    shutdown: Option<Sender<()>>,
+    acceptor: Option<JoinHandle<()>>,
+}
+
+impl ServerHandle {
+    /// Returns the address that the server listens.
+    pub fn local_addr(&self) -> SocketAddr {
+        self.address
+    }
+
+    /// Stops accepting new clients and the background threads of the server.
+    ///
+    /// Connections that are already accepted are served until the clients leave.
+    pub fn stop(mut self) {
+        self.shutdown();
+    }
+
+    /// Blocks the current thread until the server is stopped.
+    pub fn wait(mut self) {
+        if let Some(a) = self.acceptor.take() {
+            let _ = a.join();
+        }
+    }
+
+    fn shutdown(&mut self) {
+        let acceptor = match self.acceptor.take() {
+            Some(a) => a,
+            None => return,
+        };
+        self.stopped.store(true, Ordering::SeqCst);
+        self.shutdown.take();
+        // The acceptor is blocked on the listener, a connection wakes it up.
+        let mut address = self.address;
+        if address.ip().is_unspecified() {
+            address.set_ip(Ipv4Addr::LOCALHOST.into());
+        }
+        if let Err(e) = TcpStream::connect(address) {
+            warn!("Server couldn't wake up, {}", e);
+            return;
+        }
+        let _ = acceptor.join();
+    }
+}
+
+impl Drop for ServerHandle {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+/// Serves the client over the protocol that it chooses with its first byte.
+///
+/// A client that starts with the magic byte of the binary protocol talks in binary frames,
+/// a client that starts with a RESP array talks RESP like a Redis client,
+/// all other clients talk in the line framed text protocol.
+pub fn handle(stream: TcpStream, router: &Router) {
+    if let Err(e) = stream.set_nodelay(true) {
+        warn!("Nagle's algorithm couldn't be disabled, {}", e);
+    }
+    let mut first = [0_u8; 1];
+    match stream.peek(&mut first) {
+        Ok(0) => info!("Client closed the connection."),
+        Ok(_) if first[0] == binary::MAGIC => binary::serve(stream, router),
+        Ok(_) if first[0] == resp::ARRAY => resp::serve(stream, router),
+        Ok(_) => serve_text(stream, router),
+        Err(e) => error!("Read error, {}", e),
+    }
+}
+
+/// Reads the messages of the client one after another over the same connection
+/// and writes the result of every message back to the same stream.
+///
+/// The connection stays open until the client closes it or sends `QUIT`.
+fn serve_text(mut stream: TcpStream, router: &Router) {
+    let reader = match stream.try_clone() {
+        Ok(s) => s,
+        Err(e) => {
+            error!("Stream couldn't clone, {}", e);
+            Response::new(Code::Error).write(&mut stream);
+            return;
+        }
+    };
+    let mut connection = Connection::new(reader);
+    loop {
+        match connection.next_frame() {
+            Ok(Some(Frame::Message(m))) => {
+                info!("Request, {:?}", String::from_utf8_lossy(&m));
+                process(&m, router).write(&mut stream);
+            }
+            Ok(Some(Frame::Quit)) => {
+                info!("Client quit.");
+                Response::new(Code::Success).write(&mut stream);
+                break;
+            }
+            Ok(Some(Frame::TooLong)) => {
+                error!("Message is longer than {} bytes", MAX_MESSAGE_LEN);
+                Response::new(Code::MessageTooLong).write(&mut stream);
+                break;
+            }
+            Ok(None) => {
+                info!("Client closed the connection.");
+                break;
+            }
+            Err(e) => {
+                error!("Read error, {}", e);
+                Response::new(Code::Error).write(&mut stream);
+                break;
+            }
+        }
+    }
+}
+
+/// Parses a single text message and executes it.
+fn process(message: &[u8], router: &Router) -> Response {
+    match Message::try_from(message) {
+        Ok(m) => execute(m, router),
+        Err(e) => {
+            error!("Parsing error, {:?}", e);
+            Response::from(e)
+        }
+    }
+}
+
+/// Executes the message, the ones about keys are forwarded to the worker of the key's pack
+/// and their result is waited.
+pub fn execute(message: Message, router: &Router) -> Response {
+    match message.command {
+        Command::Inf => {
+            let stats = router.stats();
+            info!("{:?}", stats);
+            let text = stats
+                .iter()
+                .map(|s| s.to_string())
+                .collect::<Vec<String>>()
+                .join(",");
+            Response::with_value(Code::Success, Value::Text(text))
+        }
+        Command::Sav => match router.snapshot().ok_or(SnapshotError::Disabled) {
+            Ok(snapshot) => match snapshot.save(router) {
+                Ok(count) => counted(count),
+                Err(e) => Response::from(e),
+            },
+            Err(e) => Response::from(e),
+        },
+        Command::Rwl => match router.log().ok_or(WriteLogError::Disabled) {
+            Ok(log) => match log.rewrite(router) {
+                Ok(count) => counted(count),
+                Err(e) => Response::from(e),
+            },
+            Err(e) => Response::from(e),
+        },
+        Command::Mset if message.atomic => put_all(message.batch, router),
+        Command::Mget | Command::Mset | Command::Mdel => {
+            info!("{:?}", message);
+            // Every key is sent before any reply is waited for, so the packs work on them together.
+            let replies: Vec<_> = message
+                .batch
+                .into_iter()
+                .map(|m| {
+                    let route = router.route(&m.key);
+                    m.send(&route.pack, &route.event)
+                })
+                .collect();
+            let results = replies
+                .into_iter()
+                .map(|r| match r {
+                    Ok(reply) => receive(&reply),
+                    Err(e) => {
+                        error!("{:?}", e);
+                        Response::from(e)
+                    }
+                })
+                .collect();
+            Response::new(Code::Success).with_results(results)
+        }
+        _ => {
+            info!("{:?}", message);
+            let route = router.route(&message.key);
+            let (command, versioned) = (message.command.clone(), message.versioned);
+            match message.send(&route.pack, &route.event) {
+                Ok(reply) => match reply.recv() {
+                    Ok(info) => versioned_response(&command, versioned, info),
+                    Err(e) => {
+                        error!("Reply couldn't receive, {}", e);
+                        Response::new(Code::Error)
+                    }
+                },
+                Err(e) => {
+                    error!("{:?}", e);
+                    Response::from(e)
+                }
+            }
+        }
+    }
+}
+
+/// Replies the number of items that SAV or RWL wrote.
+fn counted(count: usize) -> Response {
+    match i64::try_from(count) {
+        Ok(n) => Response::with_value(Code::Success, Value::HugeNumber(n)),
+        Err(e) => {
+            error!("Item count couldn't fit in i64, {}", e);
+            Response::new(Code::Error)
+        }
+    }
+}
+
+/// Waits for the result of a key in a multi-key command.
+fn receive(reply: &Receiver<InformativeEvent>) -> Response {
+    match reply.recv() {
+        Ok(info) => Response::from(info),
+        Err(e) => {
+            error!("Reply couldn't receive, {}", e);
+            Response::new(Code::Error)
+        }
+    }
+}
+
+/// Puts the items of an all-or-nothing MSET, none of them is put unless every pack has room for its items.
+///
+/// The packs of the keys tell whether they have room and wait for the decision before they take other events.
+/// The items are appended to the write log as one record while the packs wait, so a crash never keeps a part of them.
+/// The results are in the order of the keys. If a pack has no room, 414 PackFull is replied with
+/// 414 for the keys of the packs without room and 400 for the other keys that weren't put either.
+fn put_all(batch: Vec<Message>, router: &Router) -> Response {
+    let count = batch.len();
+    info!("{:?}", batch);
+    let mut groups: Vec<(usize, Vec<usize>, Vec<Item>)> = Vec::new();
+    for (index, m) in batch.into_iter().enumerate() {
+        let item = m
+            .value
+            .ok_or(MessageSendError::Add)
+            .and_then(|v| Item::new(m.key, v).map_err(MessageSendError::from));
+        let item = match item {
+            Ok(i) => i,
+            Err(e) => return Response::from(e),
+        };
+        let position = router.position(&item.key);
+        match groups.iter_mut().find(|(p, _, _)| *p == position) {
+            Some((_, indexes, items)) => {
+                indexes.push(index);
+                items.push(item);
+            }
+            None => groups.push((position, vec![index], vec![item])),
+        }
+    }
+    let logged: Vec<Item> = match router.log() {
+        Some(_) => groups
+            .iter()
+            .flat_map(|(_, _, i)| i.iter().cloned())
+            .collect(),
+        None => Vec::new(),
+    };
+    let _batch = router.lock_batch();
+    let mut pending = Vec::new();
+    let mut sent = true;
+    for (position, indexes, items) in groups {
+        let route = &router.routes()[position];
+        let (ready, readiness) = channel::bounded(1);
+        let (decide, decision) = channel::bounded(1);
+        let (responder, reply) = channel::unbounded();
+        sent &= route
+            .event
+            .send(TransmitterEvent::PutBatch(Batch {
+                pack: route.pack.clone(),
+                items,
+                ready,
+                decision,
+                responder,
+            }))
+            .is_ok();
+        pending.push((indexes, readiness, decide, reply));
+    }
+    let rooms: Vec<Option<bool>> = pending.iter().map(|(_, r, _, _)| r.recv().ok()).collect();
+    let mut failure = match rooms.iter().all(Option::is_some) && sent {
+        true => None,
+        false => Some(Response::new(Code::Error)),
+    };
+    let fits = rooms.iter().all(|r| *r == Some(true));
+    if let (None, true, Some(log)) = (&failure, fits, router.log()) {
+        if let Err(e) = log.append(&Record::Batch(logged)) {
+            failure = Some(Response::from(e));
+        }
+    }
+    for (_, _, decide, _) in &pending {
+        let _ = decide.send(failure.is_none() && fits);
+    }
+    if let Some(response) = failure {
+        return response;
+    }
+    if !fits {
+        warn!("All-or-nothing MSET is rejected, a pack has no room");
+        let mut results = vec![Response::new(Code::Error); count];
+        for ((indexes, _, _, _), room) in pending.iter().zip(&rooms) {
+            if *room == Some(false) {
+                indexes
+                    .iter()
+                    .for_each(|i| results[*i] = Response::new(Code::PackFull));
+            }
+        }
+        return Response::new(Code::PackFull).with_results(results);
+    }
+    let mut results: Vec<(usize, Response)> = pending
+        .iter()
+        .flat_map(|(indexes, _, _, reply)| indexes.iter().map(|i| (*i, receive(reply))))
+        .collect();
+    results.sort_by_key(|(i, _)| *i);
+    Response::new(Code::Success).with_results(results.into_iter().map(|(_, r)| r).collect())
+}
+
+/// Turns the result into a response, GET replies the uuid of the item if it is asked
+/// and CAS replies the new uuid of the item.
+fn versioned_response(command: &Command, versioned: bool, event: InformativeEvent) -> Response {
+    match (command, event) {
+        (Command::Get, InformativeEvent::Found(item)) if versioned => {
+            Response::with_value(Code::Success, item.value.clone()).with_version(Some(item.uuid))
+        }
+        (Command::Cas, InformativeEvent::Updated(id)) => {
+            Response::with_value(Code::Success, Value::Uuid(id))
+        }
+        (_, event) => Response::from(event),
+    }
+}
+
+/// Sends the result of an event to the requester and to the informative channel.
+///
+/// Returns false if the informative channel is no longer available.
+fn notify(
+    responder: &Sender<InformativeEvent>,
+    informative: &Sender<InformativeEvent>,
+    event: InformativeEvent,
+) -> bool {
+    if responder.send(event.clone()).is_err() {
+        warn!("Requester is gone before {:?} reply", event);
+    }
+    informative.send(event).is_ok()
+}
+
+/// Records the item that the pack accepted and returns the event of the result.
+///
+/// None is returned if the informative channel is no longer available.
+fn put_result(
+    state: Option<PackState>,
+    object: Item,
+    log: &Option<Arc<WriteLog>>,
+    informative: &Sender<InformativeEvent>,
+) -> Option<InformativeEvent> {
+    let result = match state {
+        Some(PackState::Added(id)) => {
+            info!("Item {} added to pack.", object);
+            record(log, Record::Put(object));
+            InformativeEvent::Added(id)
+        }
+        Some(PackState::Updated(id)) => {
+            info!("Item {} updated in pack.", object);
+            record(log, Record::Put(object));
+            InformativeEvent::Updated(id)
+        }
+        Some(PackState::Evicted { added, key }) => {
+            info!("Item {} added to pack.", object);
+            record(log, Record::Remove(key.clone()));
+            record(log, Record::Put(object));
+            informative.send(InformativeEvent::Evicted(key)).ok()?;
+            InformativeEvent::Added(added)
+        }
+        Some(PackState::KeyExists) => InformativeEvent::KeyExists,
+        Some(PackState::KeyMissing) => InformativeEvent::NotFound,
+        Some(PackState::Conflict) => InformativeEvent::Conflict,
+        _ => InformativeEvent::AddError,
+    };
+    Some(result)
+}
+
+/// Appends the change to the write log if it is enabled.
+fn record(log: &Option<Arc<WriteLog>>, record: Record) {
+    if let Some(l) = log {
+        if let Err(e) = l.append(&record) {
+            error!("{:?} couldn't write to log, {}", record, e);
+        }
+    }
+}
+
+/// Applies the events of a pack one after another and replies the result to the requester.
+///
+/// Accepted changes are appended to the write log before the reply.
+pub fn pack_worker(
+    events: Receiver<TransmitterEvent>,
+    informative: Sender<InformativeEvent>,
+    log: Option<Arc<WriteLog>>,
+) {
     for event in events {
         match event {
             TransmitterEvent::AddNewItem(c) => {
-                c.pack.lock().unwrap().add(c.object.clone());
-                info!("Item {} added to pack.", c.object);
-                if informative
-                    .send(InformativeEvent::Added(c.object.clone().uuid))
-                    .is_err()
-                {
+                let state = c.pack.lock().unwrap().put(c.object.clone(), c.mode);
+                let result = match put_result(state, c.object, &log, &informative) {
+                    Some(r) => r,
+                    None => break,
+                };
+                if !notify(&c.responder, &informative, result) {
                     error!("{:?}", InformativeEvent::AddError);
                     break;
                 }
             }
+            TransmitterEvent::PutBatch(b) => {
+                let keys: Vec<String> = b.items.iter().map(|i| i.key.clone()).collect();
+                let fits = b.pack.lock().unwrap().fits(&keys);
+                // The worker owns the pack, so the room is still there when the decision comes.
+                if b.ready.send(fits).is_err() || !fits || b.decision.recv() != Ok(true) {
+                    continue;
+                }
+                for item in b.items {
+                    let state = b.pack.lock().unwrap().put(item.clone(), AddMode::Upsert);
+                    if let Some(PackState::Evicted { key, .. }) = &state {
+                        record(&log, Record::Remove(key.clone()));
+                    }
+                    // The items are already in the write log as the record of the whole MSET.
+                    let result = match put_result(state, item, &None, &informative) {
+                        Some(r) => r,
+                        None => return,
+                    };
+                    if !notify(&b.responder, &informative, result) {
+                        error!("{:?}", InformativeEvent::AddError);
+                        return;
+                    }
+                }
+            }
             TransmitterEvent::GetItem(s) => {
-                let pack = s.pack.lock().unwrap();
-                info!("{:?}", pack);
+                let mut pack = s.pack.lock().unwrap();
                 let item = pack.get(s.key);
                 info!("{:?}", item);
-                match item {
+                let result = match item {
                     Some(o) => {
-                        info!("{} founded.", o.to_string());
-                        if informative
-                            .send(InformativeEvent::Found(Arc::new(o.clone())))
-                            .is_err()
-                        {
-                            error!("{:?}", InformativeEvent::GetError);
-                            break;
-                        }
+                        info!("{} founded.", o);
+                        InformativeEvent::Found(Arc::new(o.clone()))
                     }
                     None => {
                         warn!("{:?}", InformativeEvent::NotFound);
+                        InformativeEvent::NotFound
+                    }
+                };
+                if !notify(&s.responder, &informative, result) {
+                    error!("{:?}", InformativeEvent::GetError);
+                    break;
+                }
+            }
+            TransmitterEvent::DeleteItem(s) => {
+                let removed = s.pack.lock().unwrap().remove(s.key);
+                let result = match removed {
+                    Some(o) => {
+                        info!("Item {} deleted from pack.", o);
+                        record(&log, Record::Remove(o.key));
+                        InformativeEvent::Deleted(o.uuid)
                     }
+                    None => {
+                        warn!("{:?}", InformativeEvent::NotFound);
+                        InformativeEvent::NotFound
+                    }
+                };
+                if !notify(&s.responder, &informative, result) {
+                    error!("{:?}", InformativeEvent::DelError);
+                    break;
+                }
+            }
+            TransmitterEvent::ExpireItem(e) => {
+                let mut pack = e.pack.lock().unwrap();
+                let changed = pack
+                    .expire(e.key.clone(), e.ttl)
+                    .and_then(|_| pack.index.get(&e.key).map(|p| pack.items[*p].clone()));
+                drop(pack);
+                let result = match changed {
+                    Some(item) => {
+                        let id = item.uuid;
+                        record(&log, Record::Put(item));
+                        InformativeEvent::ExpiryChanged(id)
+                    }
+                    None => InformativeEvent::NotFound,
+                };
+                if !notify(&e.responder, &informative, result) {
+                    break;
+                }
+            }
+            TransmitterEvent::GetTtl(s) => {
+                let mut pack = s.pack.lock().unwrap();
+                let result = match pack.get(s.key) {
+                    Some(o) => InformativeEvent::TimeToLive(o.time_to_live(SystemTime::now())),
+                    None => InformativeEvent::NotFound,
+                };
+                if !notify(&s.responder, &informative, result) {
+                    break;
+                }
+            }
+            TransmitterEvent::CheckItem(s) => {
+                let result = match s.pack.lock().unwrap().contains(&s.key) {
+                    true => InformativeEvent::Present,
+                    false => InformativeEvent::NotFound,
+                };
+                if !notify(&s.responder, &informative, result) {
+                    break;
+                }
+            }
+            TransmitterEvent::UpdateItem(u) => {
+                let applied = u.pack.lock().unwrap().apply(u.key, &u.operation);
+                let result = match applied {
+                    Ok(a) => {
+                        if let Some(key) = a.evicted {
+                            record(&log, Record::Remove(key.clone()));
+                            if informative.send(InformativeEvent::Evicted(key)).is_err() {
+                                break;
+                            }
+                        }
+                        match a.change {
+                            Change::Put(item) => record(&log, Record::Put(item)),
+                            Change::Removed(key) => record(&log, Record::Remove(key)),
+                            Change::Unchanged => {}
+                        }
+                        InformativeEvent::Applied(a.reply)
+                    }
+                    Err(OperationError::NotFound) => InformativeEvent::NotFound,
+                    Err(OperationError::WrongType) => InformativeEvent::WrongType,
+                    Err(OperationError::CollectionFull) => InformativeEvent::CollectionFull,
+                    Err(OperationError::Overflow) => InformativeEvent::Overflow,
+                    Err(e) => {
+                        warn!("{:?} couldn't apply, {}", u.operation, e);
+                        InformativeEvent::AddError
+                    }
+                };
+                if !notify(&u.responder, &informative, result) {
+                    break;
                 }
             }
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::constant::constant::{ADVANCED_PACK_COUNT, MAX_ITEM, MAX_TTL_SECS};
+    use crate::model::EvictionPolicy;
+    use crate::Pack;
+    use std::io::{Read, Write};
+    use std::sync::Mutex;
+
+    #[test]
+    fn should_get_result_routed_back_to_requester() {
+        let (event_transmitter, event_receiver) = channel::unbounded();
+        let (informative_transmitter, _informative_receiver) = channel::unbounded();
+        let pack_ref = Arc::new(Mutex::new(Pack::default()));
+        thread::spawn(|| pack_worker(event_receiver, informative_transmitter, None));
+
+        let add = Message::try_from("ADD|Logs|l|true|".as_bytes()).unwrap();
+        let reply = add.send(&pack_ref, &event_transmitter).unwrap();
+        assert!(matches!(reply.recv().unwrap(), InformativeEvent::Added(_)));
+
+        let get = Message::try_from("GET|Logs|".as_bytes()).unwrap();
+        let reply = get.send(&pack_ref, &event_transmitter).unwrap();
+        match reply.recv().unwrap() {
+            InformativeEvent::Found(item) => assert_eq!(item.value, Value::Logical(true)),
+            e => panic!("Unexpected event {:?}", e),
+        }
+
+        let get = Message::try_from("GET|Debug|".as_bytes()).unwrap();
+        let reply = get.send(&pack_ref, &event_transmitter).unwrap();
+        assert!(matches!(reply.recv().unwrap(), InformativeEvent::NotFound));
+    }
+
+    #[test]
+    fn should_text_value_returned_as_it_was_added() {
+        let (event_transmitter, event_receiver) = channel::unbounded();
+        let (informative_transmitter, _informative_receiver) = channel::unbounded();
+        let pack_ref = Arc::new(Mutex::new(Pack::default()));
+        thread::spawn(|| pack_worker(event_receiver, informative_transmitter, None));
+
+        let add = Message::try_from("ADD|ServerName|s|localhost|".as_bytes()).unwrap();
+        let reply = add.send(&pack_ref, &event_transmitter).unwrap();
+        assert!(matches!(reply.recv().unwrap(), InformativeEvent::Added(_)));
+
+        let get = Message::try_from("GET|ServerName|".as_bytes()).unwrap();
+        let reply = get.send(&pack_ref, &event_transmitter).unwrap();
+        let mut buffer = Vec::new();
+        Response::from(reply.recv().unwrap()).write(&mut buffer);
+        assert_eq!(buffer, b"200 Success|s|localhost|\r\n");
+    }
+
+    #[test]
+    fn should_many_messages_processed_over_one_connection() {
+        let (informative_transmitter, _informative_receiver) = channel::unbounded();
+        let router = Router::new(1, EvictionPolicy::Reject, &informative_transmitter);
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            handle(stream, &router);
+        });
+
+        let mut client = TcpStream::connect(address).unwrap();
+        client
+            .write_all(b"ADD|ServerName|s|localhost|\r\nGET|Server")
+            .unwrap();
+        client.write_all(b"Name|\nGET|Debug|\nQUIT\n").unwrap();
+        let mut replies = String::new();
+        client.read_to_string(&mut replies).unwrap();
+        assert_eq!(
+            replies,
+            "200 Success|\r\n200 Success|s|localhost|\r\n404 NotFound|\r\n200 Success|\r\n"
+        );
+    }
+
+    #[test]
+    fn should_del_removes_item_and_reports_missing_key() {
+        let (event_transmitter, event_receiver) = channel::unbounded();
+        let (informative_transmitter, _informative_receiver) = channel::unbounded();
+        let pack_ref = Arc::new(Mutex::new(Pack::default()));
+        thread::spawn(|| pack_worker(event_receiver, informative_transmitter, None));
+
+        let add = Message::try_from("ADD|Logs|l|true|".as_bytes()).unwrap();
+        let reply = add.send(&pack_ref, &event_transmitter).unwrap();
+        let added = match reply.recv().unwrap() {
+            InformativeEvent::Added(id) => id,
+            e => panic!("Unexpected event {:?}", e),
+        };
+
+        let del = Message::try_from("DEL|Logs|".as_bytes()).unwrap();
+        let reply = del.send(&pack_ref, &event_transmitter).unwrap();
+        let event = reply.recv().unwrap();
+        assert!(matches!(event, InformativeEvent::Deleted(id) if id == added));
+        assert_eq!(Response::from(event), Response::new(Code::Success));
+        assert_eq!(pack_ref.lock().unwrap().get_head(), 0);
+
+        let get = Message::try_from("GET|Logs|".as_bytes()).unwrap();
+        let reply = get.send(&pack_ref, &event_transmitter).unwrap();
+        assert!(matches!(reply.recv().unwrap(), InformativeEvent::NotFound));
+
+        let del = Message::try_from("DEL|Logs|".as_bytes()).unwrap();
+        let reply = del.send(&pack_ref, &event_transmitter).unwrap();
+        let event = reply.recv().unwrap();
+        assert!(matches!(event, InformativeEvent::NotFound));
+        assert_eq!(Response::from(event), Response::new(Code::NotFound));
+    }
+
+    #[test]
+    fn should_keys_processed_by_their_own_packs() {
+        let (informative_transmitter, _informative_receiver) = channel::unbounded();
+        let router = Router::new(
+            ADVANCED_PACK_COUNT,
+            EvictionPolicy::Reject,
+            &informative_transmitter,
+        );
+
+        for i in 0..100 {
+            let message = format!("ADD|key{}|i32|{}|", i, i);
+            assert_eq!(
+                process(message.as_bytes(), &router),
+                Response::new(Code::Success)
+            );
+        }
+        for i in 0..100 {
+            let message = format!("GET|key{}|", i);
+            assert_eq!(
+                process(message.as_bytes(), &router),
+                Response::with_value(Code::Success, Value::LargeNumber(i))
+            );
+        }
+        let stats = router.stats();
+        assert_eq!(stats.len(), 16);
+        assert_eq!(stats.iter().map(|s| s.items).sum::<usize>(), 100);
+        assert!(stats.iter().filter(|s| s.items > 0).count() > 1);
+
+        let response = process(b"INF|", &router);
+        let mut buffer = Vec::new();
+        response.write(&mut buffer);
+        let text = String::from_utf8(buffer).unwrap();
+        assert!(text.starts_with("200 Success|s|#1 "));
+        assert_eq!(text.matches('#').count(), 16);
+    }
+
+    #[test]
+    fn should_add_modes_reply_by_key_presence() {
+        let (informative_transmitter, _informative_receiver) = channel::unbounded();
+        let router = Router::new(1, EvictionPolicy::Reject, &informative_transmitter);
+
+        let response = process(b"AXX|Level|i8|3|", &router);
+        assert_eq!(response, Response::new(Code::NotFound));
+        let response = process(b"ANX|Level|i8|3|", &router);
+        assert_eq!(response, Response::new(Code::Success));
+        let response = process(b"ANX|Level|i8|4|", &router);
+        assert_eq!(response, Response::new(Code::KeyExists));
+        let response = process(b"AXX|Level|i8|5|", &router);
+        assert_eq!(response, Response::new(Code::Success));
+        let response = process(b"ADD|Level|i8|6|", &router);
+        assert_eq!(response, Response::new(Code::Success));
+
+        let response = process(b"GET|Level|", &router);
+        assert_eq!(
+            response,
+            Response::with_value(Code::Success, Value::ThinNumber(6))
+        );
+        assert_eq!(router.stats()[0].items, 1);
+    }
+
+    #[test]
+    fn should_counters_keep_their_type_and_refuse_overflow() {
+        let (informative_transmitter, _informative_receiver) = channel::unbounded();
+        let router = Router::new(1, EvictionPolicy::Reject, &informative_transmitter);
+
+        let response = process(b"INC|hits|", &router);
+        assert_eq!(response, Response::new(Code::NotFound));
+        let response = process(b"INC|hits|new|", &router);
+        assert_eq!(
+            response,
+            Response::with_value(Code::Success, Value::LargeNumber(1))
+        );
+        process(b"ADD|level|u8|250|", &router);
+        let response = process(b"IBY|level|i8|5|", &router);
+        assert_eq!(
+            response,
+            Response::with_value(Code::Success, Value::ThinUnsigned(255))
+        );
+        let response = process(b"INC|level|", &router);
+        assert_eq!(response, Response::new(Code::Overflow));
+        let response = process(b"GET|level|", &router);
+        assert_eq!(
+            response,
+            Response::with_value(Code::Success, Value::ThinUnsigned(255))
+        );
+        process(b"ADD|motd|s|hello|", &router);
+        let response = process(b"DEC|motd|", &router);
+        assert_eq!(response, Response::new(Code::WrongType));
+
+        process(b"ADD|visits|s|5|", &router);
+        let response = process(b"INC|visits|", &router);
+        assert_eq!(response, Response::new(Code::WrongType));
+        let response = process(b"IBY|visits|i64|2|text|", &router);
+        assert_eq!(
+            response,
+            Response::with_value(Code::Success, Value::HugeNumber(7))
+        );
+        let response = process(b"GET|visits|", &router);
+        assert_eq!(
+            response,
+            Response::with_value(Code::Success, Value::Text("7".to_string()))
+        );
+        let response = process(b"DEC|motd|text|", &router);
+        assert_eq!(response, Response::new(Code::WrongType));
+    }
+
+    #[test]
+    fn should_cas_replace_only_the_read_version() {
+        let (informative_transmitter, _informative_receiver) = channel::unbounded();
+        let router = Router::new(1, EvictionPolicy::Reject, &informative_transmitter);
+
+        process(b"ADD|config|s|v1|", &router);
+        let read = process(b"GET|config|uuid|", &router);
+        assert_eq!(read.value(), Some(&Value::Text("v1".to_string())));
+        let version = read.version().unwrap();
+        let response = process(b"GET|config|", &router);
+        assert_eq!(response.version(), None);
+
+        let cas = format!("CAS|config|{}|s|v2|", version);
+        let response = process(cas.as_bytes(), &router);
+        assert_eq!(response.code(), Code::Success);
+        let new_version = match response.value() {
+            Some(Value::Uuid(u)) => *u,
+            v => panic!("CAS replied {:?}", v),
+        };
+        assert_ne!(new_version, version);
+        let response = process(cas.as_bytes(), &router);
+        assert_eq!(response, Response::new(Code::Conflict));
+
+        let response = process(b"GET|config|uuid|", &router);
+        assert_eq!(
+            response,
+            Response::with_value(Code::Success, Value::Text("v2".to_string()))
+                .with_version(Some(new_version))
+        );
+        let response = process(format!("CAS|none|{}|s|v1|", version).as_bytes(), &router);
+        assert_eq!(response, Response::new(Code::NotFound));
+    }
+
+    #[test]
+    fn should_multi_key_commands_reply_per_key() {
+        let (informative_transmitter, _informative_receiver) = channel::unbounded();
+        let router = Router::new(4, EvictionPolicy::Reject, &informative_transmitter);
+
+        let response = process(b"MSET|a|s|on|b|i8|2|c|l|true|", &router);
+        assert_eq!(
+            response,
+            Response::new(Code::Success).with_results(vec![
+                Response::new(Code::Success),
+                Response::new(Code::Success),
+                Response::new(Code::Success),
+            ])
+        );
+        let response = process(b"MGET|b|none|a|", &router);
+        assert_eq!(
+            response.results(),
+            &[
+                Response::with_value(Code::Success, Value::ThinNumber(2)),
+                Response::new(Code::NotFound),
+                Response::with_value(Code::Success, Value::Text("on".to_string())),
+            ]
+        );
+        let response = process(b"MDEL|a|none|", &router);
+        assert_eq!(
+            response.results(),
+            &[Response::new(Code::Success), Response::new(Code::NotFound)]
+        );
+        let mut buffer = Vec::new();
+        process(b"MGET|a|c|", &router).write(&mut buffer);
+        assert_eq!(
+            String::from_utf8(buffer).unwrap(),
+            "200 Success|2|404|200|l|true|\r\n"
+        );
+    }
+
+    #[test]
+    fn should_all_or_nothing_mset_leave_packs_unchanged_without_room() {
+        let (informative_transmitter, _informative_receiver) = channel::unbounded();
+        let router = Router::new(2, EvictionPolicy::Reject, &informative_transmitter);
+        let key_of = |position: usize, from: usize| {
+            (from..)
+                .map(|i| format!("key{}", i))
+                .find(|k| router.position(k) == position)
+                .unwrap()
+        };
+        let mut next = 0;
+        for _ in 0..MAX_ITEM {
+            let key = key_of(0, next);
+            next = key[3..].parse::<usize>().unwrap() + 1;
+            process(format!("ADD|{}|l|true|", key).as_bytes(), &router);
+        }
+        let (full, other, known) = (key_of(0, next), key_of(1, 0), key_of(0, 0));
+
+        let mset = format!("MSET|{}|i8|1|{}|i8|2|all|", other, full);
+        assert_eq!(
+            process(mset.as_bytes(), &router),
+            Response::new(Code::PackFull).with_results(vec![
+                Response::new(Code::Error),
+                Response::new(Code::PackFull),
+            ])
+        );
+        let get = format!("GET|{}|", other);
+        assert_eq!(
+            process(get.as_bytes(), &router),
+            Response::new(Code::NotFound)
+        );
+
+        let mset = format!("MSET|{}|i8|1|{}|i8|2|", other, full);
+        assert_eq!(
+            process(mset.as_bytes(), &router).results(),
+            &[Response::new(Code::Success), Response::new(Code::Error)]
+        );
+        let mset = format!("MSET|{}|i8|3|{}|i8|4|all|", known, other);
+        assert_eq!(
+            process(mset.as_bytes(), &router).results(),
+            &[Response::new(Code::Success), Response::new(Code::Success)]
+        );
+        assert_eq!(
+            process(get.as_bytes(), &router),
+            Response::with_value(Code::Success, Value::ThinNumber(4))
+        );
+    }
+
+    #[test]
+    fn should_ttl_commands_change_item_lifetime() {
+        let (informative_transmitter, _informative_receiver) = channel::unbounded();
+        let router = Router::new(1, EvictionPolicy::Reject, &informative_transmitter);
+
+        let response = process(b"TTL|token|", &router);
+        assert_eq!(response, Response::new(Code::NotFound));
+        process(b"ADD|token|s|abc|60|", &router);
+        let response = process(b"TTL|token|", &router);
+        assert_eq!(
+            response,
+            Response::with_value(Code::Success, Value::HugeNumber(60))
+        );
+
+        let response = process(b"PRS|token|", &router);
+        assert_eq!(response, Response::new(Code::Success));
+        let response = process(b"TTL|token|", &router);
+        assert_eq!(
+            response,
+            Response::with_value(Code::Success, Value::HugeNumber(-1))
+        );
+
+        let response = process(b"EXP|token|5|", &router);
+        assert_eq!(response, Response::new(Code::Success));
+        let response = process(b"TTL|token|", &router);
+        assert_eq!(
+            response,
+            Response::with_value(Code::Success, Value::HugeNumber(5))
+        );
+
+        let response = process(b"EXP|none|5|", &router);
+        assert_eq!(response, Response::new(Code::NotFound));
+
+        let huge = format!("EXP|token|{}|", u64::MAX);
+        let response = process(huge.as_bytes(), &router);
+        assert_eq!(response, Response::new(Code::ValueOutOfRange));
+        let response = process(b"TTL|token|", &router);
+        assert_eq!(
+            response,
+            Response::with_value(Code::Success, Value::HugeNumber(5))
+        );
+        let longest = format!("EXP|token|{}|", MAX_TTL_SECS);
+        process(longest.as_bytes(), &router);
+        let response = process(b"TTL|token|", &router);
+        assert_eq!(
+            response,
+            Response::with_value(Code::Success, Value::HugeNumber(MAX_TTL_SECS as i64))
+        );
+    }
+
+    #[test]
+    fn should_exs_answer_without_a_use_of_the_item() {
+        let (informative_transmitter, informative_receiver) = channel::unbounded();
+        let router = Router::new(1, EvictionPolicy::Lru, &informative_transmitter);
+        let response = process(b"EXS|key0|", &router);
+        assert_eq!(response, Response::new(Code::NotFound));
+        for i in 0..MAX_ITEM {
+            let message = format!("ADD|key{}|l|true|", i);
+            process(message.as_bytes(), &router);
+        }
+        let response = process(b"EXS|key0|", &router);
+        assert_eq!(response, Response::new(Code::Success));
+
+        process(b"ADD|lorem|s|ipsum|", &router);
+        let evicted: Vec<String> = informative_receiver
+            .try_iter()
+            .filter_map(|e| match e {
+                InformativeEvent::Evicted(key) => Some(key),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(evicted, vec!["key0".to_string()]);
+    }
+
+    #[test]
+    fn should_eviction_notified_to_informative_channel() {
+        let (informative_transmitter, informative_receiver) = channel::unbounded();
+        let router = Router::new(1, EvictionPolicy::Fifo, &informative_transmitter);
+        for i in 0..MAX_ITEM {
+            let message = format!("ADD|key{}|l|true|", i);
+            process(message.as_bytes(), &router);
+        }
+        let response = process(b"ADD|lorem|s|ipsum|", &router);
+        assert_eq!(response, Response::new(Code::Success));
+
+        let evicted: Vec<String> = informative_receiver
+            .try_iter()
+            .filter_map(|e| match e {
+                InformativeEvent::Evicted(key) => Some(key),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(evicted, vec!["key0".to_string()]);
+    }
+
+    #[test]
+    fn should_sav_write_snapshot_when_enabled() {
+        let (informative_transmitter, _informative_receiver) = channel::unbounded();
+        let router = Router::new(1, EvictionPolicy::Reject, &informative_transmitter);
+        process(b"ADD|Level|i8|3|", &router);
+        let response = process(b"SAV|", &router);
+        assert_eq!(response, Response::new(Code::Persistence));
+
+        let path = std::env::temp_dir().join(format!("dory-sav-{}.snapshot", std::process::id()));
+        let router = router.with_snapshot(Snapshot::new(&path));
+        let response = process(b"SAV|", &router);
+        assert_eq!(
+            response,
+            Response::with_value(Code::Success, Value::HugeNumber(1))
+        );
+        assert!(path.exists());
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn should_accepted_changes_survive_restart_by_write_log() {
+        let path = std::env::temp_dir().join(format!("dory-worker-{}.wal", std::process::id()));
+        let (informative_transmitter, _informative_receiver) = channel::unbounded();
+        let log = Arc::new(WriteLog::open(&path, FsyncPolicy::Always).unwrap());
+        let router = Router::logged(4, EvictionPolicy::Reject, &informative_transmitter, log);
+        process(b"ADD|Level|i8|3|", &router);
+        process(b"ADD|Logs|l|true|", &router);
+        process(b"ADD|token|s|abc|", &router);
+        process(b"DEL|Logs|", &router);
+        process(b"EXP|token|60|", &router);
+        process(b"ANX|Level|i8|9|", &router);
+        drop(router);
+
+        let log = Arc::new(WriteLog::open(&path, FsyncPolicy::Never).unwrap());
+        let router = Router::logged(2, EvictionPolicy::Reject, &informative_transmitter, log);
+        assert_eq!(router.log().unwrap().replay(&router).unwrap(), 5);
+        assert_eq!(
+            process(b"GET|Level|", &router),
+            Response::with_value(Code::Success, Value::ThinNumber(3))
+        );
+        assert_eq!(
+            process(b"GET|Logs|", &router),
+            Response::new(Code::NotFound)
+        );
+        assert_eq!(
+            process(b"TTL|token|", &router),
+            Response::with_value(Code::Success, Value::HugeNumber(60))
+        );
+
+        assert_eq!(
+            process(b"RWL|", &router),
+            Response::with_value(Code::Success, Value::HugeNumber(2))
+        );
+        let router = Router::new(1, EvictionPolicy::Reject, &informative_transmitter);
+        assert_eq!(process(b"RWL|", &router), Response::new(Code::Persistence));
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn should_all_or_nothing_mset_be_one_record_of_write_log() {
+        let path = std::env::temp_dir().join(format!("dory-mset-{}.wal", std::process::id()));
+        let (informative_transmitter, _informative_receiver) = channel::unbounded();
+        let log = Arc::new(WriteLog::open(&path, FsyncPolicy::Always).unwrap());
+        let router = Router::logged(4, EvictionPolicy::Reject, &informative_transmitter, log);
+        process(b"MSET|a|i8|1|b|i8|2|c|i8|3|all|", &router);
+        process(b"MSET|a|i8|4|d|i8|5|", &router);
+        drop(router);
+
+        let log = Arc::new(WriteLog::open(&path, FsyncPolicy::Never).unwrap());
+        let router = Router::logged(1, EvictionPolicy::Reject, &informative_transmitter, log);
+        assert_eq!(router.log().unwrap().replay(&router).unwrap(), 3);
+        assert_eq!(
+            process(b"MGET|a|b|c|d|", &router).results(),
+            &[
+                Response::with_value(Code::Success, Value::ThinNumber(4)),
+                Response::with_value(Code::Success, Value::ThinNumber(2)),
+                Response::with_value(Code::Success, Value::ThinNumber(3)),
+                Response::with_value(Code::Success, Value::ThinNumber(5)),
+            ]
+        );
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn should_collection_commands_survive_restart_by_write_log() {
+        let path = std::env::temp_dir().join(format!("dory-collection-{}.wal", std::process::id()));
+        let (informative_transmitter, _informative_receiver) = channel::unbounded();
+        let log = Arc::new(WriteLog::open(&path, FsyncPolicy::Always).unwrap());
+        let router = Router::logged(2, EvictionPolicy::Reject, &informative_transmitter, log);
+        assert_eq!(
+            process(b"LPUSH|queue|s|a|", &router),
+            Response::with_value(Code::Success, Value::LargeNumber(1))
+        );
+        process(b"LPUSH|queue|i8|2|", &router);
+        process(b"SADD|tags|s|x|", &router);
+        process(b"SREM|tags|s|x|", &router);
+        process(b"HSET|user|name|s|dory|", &router);
+        assert_eq!(
+            process(b"HGET|user|name|", &router),
+            Response::with_value(Code::Success, Value::Text("dory".to_string()))
+        );
+        assert_eq!(
+            process(b"HGET|queue|name|", &router),
+            Response::new(Code::WrongType)
+        );
+        assert_eq!(
+            process(b"LPOP|none|", &router),
+            Response::new(Code::NotFound)
+        );
+        drop(router);
+
+        let log = Arc::new(WriteLog::open(&path, FsyncPolicy::Never).unwrap());
+        let router = Router::logged(2, EvictionPolicy::Reject, &informative_transmitter, log);
+        router.log().unwrap().replay(&router).unwrap();
+        assert_eq!(
+            process(b"LRANGE|queue|0|-1|", &router),
+            Response::with_value(
+                Code::Success,
+                Value::List(vec![Value::ThinNumber(2), Value::Text("a".to_string())])
+            )
+        );
+        assert_eq!(
+            process(b"SMEMBERS|tags|", &router),
+            Response::new(Code::NotFound)
+        );
+        assert_eq!(
+            process(b"HGETALL|user|", &router),
+            Response::with_value(
+                Code::Success,
+                Value::Hash(vec![("name".to_string(), Value::Text("dory".to_string()))])
+            )
+        );
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn should_deleted_key_stay_deleted_after_rewrite_and_restart() {
+        let directory = std::env::temp_dir();
+        let snapshot = directory.join(format!("dory-stale-{}.snapshot", std::process::id()));
+        let log = directory.join(format!("dory-stale-{}.wal", std::process::id()));
+        let start = || {
+            Server::new("127.0.0.1", 0)
+                .snapshot(&snapshot, Duration::from_secs(3600))
+                .write_log(&log, FsyncPolicy::Always)
+                .start()
+                .unwrap()
+        };
+        let request = |handle: &ServerHandle, message: &[u8]| {
+            let mut stream = TcpStream::connect(handle.local_addr()).unwrap();
+            stream.write_all(message).unwrap();
+            stream.write_all(b"QUIT\n").unwrap();
+            let mut reply = String::new();
+            stream.read_to_string(&mut reply).unwrap();
+            reply
+        };
+
+        let handle = start();
+        request(
+            &handle,
+            b"ADD|kept|l|true|\nADD|gone|l|true|\nSAV|\nDEL|gone|\nRWL|\n",
+        );
+        handle.stop();
+
+        let handle = start();
+        assert_eq!(
+            request(&handle, b"GET|gone|\nGET|kept|\n"),
+            "404 NotFound|\r\n200 Success|l|true|\r\n200 Success|\r\n"
+        );
+        handle.stop();
+        std::fs::remove_file(&log).unwrap();
+
+        // A new write log starts with the items of the snapshot.
+        let handle = start();
+        handle.stop();
+        let handle = start();
+        assert_eq!(
+            request(&handle, b"GET|kept|\n"),
+            "200 Success|l|true|\r\n200 Success|\r\n"
+        );
+        handle.stop();
+        std::fs::remove_file(snapshot).unwrap();
+        std::fs::remove_file(log).unwrap();
+    }
+
+    #[test]
+    fn should_embedded_server_keep_items_over_restart() {
+        let path = std::env::temp_dir().join(format!("dory-embedded-{}.wal", std::process::id()));
+        let request = |handle: &ServerHandle, message: &[u8]| {
+            let mut stream = TcpStream::connect(handle.local_addr()).unwrap();
+            stream.write_all(message).unwrap();
+            stream.write_all(b"QUIT\n").unwrap();
+            let mut reply = String::new();
+            stream.read_to_string(&mut reply).unwrap();
+            reply
+        };
+
+        let handle = Server::new("127.0.0.1", 0)
+            .packs(4)
+            .write_log(&path, FsyncPolicy::Always)
+            .start()
+            .unwrap();
+        assert_ne!(handle.local_addr().port(), 0);
+        let reply = request(&handle, b"ADD|ServerName|s|localhost|\n");
+        assert_eq!(reply, "200 Success|\r\n200 Success|\r\n");
+        let address = handle.local_addr();
+        handle.stop();
+        assert!(TcpStream::connect(address).is_err());
+
+        let handle = Server::new("127.0.0.1", 0)
+            .write_log(&path, FsyncPolicy::Never)
+            .start()
+            .unwrap();
+        let reply = request(&handle, b"GET|ServerName|\n");
+        assert_eq!(reply, "200 Success|s|localhost|\r\n200 Success|\r\n");
+        handle.stop();
+        std::fs::remove_file(path).unwrap();
+    }
+}